@@ -0,0 +1,332 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::ErrorCode;
+use crate::settings;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::Utc;
+use rusqlite::Connection;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+// ロック中も実行できる唯一のコマンド
+pub const UNLOCK_COMMAND: &str = "unlock_app";
+// アイドル時間を超えてロックした際にフロントエンドへ送るイベント
+pub const APP_LOCKED_EVENT: &str = "app_locked";
+
+// 設定のキー（PINのArgon2ハッシュ、自動ロックまでの時間、連続して失敗した回数、次に入力できる時刻）
+const PIN_HASH: &str = "app_lock_pin_hash";
+const IDLE_TIMEOUT_MINUTES: &str = "app_lock_idle_timeout_minutes";
+const FAILED_ATTEMPTS: &str = "app_lock_failed_attempts";
+const RETRY_AT: &str = "app_lock_retry_at";
+
+pub const MIN_PIN_LENGTH: i64 = 4;
+pub const MAX_IDLE_TIMEOUT_MINUTES: i64 = 24 * 60;
+pub const DEFAULT_IDLE_TIMEOUT_MINUTES: i64 = 5;
+
+// この回数までは続けて入力できる。以降は失敗するたびに待ち時間を倍にする
+const FREE_ATTEMPTS: i64 = 5;
+const BASE_DELAY_SECONDS: i64 = 30;
+const MAX_DELAY_SECONDS: i64 = 60 * 60;
+
+// アイドル時間を確認する間隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// PINによるアプリのロック
+///
+/// PINが設定されている場合、起動時と一定時間コマンドが実行されなかった場合にロックし、
+/// unlock_app以外のコマンドを拒否する（lib.rsのinvoke_handlerでcheckを呼び出す）。
+#[derive(Clone, Default)]
+pub struct AppLock {
+    state: Arc<Mutex<LockState>>,
+}
+
+#[derive(Default)]
+struct LockState {
+    // PINが設定されている場合の自動ロックまでの時間
+    idle_timeout: Option<Duration>,
+    locked: bool,
+    last_activity: Option<Instant>,
+    // 次にPINの設定を読み込んだ際にロックする（起動時・プロファイルの切り替え時にデータベースを開けていない場合）
+    pending_lock: bool,
+}
+
+impl AppLock {
+    /// PINの設定を読み込む（lockがtrueの場合とresetの後の初回は、PINが設定されていればロックする）
+    pub fn load(&self, conn: &Connection, lock: bool) -> Result<(), AppError> {
+        let idle_timeout = idle_timeout_minutes(conn)?.map(minutes);
+        let mut state = self.state();
+        state.idle_timeout = idle_timeout;
+        state.locked = (lock || state.pending_lock) && idle_timeout.is_some();
+        state.pending_lock = false;
+        state.last_activity = Some(Instant::now());
+        Ok(())
+    }
+
+    /// PINの設定を忘れ、次に読み込んだ際にロックする
+    ///
+    /// 起動時・プロファイルの切り替え時にデータベースがまだ開かれていない場合（パスフレーズの入力待ちなど）に呼び出す。
+    /// パスフレーズを入力して開いた後も、PINが設定されていればPINの入力を求める。
+    pub fn reset(&self) {
+        *self.state() = LockState {
+            pending_lock: true,
+            ..LockState::default()
+        };
+    }
+
+    /// コマンドの実行前に呼び出す（ロック中はエラーを返し、それ以外は最後に操作した時刻を更新する）
+    pub fn check(&self, command: &str) -> Result<(), AppError> {
+        if command == UNLOCK_COMMAND {
+            return Ok(());
+        }
+
+        let mut state = self.state();
+        state.lock_if_idle();
+        if state.locked {
+            return Err(AppError::AppLocked);
+        }
+        state.last_activity = Some(Instant::now());
+        Ok(())
+    }
+
+    /// PINが設定されていればすぐにロックする
+    pub fn lock(&self) {
+        let mut state = self.state();
+        state.locked = state.idle_timeout.is_some();
+    }
+
+    pub fn unlock(&self) {
+        let mut state = self.state();
+        state.locked = false;
+        state.last_activity = Some(Instant::now());
+    }
+
+    /// 一定時間ごとにアイドル時間を確認し、ロックした場合はon_lockを呼び出す
+    pub fn watch_idle<F>(&self, on_lock: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        let app_lock = self.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(IDLE_CHECK_INTERVAL);
+            if app_lock.state().lock_if_idle() {
+                log::info!("一定時間操作がなかったため、アプリをロックしました");
+                on_lock();
+            }
+        });
+    }
+
+    fn state(&self) -> MutexGuard<'_, LockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl LockState {
+    /// 自動ロックまでの時間を超えていればロックする（新たにロックした場合はtrue）
+    fn lock_if_idle(&mut self) -> bool {
+        let idle = match (self.idle_timeout, self.last_activity) {
+            (Some(timeout), Some(last_activity)) => last_activity.elapsed() >= timeout,
+            _ => false,
+        };
+        if idle && !self.locked {
+            self.locked = true;
+            return true;
+        }
+        false
+    }
+}
+
+fn minutes(minutes: i64) -> Duration {
+    Duration::from_secs(minutes as u64 * 60)
+}
+
+/// 自動ロックまでの時間（分。PINが設定されていない場合はNone）
+pub fn idle_timeout_minutes(conn: &Connection) -> Result<Option<i64>, AppError> {
+    if settings::get_value(conn, PIN_HASH)?.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(
+        settings::get_value(conn, IDLE_TIMEOUT_MINUTES)?
+            .and_then(|value| value.parse().ok())
+            .filter(|minutes| (1..=MAX_IDLE_TIMEOUT_MINUTES).contains(minutes))
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_MINUTES),
+    ))
+}
+
+/// PINを照合する（PINが設定されていない場合は成功とする）
+///
+/// 失敗した回数は設定に保存し、アプリを再起動しても待ち時間が過ぎるまでは照合しない。
+pub fn verify_pin(conn: &Connection, pin: &str) -> Result<(), AppError> {
+    let Some(hash) = settings::get_value(conn, PIN_HASH)? else {
+        return Ok(());
+    };
+
+    let now = Utc::now().timestamp();
+    let retry_at = settings::get_value(conn, RETRY_AT)?.and_then(|value| value.parse::<i64>().ok());
+    if let Some(retry_at) = retry_at.filter(|retry_at| *retry_at > now) {
+        return Err(ErrorDetail::new(ErrorCode::TooManyUnlockAttempts)
+            .param("retry_after_seconds", retry_at - now)
+            .into());
+    }
+
+    if pin_matches(pin, &hash)? {
+        settings::delete_value(conn, FAILED_ATTEMPTS)?;
+        settings::delete_value(conn, RETRY_AT)?;
+        return Ok(());
+    }
+
+    let failed_attempts = settings::get_value(conn, FAILED_ATTEMPTS)?
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(0)
+        + 1;
+    settings::set_value(conn, FAILED_ATTEMPTS, &failed_attempts.to_string())?;
+    log::warn!("PINの照合に失敗しました（{}回目）", failed_attempts);
+
+    if let Some(delay) = retry_delay_seconds(failed_attempts) {
+        settings::set_value(conn, RETRY_AT, &(now + delay).to_string())?;
+        return Err(ErrorDetail::new(ErrorCode::TooManyUnlockAttempts)
+            .param("retry_after_seconds", delay)
+            .into());
+    }
+    Err(ErrorDetail::new(ErrorCode::WrongPin)
+        .param("remaining_attempts", FREE_ATTEMPTS - failed_attempts)
+        .into())
+}
+
+/// 連続して失敗した回数に応じた、次に入力できるまでの待ち時間（秒。待たずに入力できる場合はNone）
+fn retry_delay_seconds(failed_attempts: i64) -> Option<i64> {
+    if failed_attempts < FREE_ATTEMPTS {
+        return None;
+    }
+    let doublings = (failed_attempts - FREE_ATTEMPTS).min(16) as u32;
+    Some((BASE_DELAY_SECONDS << doublings).min(MAX_DELAY_SECONDS))
+}
+
+/// PINと自動ロックまでの時間を設定する（PINは検証済みとする）
+pub fn set_pin(conn: &Connection, pin: &str, idle_timeout_minutes: i64) -> Result<(), AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map_err(|error| AppError::Internal(error.to_string()))?
+        .to_string();
+
+    let tx = conn.unchecked_transaction()?;
+    settings::set_value(&tx, PIN_HASH, &hash)?;
+    settings::set_value(&tx, IDLE_TIMEOUT_MINUTES, &idle_timeout_minutes.to_string())?;
+    settings::delete_value(&tx, FAILED_ATTEMPTS)?;
+    settings::delete_value(&tx, RETRY_AT)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// PINを削除してアプリのロックを無効にする
+pub fn clear_pin(conn: &Connection) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction()?;
+    for key in [PIN_HASH, IDLE_TIMEOUT_MINUTES, FAILED_ATTEMPTS, RETRY_AT] {
+        settings::delete_value(&tx, key)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn pin_matches(pin: &str, hash: &str) -> Result<bool, AppError> {
+    let hash = PasswordHash::new(hash).map_err(|error| AppError::Internal(error.to_string()))?;
+    Ok(Argon2::default().verify_password(pin.as_bytes(), &hash).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn error_code(result: Result<(), AppError>) -> ErrorCode {
+        result.unwrap_err().detail().code
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        for failed_attempts in 0..FREE_ATTEMPTS {
+            assert_eq!(retry_delay_seconds(failed_attempts), None);
+        }
+        assert_eq!(retry_delay_seconds(5), Some(30));
+        assert_eq!(retry_delay_seconds(6), Some(60));
+        assert_eq!(retry_delay_seconds(7), Some(120));
+        assert_eq!(retry_delay_seconds(11), Some(1920));
+        assert_eq!(retry_delay_seconds(12), Some(MAX_DELAY_SECONDS));
+        // 桁あふれせずに上限のまま
+        assert_eq!(retry_delay_seconds(1000), Some(MAX_DELAY_SECONDS));
+    }
+
+    #[test]
+    fn successful_unlock_resets_the_counter() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        set_pin(conn, "1234", DEFAULT_IDLE_TIMEOUT_MINUTES).unwrap();
+
+        for _ in 0..FREE_ATTEMPTS - 1 {
+            assert_eq!(error_code(verify_pin(conn, "0000")), ErrorCode::WrongPin);
+        }
+        verify_pin(conn, "1234").unwrap();
+        assert_eq!(settings::get_value(conn, FAILED_ATTEMPTS).unwrap(), None);
+        assert_eq!(settings::get_value(conn, RETRY_AT).unwrap(), None);
+
+        // 数え直すため、再び失敗しても待ち時間にはならない
+        let detail = verify_pin(conn, "0000").unwrap_err().detail();
+        assert_eq!(detail.code, ErrorCode::WrongPin);
+        assert_eq!(detail.params["remaining_attempts"], FREE_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn too_many_attempts_blocks_even_the_right_pin() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        set_pin(conn, "1234", DEFAULT_IDLE_TIMEOUT_MINUTES).unwrap();
+
+        for _ in 0..FREE_ATTEMPTS - 1 {
+            verify_pin(conn, "0000").unwrap_err();
+        }
+        let detail = verify_pin(conn, "0000").unwrap_err().detail();
+        assert_eq!(detail.code, ErrorCode::TooManyUnlockAttempts);
+        assert_eq!(detail.params["retry_after_seconds"], BASE_DELAY_SECONDS);
+        assert_eq!(error_code(verify_pin(conn, "1234")), ErrorCode::TooManyUnlockAttempts);
+
+        // 待ち時間が過ぎた後に再び失敗すると、待ち時間が倍になる
+        settings::set_value(conn, RETRY_AT, "0").unwrap();
+        let detail = verify_pin(conn, "0000").unwrap_err().detail();
+        assert_eq!(detail.params["retry_after_seconds"], BASE_DELAY_SECONDS * 2);
+    }
+
+    #[test]
+    fn locked_app_only_accepts_unlock() {
+        let app_lock = AppLock::default();
+        {
+            let mut state = app_lock.state();
+            state.idle_timeout = Some(minutes(DEFAULT_IDLE_TIMEOUT_MINUTES));
+            state.locked = true;
+        }
+        for command in ["get_posts", "create_post", "lock_app", "get_database_status", "switch_profile", "disable_app_lock"] {
+            assert!(matches!(app_lock.check(command), Err(AppError::AppLocked)), "{}", command);
+        }
+        assert!(app_lock.check(UNLOCK_COMMAND).is_ok());
+
+        app_lock.unlock();
+        assert!(app_lock.check("get_posts").is_ok());
+    }
+
+    #[test]
+    fn reset_locks_on_the_next_load() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        set_pin(conn, "1234", DEFAULT_IDLE_TIMEOUT_MINUTES).unwrap();
+
+        let app_lock = AppLock::default();
+        app_lock.reset();
+        // パスフレーズを入力して開いた場合（lockはfalse）もロックする
+        app_lock.load(conn, false).unwrap();
+        assert!(app_lock.check("get_posts").is_err());
+
+        app_lock.unlock();
+        app_lock.load(conn, false).unwrap();
+        assert!(app_lock.check("get_posts").is_ok());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::{ErrorCode, Label};
+use crate::models::*;
+use crate::validation;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::collections::HashMap;
+
+impl AttributeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeType::Text => "text",
+            AttributeType::Number => "number",
+            AttributeType::Integer => "integer",
+            AttributeType::Boolean => "boolean",
+            AttributeType::Select => "select",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(AttributeType::Text),
+            "number" => Some(AttributeType::Number),
+            "integer" => Some(AttributeType::Integer),
+            "boolean" => Some(AttributeType::Boolean),
+            "select" => Some(AttributeType::Select),
+            _ => None,
+        }
+    }
+}
+
+/// 保存用に変換した属性値（文字列系はvalue_text、数値・真偽値はvalue_numberに格納する）
+pub struct StoredAttributeValue {
+    pub text: Option<String>,
+    pub number: Option<f64>,
+}
+
+fn category_attribute_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<CategoryAttribute> {
+    let value_type: String = row.get(3)?;
+    let allowed_values: Option<String> = row.get(5)?;
+    Ok(CategoryAttribute {
+        id: row.get(0)?,
+        category_id: row.get(1)?,
+        name: row.get(2)?,
+        value_type: AttributeType::parse(&value_type).unwrap_or(AttributeType::Text),
+        unit: row.get(4)?,
+        allowed_values: allowed_values
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        display_order: row.get(6)?,
+    })
+}
+
+/// カテゴリーに定義された属性スキーマを表示順で取得する
+pub fn get_category_attributes(
+    conn: &Connection,
+    category_id: i64,
+) -> Result<Vec<CategoryAttribute>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, category_id, name, value_type, unit, allowed_values, display_order
+        FROM category_attributes
+        WHERE category_id = ?1
+        ORDER BY display_order, id",
+    )?;
+
+    let attributes = stmt.query_map(params![category_id], category_attribute_from_row)?;
+
+    Ok(attributes.collect::<Result<Vec<_>, _>>()?)
+}
+
+pub fn get_category_attribute(conn: &Connection, id: i64) -> Result<CategoryAttribute, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, category_id, name, value_type, unit, allowed_values, display_order
+        FROM category_attributes
+        WHERE id = ?1",
+    )?;
+
+    let mut attributes = stmt.query_map(params![id], category_attribute_from_row)?;
+
+    match attributes.next() {
+        Some(attribute) => Ok(attribute?),
+        None => Err(ErrorDetail::new(ErrorCode::AttributeNotFound).into()),
+    }
+}
+
+/// 属性定義リクエストの選択肢を検証し、正規化した名称・単位・選択肢を返す
+pub fn normalize_attribute_request(
+    request: CreateCategoryAttributeRequest,
+) -> Result<CreateCategoryAttributeRequest, AppError> {
+    let name = request.name.trim().to_string();
+    let mut allowed_values: Vec<String> = Vec::new();
+    for value in request.allowed_values.unwrap_or_default() {
+        let value = value.trim().to_string();
+        if !value.is_empty() && !allowed_values.contains(&value) {
+            allowed_values.push(value);
+        }
+    }
+
+    if request.value_type == AttributeType::Select && allowed_values.is_empty() {
+        return Err(validation::field_error(
+            "allowed_values",
+            ErrorDetail::new(ErrorCode::AllowedValuesRequired),
+        ));
+    }
+
+    Ok(CreateCategoryAttributeRequest {
+        name,
+        value_type: request.value_type,
+        unit: request
+            .unit
+            .map(|unit| unit.trim().to_string())
+            .filter(|unit| !unit.is_empty()),
+        allowed_values: Some(allowed_values),
+        display_order: request.display_order,
+    })
+}
+
+/// 入力値を属性の型に照らして検証し、保存用の値に変換する
+pub fn to_stored_value(
+    attribute: &CategoryAttribute,
+    value: &Value,
+) -> Result<StoredAttributeValue, AppError> {
+    let type_error = |expected: Label| -> AppError {
+        ErrorDetail::new(ErrorCode::AttributeValueType)
+            .param("name", attribute.name.as_str())
+            .param("expected", serde_json::to_value(expected).unwrap_or_default())
+            .into()
+    };
+
+    match attribute.value_type {
+        AttributeType::Text => {
+            let text = value.as_str().ok_or_else(|| type_error(Label::Text))?.trim();
+            if text.is_empty() {
+                return Err(type_error(Label::Text));
+            }
+            Ok(StoredAttributeValue { text: Some(text.to_string()), number: None })
+        }
+        AttributeType::Number => {
+            let number = value.as_f64().ok_or_else(|| type_error(Label::Number))?;
+            Ok(StoredAttributeValue { text: None, number: Some(number) })
+        }
+        AttributeType::Integer => {
+            let number = value.as_i64().ok_or_else(|| type_error(Label::Integer))?;
+            Ok(StoredAttributeValue { text: None, number: Some(number as f64) })
+        }
+        AttributeType::Boolean => {
+            let flag = value.as_bool().ok_or_else(|| type_error(Label::Boolean))?;
+            Ok(StoredAttributeValue { text: None, number: Some(if flag { 1.0 } else { 0.0 }) })
+        }
+        AttributeType::Select => {
+            let text = value.as_str().ok_or_else(|| type_error(Label::Select))?.trim();
+            if !attribute.allowed_values.iter().any(|allowed| allowed == text) {
+                return Err(ErrorDetail::new(ErrorCode::AttributeValueNotAllowed)
+                    .param("name", attribute.name.as_str())
+                    .param("allowed_values", attribute.allowed_values.clone())
+                    .into());
+            }
+            Ok(StoredAttributeValue { text: Some(text.to_string()), number: None })
+        }
+    }
+}
+
+/// 保存されている値を属性の型に応じたJSON値に戻す
+pub fn from_stored_value(value_type: AttributeType, text: Option<String>, number: Option<f64>) -> Value {
+    match value_type {
+        AttributeType::Text | AttributeType::Select => text.map(Value::from).unwrap_or(Value::Null),
+        AttributeType::Number => number.map(Value::from).unwrap_or(Value::Null),
+        AttributeType::Integer => number.map(|n| Value::from(n as i64)).unwrap_or(Value::Null),
+        AttributeType::Boolean => number.map(|n| Value::from(n != 0.0)).unwrap_or(Value::Null),
+    }
+}
+
+/// お酒の属性値をカテゴリーのスキーマで検証し、まとめて置き換える
+pub fn replace_beverage_attributes(
+    conn: &Connection,
+    beverage_id: i64,
+    category_id: i64,
+    inputs: &[BeverageAttributeInput],
+) -> Result<(), AppError> {
+    let schema = get_category_attributes(conn, category_id)?;
+
+    let mut stored_values = Vec::new();
+    for input in inputs {
+        if stored_values.iter().any(|(id, _)| *id == input.attribute_id) {
+            return Err(ErrorDetail::new(ErrorCode::DuplicateAttribute).into());
+        }
+
+        let attribute = schema
+            .iter()
+            .find(|attribute| attribute.id == input.attribute_id)
+            .ok_or_else(|| AppError::from(ErrorDetail::new(ErrorCode::AttributeNotInCategory)))?;
+
+        // nullは未入力として扱う
+        if input.value.is_null() {
+            continue;
+        }
+
+        stored_values.push((attribute.id, to_stored_value(attribute, &input.value)?));
+    }
+
+    conn.execute(
+        "DELETE FROM beverage_attributes WHERE beverage_id = ?1",
+        params![beverage_id],
+    )?;
+
+    for (attribute_id, value) in stored_values {
+        conn.execute(
+            "INSERT INTO beverage_attributes (beverage_id, attribute_id, value_text, value_number)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![beverage_id, attribute_id, value.text, value.number],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// カテゴリー変更後に、新しいカテゴリーのスキーマに存在しない属性値を削除する
+pub fn remove_foreign_attributes(
+    conn: &Connection,
+    beverage_id: i64,
+    category_id: i64,
+) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM beverage_attributes
+        WHERE beverage_id = ?1
+        AND attribute_id NOT IN (SELECT id FROM category_attributes WHERE category_id = ?2)",
+        params![beverage_id, category_id],
+    )?;
+    Ok(())
+}
+
+/// お酒一覧に属性値を付与する（一覧に含まれるお酒の属性値のみを読み込む）
+pub fn attach_attributes(conn: &Connection, beverages: &mut [Beverage]) -> Result<(), AppError> {
+    if beverages.is_empty() {
+        return Ok(());
+    }
+
+    // IDの数がSQLのパラメーター数の上限を超えないよう、JSON配列1つにまとめて渡す
+    let beverage_ids = serde_json::to_string(
+        &beverages.iter().map(|beverage| beverage.id).collect::<Vec<_>>(),
+    )
+    .unwrap_or_default();
+
+    let mut stmt = conn.prepare(
+        "SELECT
+            ba.beverage_id,
+            a.id,
+            a.name,
+            a.value_type,
+            a.unit,
+            ba.value_text,
+            ba.value_number
+        FROM beverage_attributes ba
+        INNER JOIN category_attributes a ON ba.attribute_id = a.id
+        WHERE ba.beverage_id IN (SELECT value FROM json_each(?1))
+        ORDER BY a.display_order, a.id",
+    )?;
+
+    let rows = stmt.query_map(params![beverage_ids], |row| {
+        let value_type = AttributeType::parse(&row.get::<_, String>(3)?).unwrap_or(AttributeType::Text);
+        Ok((
+            row.get::<_, i64>(0)?,
+            BeverageAttributeValue {
+                attribute_id: row.get(1)?,
+                name: row.get(2)?,
+                value_type,
+                unit: row.get(4)?,
+                value: from_stored_value(value_type, row.get(5)?, row.get(6)?),
+            },
+        ))
+    })?;
+
+    let mut values_by_beverage: HashMap<i64, Vec<BeverageAttributeValue>> = HashMap::new();
+    for row in rows {
+        let (beverage_id, value) = row?;
+        values_by_beverage.entry(beverage_id).or_default().push(value);
+    }
+
+    for beverage in beverages.iter_mut() {
+        beverage.attributes = values_by_beverage.remove(&beverage.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// 属性の絞り込み条件を、beverage_attributesに対するEXISTS句の条件に変換する
+///
+/// 条件はエイリアスbのお酒に対するもので、パラメーターは?{first_index}から順に番号を振る。
+/// valueは属性の型に応じた完全一致、min/maxは数値型の属性の範囲として扱う。
+pub fn filter_conditions(
+    filters: &[BeverageAttributeFilter],
+    first_index: usize,
+) -> (Vec<String>, Vec<rusqlite::types::Value>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+    let mut placeholder = |value: rusqlite::types::Value| {
+        values.push(value);
+        format!("?{}", first_index + values.len() - 1)
+    };
+
+    for filter in filters {
+        let mut clauses = vec![format!(
+            "ba.attribute_id = {}",
+            placeholder(filter.attribute_id.into())
+        )];
+
+        if let Some(expected) = &filter.value {
+            clauses.push(match expected {
+                Value::String(text) => format!(
+                    "a.value_type IN ('text', 'select') AND ba.value_text = {}",
+                    placeholder(text.clone().into())
+                ),
+                Value::Number(number) => format!(
+                    "a.value_type IN ('number', 'integer') AND ba.value_number = {}",
+                    placeholder(number.as_f64().unwrap_or_default().into())
+                ),
+                Value::Bool(flag) => format!(
+                    "a.value_type = 'boolean' AND ba.value_number = {}",
+                    placeholder((if *flag { 1.0 } else { 0.0 }).into())
+                ),
+                // 未入力の値は保存しないため、nullや配列などに一致する属性値はない
+                _ => "0".to_string(),
+            });
+        }
+
+        if filter.min.is_some() || filter.max.is_some() {
+            clauses.push("a.value_type IN ('number', 'integer')".to_string());
+        }
+        if let Some(min) = filter.min {
+            clauses.push(format!("ba.value_number >= {}", placeholder(min.into())));
+        }
+        if let Some(max) = filter.max {
+            clauses.push(format!("ba.value_number <= {}", placeholder(max.into())));
+        }
+
+        conditions.push(format!(
+            "EXISTS (
+                SELECT 1 FROM beverage_attributes ba
+                INNER JOIN category_attributes a ON ba.attribute_id = a.id
+                WHERE ba.beverage_id = b.id AND {}
+            )",
+            clauses.join(" AND ")
+        ));
+    }
+
+    (conditions, values)
+}
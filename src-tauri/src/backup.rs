@@ -0,0 +1,426 @@
+use crate::db::{self, Database};
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::ErrorCode;
+use crate::models::BackupInfo;
+use crate::pool::DbPool;
+use crate::settings;
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+// 前回のバックアップからこの時間が経過したら、次のバックアップを作成する
+const BACKUP_INTERVAL_HOURS: i64 = 24;
+// バックアップが必要か確認する間隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// 起動時のバックアップを作成できていない場合（パスフレーズの入力待ちなど）に再確認する間隔
+const STARTUP_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// バックアップの保存先のディレクトリ名
+const BACKUP_DIR: &str = "backups";
+const EXTERNAL_BACKUP_DIR: &str = "external-backups";
+
+// バックアップのファイル名（nomi-log-20240101-093000-123.db）
+const FILE_PREFIX: &str = "nomi-log-";
+const FILE_EXTENSION: &str = ".db";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+/// バックアップの保存先（データベースごとに別のディレクトリ。backup_dirを参照）
+///
+/// プロファイルを切り替えた場合は、set_dirで切り替え先のデータベースの保存先に変更する。
+#[derive(Clone)]
+pub struct BackupStore {
+    dir: Arc<RwLock<PathBuf>>,
+}
+
+/// データベースのバックアップの保存先
+///
+/// アプリのデータディレクトリのデータベース（既定・追加したプロファイル）は、データベースごとのディレクトリにあるため同じディレクトリのbackups。
+/// 場所を指定したデータベースは、同じディレクトリの別のデータベースと混ざらず、同期フォルダーにも書き込まないよう、
+/// アプリのデータディレクトリのexternal-backups/<ファイル名>-<パスのハッシュ>にする。
+pub fn backup_dir(app_dir: &Path, db_path: &Path) -> PathBuf {
+    if db_path.starts_with(app_dir) {
+        return db_path.parent().unwrap_or(app_dir).join(BACKUP_DIR);
+    }
+    let stem = db_path.file_stem().unwrap_or_default().to_string_lossy();
+    app_dir
+        .join(EXTERNAL_BACKUP_DIR)
+        .join(format!("{}-{:016x}", stem, path_hash(db_path)))
+}
+
+/// パスのFNV-1aハッシュ（DefaultHasherはRustのバージョンによって値が変わりうるため使わない）
+fn path_hash(path: &Path) -> u64 {
+    path.to_string_lossy().bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl BackupStore {
+    pub fn new(dir: PathBuf) -> Self {
+        BackupStore { dir: Arc::new(RwLock::new(dir)) }
+    }
+
+    pub fn set_dir(&self, dir: PathBuf) {
+        *self.dir.write().unwrap_or_else(PoisonError::into_inner) = dir;
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.dir.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// バックアップの一覧を新しい順に取得する（スキーマのバージョンはデータベースのパスフレーズで開いて読み取る）
+    pub fn list(&self, passphrase: Option<&str>) -> Result<Vec<BackupInfo>, AppError> {
+        self.ids()?
+            .iter()
+            .map(|id| self.info(id, passphrase))
+            .collect()
+    }
+
+    /// バックアップを作成し、古いバックアップを整理する
+    pub async fn create(&self, pool: &DbPool) -> Result<BackupInfo, AppError> {
+        let backup = self.create_file(pool).await?;
+        self.rotate(pool).await?;
+        Ok(backup)
+    }
+
+    /// バックアップから復元する
+    ///
+    /// 復元前の状態に戻せるよう、現在のデータもバックアップしてから置き換える。
+    /// 置き換えの間は読み取り用の接続も止めるため、途中の状態が読まれることはない。
+    pub async fn restore(&self, pool: &DbPool, id: &str) -> Result<BackupInfo, AppError> {
+        let passphrase = pool.config()?.passphrase;
+        let path = self.path(id)?;
+        let backup = self.info(id, passphrase.as_deref())?;
+        validate(&path, id, passphrase.as_deref())?;
+
+        self.create_file(pool).await?;
+        pool.write_exclusive(move |db| {
+            db.restore_from(&path)?;
+            db.changes().all();
+            Ok(())
+        })
+        .await?;
+        log::info!("バックアップ「{}」から復元しました", id);
+
+        self.rotate(pool).await?;
+        Ok(backup)
+    }
+
+    /// 起動時と、前回のバックアップから1日以上経過した時点でバックアップを作成する
+    ///
+    /// 暗号化されたデータベースのパスフレーズの入力待ちの間は、開かれてから作成する。
+    pub fn schedule(self, pool: DbPool) {
+        std::thread::spawn(move || {
+            let mut startup = true;
+            loop {
+                if pool.is_open() {
+                    match self.is_due() {
+                        Ok(due) if due || startup => {
+                            match tauri::async_runtime::block_on(self.create(&pool)) {
+                                Ok(_) => startup = false,
+                                Err(error) => log::warn!("自動バックアップに失敗しました: {}", error),
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(error) => log::warn!("バックアップの確認に失敗しました: {}", error),
+                    }
+                }
+                std::thread::sleep(if startup { STARTUP_RETRY_INTERVAL } else { CHECK_INTERVAL });
+            }
+        });
+    }
+
+    /// すべてのバックアップを新しいパスフレーズで暗号化し直す（データベースの暗号化を切り替えた後）
+    ///
+    /// 古いパスフレーズのままのバックアップは復元できず、暗号化を有効にした場合は暗号化されていないコピーが残るため。
+    pub fn rekey(&self, old_passphrase: Option<&str>, new_passphrase: Option<&str>) -> Result<(), AppError> {
+        let dir = self.dir();
+        for id in self.ids()? {
+            let path = dir.join(&id);
+            let temp_path = dir.join(format!("{}.tmp", id));
+            let _ = fs::remove_file(&temp_path);
+            let rekeyed = Database::open_connection(&path, OpenFlags::default(), old_passphrase)
+                .and_then(|conn| db::export(&conn, &temp_path, new_passphrase));
+            match rekeyed {
+                Ok(()) => fs::rename(&temp_path, &path)?,
+                Err(error) => {
+                    let _ = fs::remove_file(&temp_path);
+                    log::warn!("バックアップ「{}」を暗号化し直せませんでした: {}", id, error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 一時ファイルにバックアップしてから名前を変更する（途中で終了しても不完全なバックアップが残らない）
+    async fn create_file(&self, pool: &DbPool) -> Result<BackupInfo, AppError> {
+        let dir = self.dir();
+        fs::create_dir_all(&dir)?;
+        let id = format!(
+            "{}{}{}",
+            FILE_PREFIX,
+            Local::now().naive_local().format(TIMESTAMP_FORMAT),
+            FILE_EXTENSION
+        );
+        let path = dir.join(&id);
+        let temp_path = dir.join(format!("{}.tmp", id));
+
+        let target = temp_path.clone();
+        let passphrase = pool
+            .read(move |db| {
+                db.backup_to(&target)?;
+                Ok(db.passphrase().map(str::to_string))
+            })
+            .await?;
+        fs::rename(&temp_path, &path)?;
+
+        log::info!("バックアップを作成しました: {}", id);
+        self.info(&id, passphrase.as_deref())
+    }
+
+    /// 設定された数（直近の日ごと・月ごと）の最新のバックアップを残し、それ以外を削除する
+    async fn rotate(&self, pool: &DbPool) -> Result<(), AppError> {
+        let (keep_daily, keep_monthly) = pool
+            .read(|db| {
+                Ok((
+                    settings::backup_keep_daily(db.conn())?,
+                    settings::backup_keep_monthly(db.conn())?,
+                ))
+            })
+            .await?;
+        let dir = self.dir();
+        for id in expired(&self.ids()?, keep_daily as usize, keep_monthly as usize) {
+            fs::remove_file(dir.join(&id))?;
+            log::info!("古いバックアップを削除しました: {}", id);
+        }
+        Ok(())
+    }
+
+    fn is_due(&self) -> Result<bool, AppError> {
+        Ok(match self.ids()?.first().and_then(|id| parse_id(id)) {
+            Some(latest) => (Local::now().naive_local() - latest).num_hours() >= BACKUP_INTERVAL_HOURS,
+            None => true,
+        })
+    }
+
+    /// バックアップのファイル名の一覧（新しい順）
+    fn ids(&self) -> Result<Vec<String>, AppError> {
+        let dir = self.dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let id = entry?.file_name().to_string_lossy().into_owned();
+            if parse_id(&id).is_some() {
+                ids.push(id);
+            }
+        }
+        ids.sort_by_key(|id| std::cmp::Reverse(parse_id(id)));
+        Ok(ids)
+    }
+
+    /// idからファイルのパスを求める（ファイル名の形式に合わないidはバックアップ以外のファイルを指しうるため拒否する）
+    fn path(&self, id: &str) -> Result<PathBuf, AppError> {
+        let path = self.dir().join(id);
+        if parse_id(id).is_none() || !path.is_file() {
+            return Err(ErrorDetail::new(ErrorCode::BackupNotFound).into());
+        }
+        Ok(path)
+    }
+
+    fn info(&self, id: &str, passphrase: Option<&str>) -> Result<BackupInfo, AppError> {
+        let path = self.path(id)?;
+        Ok(BackupInfo {
+            id: id.to_string(),
+            created_at: parse_id(id).unwrap_or_default(),
+            size_bytes: fs::metadata(&path)?.len(),
+            schema_version: schema_version(&path, passphrase).ok(),
+        })
+    }
+}
+
+/// 削除するバックアップ（idsは新しい順。直近keep_daily日分は日ごと、直近keep_monthlyか月分は月ごとに最新の1つを残す）
+fn expired(ids: &[String], keep_daily: usize, keep_monthly: usize) -> Vec<String> {
+    let mut days: BTreeSet<NaiveDate> = BTreeSet::new();
+    let mut months: BTreeSet<(i32, u32)> = BTreeSet::new();
+    let mut expired = Vec::new();
+
+    for id in ids {
+        let Some(created_at) = parse_id(id) else { continue };
+        let day = created_at.date();
+        let month = (day.year(), day.month());
+
+        let mut keep = false;
+        if !days.contains(&day) && days.len() < keep_daily {
+            days.insert(day);
+            keep = true;
+        }
+        if !months.contains(&month) && months.len() < keep_monthly {
+            months.insert(month);
+            keep = true;
+        }
+        if !keep {
+            expired.push(id.clone());
+        }
+    }
+    expired
+}
+
+fn parse_id(id: &str) -> Option<NaiveDateTime> {
+    let timestamp = id.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_EXTENSION)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+fn open(path: &Path, passphrase: Option<&str>) -> Result<Connection, AppError> {
+    Database::open_connection(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        passphrase,
+    )
+}
+
+fn schema_version(path: &Path, passphrase: Option<&str>) -> Result<i64, AppError> {
+    Ok(open(path, passphrase)?.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// 復元できるバックアップか確認する（破損していない・のみログのデータ・このアプリ以前のスキーマ）
+fn validate(path: &Path, id: &str, passphrase: Option<&str>) -> Result<(), AppError> {
+    let invalid = || AppError::from(ErrorDetail::new(ErrorCode::BackupInvalid).param("id", id));
+
+    let conn = open(path, passphrase).map_err(|_| invalid())?;
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|_| invalid())?;
+    let has_posts: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'posts')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|_| invalid())?;
+    if check != "ok" || !has_posts {
+        return Err(invalid());
+    }
+
+    let backup_version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|_| invalid())?;
+    if backup_version > Database::schema_version() {
+        return Err(ErrorDetail::new(ErrorCode::BackupSchemaTooNew)
+            .param("id", id)
+            .param("backup_version", backup_version)
+            .param("app_version", Database::schema_version())
+            .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as TimeDelta, Timelike};
+
+    fn id(created_at: NaiveDateTime) -> String {
+        format!("{}{}{}", FILE_PREFIX, created_at.format(TIMESTAMP_FORMAT), FILE_EXTENSION)
+    }
+
+    /// 2024年の毎日9時と21時のバックアップ（新しい順）
+    fn twice_daily() -> Vec<String> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut ids: Vec<String> = (0..366)
+            .flat_map(|day| {
+                let date = start + TimeDelta::days(day);
+                [9, 21].map(|hour| id(date.and_hms_opt(hour, 0, 0).unwrap()))
+            })
+            .collect();
+        ids.reverse();
+        ids
+    }
+
+    #[test]
+    fn rotation_keeps_latest_per_day_and_month() {
+        let ids = twice_daily();
+        let expired = expired(&ids, 7, 12);
+        let kept: Vec<&String> = ids.iter().filter(|id| !expired.contains(id)).collect();
+
+        // 直近7日の21時（12月25日〜31日）と、1〜11月の各月末の21時
+        let mut expected: Vec<String> = (25..=31)
+            .rev()
+            .map(|day| id(NaiveDate::from_ymd_opt(2024, 12, day).unwrap().and_hms_opt(21, 0, 0).unwrap()))
+            .collect();
+        for month in (1..=11).rev() {
+            let last_day = NaiveDate::from_ymd_opt(2024, month + 1, 1).unwrap().pred_opt().unwrap();
+            expected.push(id(last_day.and_hms_opt(21, 0, 0).unwrap()));
+        }
+        assert_eq!(kept, expected.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rotation_follows_the_configured_counts() {
+        let ids = twice_daily();
+        // (日ごと, 月ごと, 残る数)。月ごとの最新は日ごとの最新と重なる場合がある
+        for (keep_daily, keep_monthly, count) in [(1, 1, 1), (3, 2, 4), (40, 1, 40), (1, 24, 12)] {
+            let expired = expired(&ids, keep_daily, keep_monthly);
+            let kept: Vec<NaiveDateTime> = ids
+                .iter()
+                .filter(|id| !expired.contains(id))
+                .filter_map(|id| parse_id(id))
+                .collect();
+            assert_eq!(kept.len(), count, "{} / {}", keep_daily, keep_monthly);
+
+            let days: BTreeSet<NaiveDate> = kept.iter().map(|created_at| created_at.date()).collect();
+            assert_eq!(days.len(), kept.len());
+            assert!(kept.iter().all(|created_at| created_at.hour() == 21));
+        }
+    }
+
+    #[test]
+    fn rotation_ignores_other_files() {
+        let ids = vec!["notes.txt".to_string(), id(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap())];
+        assert!(expired(&ids, 1, 1).is_empty());
+    }
+
+    fn create_file(path: &Path, sql: &str, user_version: i64) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(sql).unwrap();
+        conn.pragma_update(None, "user_version", user_version).unwrap();
+    }
+
+    #[test]
+    fn validate_accepts_current_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("current.db");
+        drop(Database::new(path.clone(), None).unwrap());
+        validate(&path, "current.db", None).unwrap();
+    }
+
+    #[test]
+    fn validate_rejects_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("newer.db");
+        create_file(&path, "CREATE TABLE posts (id INTEGER PRIMARY KEY)", Database::schema_version() + 1);
+
+        let detail = validate(&path, "newer.db", None).unwrap_err().detail();
+        assert_eq!(detail.code, ErrorCode::BackupSchemaTooNew);
+        assert_eq!(detail.params["backup_version"], Database::schema_version() + 1);
+    }
+
+    #[test]
+    fn validate_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let without_posts = dir.path().join("other.db");
+        create_file(&without_posts, "CREATE TABLE notes (id INTEGER PRIMARY KEY)", 1);
+        let not_sqlite = dir.path().join("text.db");
+        fs::write(&not_sqlite, "not a database").unwrap();
+
+        for path in [without_posts, not_sqlite] {
+            let detail = validate(&path, "x", None).unwrap_err().detail();
+            assert_eq!(detail.code, ErrorCode::BackupInvalid);
+        }
+    }
+}
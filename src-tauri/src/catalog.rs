@@ -0,0 +1,498 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::i18n::{self, NameTable};
+use crate::messages::{ErrorCode, Locale};
+use crate::models::*;
+use crate::recipes;
+use crate::settings;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// アプリに同梱するお酒カタログ（内容を更新した場合はversionを上げる）
+const CATALOG_JSON: &str = include_str!("catalog/beverages.json");
+
+#[derive(Debug, Deserialize)]
+pub struct Catalog {
+    pub version: i64,
+    pub categories: Vec<CatalogCategory>,
+    pub beverages: Vec<CatalogEntry>,
+}
+
+/// 同梱のカテゴリー（初期データ・カタログの取り込みで作成する。keyはcategories.seed_keyに保存する）
+#[derive(Debug, Deserialize)]
+pub struct CatalogCategory {
+    pub key: String,
+    pub names: BTreeMap<Locale, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CatalogEntry {
+    pub key: String,
+    pub names: BTreeMap<Locale, String>,
+    pub category: String,
+    pub alcohol_content: f64,
+    pub serving_ml: f64,
+    pub producer: Option<String>,
+    pub country: Option<String>,
+    pub style: Option<String>,
+}
+
+/// 取り込んだ時点のカタログの項目の内容（beverages.catalog_valuesに保存する）
+///
+/// 再取り込みの際に、お酒の現在の内容と比べてユーザーが編集した項目を判定する。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CatalogValues {
+    names: BTreeMap<Locale, String>,
+    alcohol_content: f64,
+    serving_ml: f64,
+    producer: Option<String>,
+    country: Option<String>,
+    style: Option<String>,
+}
+
+impl CatalogEntry {
+    fn values(&self) -> CatalogValues {
+        CatalogValues {
+            names: self.names.clone(),
+            alcohol_content: self.alcohol_content,
+            serving_ml: self.serving_ml,
+            producer: self.producer.clone(),
+            country: self.country.clone(),
+            style: self.style.clone(),
+        }
+    }
+}
+
+pub fn load() -> Result<Catalog, AppError> {
+    Ok(serde_json::from_str(CATALOG_JSON)?)
+}
+
+/// キーまたはいずれかの言語の名前が一致するお酒（キーが一致するものを優先する）
+fn find_beverage(
+    conn: &Connection,
+    entry: &CatalogEntry,
+) -> Result<Option<(i64, Option<String>)>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, catalog_key FROM beverages WHERE catalog_key = ?1 OR name = ?2
+        ORDER BY catalog_key IS NULL",
+    )?;
+    let mut found = None;
+    for name in entry.names.values() {
+        let existing = stmt
+            .query_row(params![entry.key, name], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+        match existing {
+            Some((id, Some(key))) => return Ok(Some((id, Some(key)))),
+            Some(existing) if found.is_none() => found = Some(existing),
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+/// カタログの項目が既に取り込まれているか（キーまたは同名のお酒が存在するか）
+fn is_imported(conn: &Connection, entry: &CatalogEntry) -> Result<bool, AppError> {
+    Ok(find_beverage(conn, entry)?.is_some())
+}
+
+fn last_imported_version(conn: &Connection) -> Result<Option<i64>, AppError> {
+    Ok(conn.query_row("SELECT MAX(version) FROM catalog_imports", [], |row| row.get(0))?)
+}
+
+/// カタログのバージョンとカテゴリーごとの取り込み状況を返す（カテゴリー名は言語の設定に合わせる）
+pub fn info(conn: &Connection) -> Result<CatalogInfo, AppError> {
+    let catalog = load()?;
+    let locale = settings::language(conn)?;
+    let mut categories: Vec<CatalogCategorySummary> = Vec::new();
+
+    for category in &catalog.categories {
+        let mut summary = CatalogCategorySummary {
+            category_key: category.key.clone(),
+            category: i18n::pick_name(&category.names, locale).to_string(),
+            total_count: 0,
+            imported_count: 0,
+        };
+        for entry in catalog.beverages.iter().filter(|entry| entry.category == category.key) {
+            summary.total_count += 1;
+            if is_imported(conn, entry)? {
+                summary.imported_count += 1;
+            }
+        }
+        if summary.total_count > 0 {
+            categories.push(summary);
+        }
+    }
+
+    Ok(CatalogInfo {
+        version: catalog.version,
+        last_imported_version: last_imported_version(conn)?,
+        categories,
+    })
+}
+
+/// 同梱のカテゴリーのIDを取得し、存在しなければ言語の設定に合わせた名前で末尾に追加する
+///
+/// キー（seed_key）で探し、見つからない場合はいずれかの言語の名前が一致するカテゴリーを使う。
+fn ensure_category(
+    conn: &Connection,
+    category: &CatalogCategory,
+    locale: Locale,
+    created_categories: &mut Vec<String>,
+) -> Result<i64, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM categories WHERE seed_key = ?1 OR name = ?2
+        ORDER BY seed_key IS NULL",
+    )?;
+    for name in category.names.values() {
+        let existing: Option<i64> = stmt
+            .query_row(params![category.key, name], |row| row.get(0))
+            .optional()?;
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+    }
+
+    let name = i18n::pick_name(&category.names, locale);
+    conn.execute(
+        "INSERT INTO categories (name, seed_key, display_order)
+        VALUES (?1, ?2, (SELECT COALESCE(MAX(display_order), 0) + 1 FROM categories))",
+        params![name, category.key],
+    )?;
+    let id = conn.last_insert_rowid();
+    i18n::set_translations(conn, NameTable::Categories, id, &category.names)?;
+    created_categories.push(name.to_string());
+    Ok(id)
+}
+
+/// 前回の取り込みから変わった項目の新しい値（現在の値が前回の取り込みのまま＝ユーザーが編集していない場合のみ）
+fn changed<T: PartialEq + Clone>(current: &T, previous: &T, next: &T) -> Option<T> {
+    (previous != next && current == previous).then(|| next.clone())
+}
+
+/// 取り込み済みのお酒を新しいカタログの内容に更新する（更新した項目があればtrue）
+///
+/// 前回取り込んだ内容（catalog_values）から変わった項目のうち、ユーザーが編集していない項目だけを更新する。
+/// 前回の内容を記録していないお酒は編集したか判定できないため更新せず、今回の内容を記録するだけにする。
+fn update_beverage(conn: &Connection, id: i64, entry: &CatalogEntry) -> Result<bool, AppError> {
+    let values = entry.values();
+    let (name, previous): (String, Option<String>) = conn.query_row(
+        "SELECT name, catalog_values FROM beverages WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let previous: Option<CatalogValues> = previous.and_then(|json| serde_json::from_str(&json).ok());
+    let Some(previous) = previous.filter(|previous| *previous != values) else {
+        save_values(conn, id, &values)?;
+        return Ok(false);
+    };
+
+    let mut updated = false;
+
+    // 名前は前回のいずれかの言語の名前のままの場合に、同じ言語の新しい名前にする（他のお酒と重複する場合は変更しない）
+    let name_locale = previous
+        .names
+        .iter()
+        .find(|(_, previous_name)| **previous_name == name)
+        .map(|(locale, _)| *locale);
+    if let Some(locale) = name_locale.filter(|_| previous.names != values.names) {
+        let new_name = i18n::pick_name(&values.names, locale);
+        let duplicate: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM beverages WHERE name = ?1 AND id != ?2",
+            params![new_name, id],
+            |row| row.get(0),
+        )?;
+        if duplicate == 0 {
+            conn.execute("UPDATE beverages SET name = ?1 WHERE id = ?2", params![new_name, id])?;
+            i18n::set_translations(conn, NameTable::Beverages, id, &values.names)?;
+            updated = true;
+        }
+    }
+
+    let (alcohol_content, serving_ml): (Option<f64>, Option<f64>) = conn.query_row(
+        "SELECT alcohol_content, serving_ml FROM beverages WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let numbers = [
+        ("alcohol_content", changed(&alcohol_content, &Some(previous.alcohol_content), &Some(values.alcohol_content))),
+        ("serving_ml", changed(&serving_ml, &Some(previous.serving_ml), &Some(values.serving_ml))),
+    ];
+    for (column, value) in numbers {
+        if let Some(value) = value {
+            conn.execute(&format!("UPDATE beverages SET {} = ?1 WHERE id = ?2", column), params![value, id])?;
+            updated = true;
+        }
+    }
+
+    let (producer, country, style): (Option<String>, Option<String>, Option<String>) = conn.query_row(
+        "SELECT producer, country, style FROM beverages WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let texts = [
+        ("producer", changed(&producer, &previous.producer, &values.producer)),
+        ("country", changed(&country, &previous.country, &values.country)),
+        ("style", changed(&style, &previous.style, &values.style)),
+    ];
+    for (column, value) in texts {
+        if let Some(value) = value {
+            conn.execute(&format!("UPDATE beverages SET {} = ?1 WHERE id = ?2", column), params![value, id])?;
+            updated = true;
+        }
+    }
+
+    if updated {
+        conn.execute(
+            "UPDATE beverages SET updated_at = datetime('now', 'localtime') WHERE id = ?1",
+            params![id],
+        )?;
+        // 度数・量が変わったお酒を材料に使うレシピも再計算する
+        recipes::refresh(conn, id)?;
+    }
+    save_values(conn, id, &values)?;
+    Ok(updated)
+}
+
+fn save_values(conn: &Connection, id: i64, values: &CatalogValues) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE beverages SET catalog_values = ?1 WHERE id = ?2",
+        params![serde_json::to_string(values)?, id],
+    )?;
+    Ok(())
+}
+
+/// カタログを取り込む（categoriesを指定した場合はそのカテゴリーのみ。キーまたはいずれかの言語の名前で指定する）
+///
+/// 同名のお酒が既にある場合は新規作成せずカタログの項目と紐付ける。
+/// 取り込み済みの項目は、前回の取り込みからカタログで変わった項目のうちユーザーが編集していないものだけを更新し
+/// （update_beverage）、更新する項目がない場合はスキップする。新しいバージョンのカタログも繰り返し取り込める。
+pub fn import(
+    conn: &Connection,
+    categories: Option<&[String]>,
+) -> Result<CatalogImportResult, AppError> {
+    import_catalog(conn, &load()?, categories)
+}
+
+fn import_catalog(
+    conn: &Connection,
+    catalog: &Catalog,
+    categories: Option<&[String]>,
+) -> Result<CatalogImportResult, AppError> {
+    let locale = settings::language(conn)?;
+
+    let category_keys = categories
+        .map(|categories| {
+            categories
+                .iter()
+                .map(|requested| {
+                    catalog
+                        .categories
+                        .iter()
+                        .find(|category| {
+                            category.key == *requested
+                                || category.names.values().any(|name| name == requested)
+                        })
+                        .map(|category| category.key.clone())
+                        .ok_or_else(|| {
+                            AppError::from(
+                                ErrorDetail::new(ErrorCode::CatalogCategoryNotFound)
+                                    .param("category", requested.as_str()),
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+
+    let tx = conn.unchecked_transaction()?;
+    let mut result = CatalogImportResult {
+        version: catalog.version,
+        inserted_count: 0,
+        linked_count: 0,
+        updated_count: 0,
+        skipped_count: 0,
+        created_categories: vec![],
+    };
+
+    let entries = catalog.beverages.iter().filter(|entry| {
+        category_keys
+            .as_ref()
+            .map_or(true, |keys| keys.contains(&entry.category))
+    });
+
+    for entry in entries {
+        match find_beverage(&tx, entry)? {
+            Some((id, Some(key))) if key == entry.key => {
+                if update_beverage(&tx, id, entry)? {
+                    result.updated_count += 1;
+                } else {
+                    result.skipped_count += 1;
+                }
+            }
+            // 同名のお酒が別のカタログの項目と紐付いている
+            Some((_, Some(_))) => result.skipped_count += 1,
+            Some((id, None)) => {
+                // ユーザーが登録済みの同名のお酒はカタログの項目として扱う（カタログと異なる項目は編集したものとみなす）
+                tx.execute(
+                    "UPDATE beverages SET catalog_key = ?1 WHERE id = ?2",
+                    params![entry.key, id],
+                )?;
+                save_values(&tx, id, &entry.values())?;
+                result.linked_count += 1;
+            }
+            None => {
+                let category = catalog
+                    .categories
+                    .iter()
+                    .find(|category| category.key == entry.category)
+                    .ok_or_else(|| {
+                        AppError::from(
+                            ErrorDetail::new(ErrorCode::CatalogCategoryNotFound)
+                                .param("category", entry.category.as_str()),
+                        )
+                    })?;
+                let category_id =
+                    ensure_category(&tx, category, locale, &mut result.created_categories)?;
+                tx.execute(
+                    "INSERT INTO beverages (
+                        name, alcohol_content, category_id,
+                        producer, country, style, serving_ml, catalog_key, catalog_values
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        i18n::pick_name(&entry.names, locale),
+                        entry.alcohol_content,
+                        category_id,
+                        entry.producer,
+                        entry.country,
+                        entry.style,
+                        entry.serving_ml,
+                        entry.key,
+                        serde_json::to_string(&entry.values())?,
+                    ],
+                )?;
+                let beverage_id = tx.last_insert_rowid();
+                i18n::set_translations(&tx, NameTable::Beverages, beverage_id, &entry.names)?;
+                result.inserted_count += 1;
+            }
+        }
+    }
+
+    // 名前を変更していない既存のお酒（同名で紐付けたものを含む）にも言語ごとの名前を付与する
+    i18n::link_bundled_translations(&tx)?;
+
+    tx.execute(
+        "INSERT INTO catalog_imports (version, categories, inserted_count, linked_count, updated_count)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            catalog.version,
+            category_keys.as_ref().map(serde_json::to_string).transpose()?,
+            result.inserted_count,
+            result.linked_count,
+            result.updated_count,
+        ],
+    )?;
+
+    tx.commit()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn catalog(version: i64, beverages: serde_json::Value) -> Catalog {
+        serde_json::from_value(serde_json::json!({
+            "version": version,
+            "categories": [{"key": "beer", "names": {"ja": "ビール", "en": "Beer"}}],
+            "beverages": beverages,
+        }))
+        .unwrap()
+    }
+
+    fn entry(key: &str, ja: &str, alcohol_content: f64, style: &str) -> serde_json::Value {
+        serde_json::json!({
+            "key": key,
+            "names": {"ja": ja, "en": key},
+            "category": "beer",
+            "alcohol_content": alcohol_content,
+            "serving_ml": 350.0,
+            "producer": null,
+            "country": "日本",
+            "style": style,
+        })
+    }
+
+    fn beverage(conn: &Connection, catalog_key: &str) -> (String, f64, Option<String>) {
+        conn.query_row(
+            "SELECT name, alcohol_content, style FROM beverages WHERE catalog_key = ?1",
+            params![catalog_key],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    fn counts(result: &CatalogImportResult) -> (i64, i64, i64, i64) {
+        (result.inserted_count, result.linked_count, result.updated_count, result.skipped_count)
+    }
+
+    #[test]
+    fn reimport_updates_only_fields_the_user_did_not_edit() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        settings::set_value(conn, settings::LANGUAGE, "ja").unwrap();
+        // ユーザーが登録済みの同名のお酒（度数がカタログと異なる）
+        conn.execute(
+            "INSERT INTO beverages (name, alcohol_content, category_id)
+            VALUES ('一番搾り', 4.5, (SELECT MIN(id) FROM categories))",
+            [],
+        )
+        .unwrap();
+
+        let v1 = catalog(
+            1,
+            serde_json::json!([
+                entry("dry", "スーパードライ", 5.0, "ラガー"),
+                entry("ichiban", "一番搾り", 5.0, "ラガー"),
+                entry("lager", "ラガー", 5.0, "ラガー"),
+            ]),
+        );
+        assert_eq!(counts(&import_catalog(conn, &v1, None).unwrap()), (2, 1, 0, 0));
+        // 同じバージョンの再取り込みは何も変えない
+        assert_eq!(counts(&import_catalog(conn, &v1, None).unwrap()), (0, 0, 0, 3));
+
+        // ユーザーがスタイルを編集し、lagerは前回の内容を記録する前に取り込んだものとする
+        conn.execute("UPDATE beverages SET style = '辛口' WHERE catalog_key = 'dry'", []).unwrap();
+        conn.execute("UPDATE beverages SET catalog_values = NULL WHERE catalog_key = 'lager'", []).unwrap();
+
+        let v2 = catalog(
+            2,
+            serde_json::json!([
+                entry("dry", "アサヒスーパードライ", 5.5, "ドライ"),
+                entry("ichiban", "一番搾り", 5.5, "ラガー"),
+                entry("lager", "ラガー", 4.5, "ラガー"),
+            ]),
+        );
+        let result = import_catalog(conn, &v2, None).unwrap();
+        assert_eq!(counts(&result), (0, 0, 1, 2));
+
+        // 編集していない名前・度数は更新し、編集したスタイルはそのまま
+        assert_eq!(beverage(conn, "dry"), ("アサヒスーパードライ".to_string(), 5.5, Some("辛口".to_string())));
+        // 紐付けた時点でカタログと異なっていた度数はユーザーの値
+        assert_eq!(beverage(conn, "ichiban").1, 4.5);
+        // 前回の内容がないお酒は更新せず、今回の内容を記録する（以降は記録した内容と異なる度数を編集したものとみなす）
+        assert_eq!(beverage(conn, "lager").1, 5.0);
+        let v3 = catalog(3, serde_json::json!([entry("lager", "ラガー", 6.0, "ラガー")]));
+        assert_eq!(counts(&import_catalog(conn, &v3, None).unwrap()), (0, 0, 0, 1));
+        assert_eq!(beverage(conn, "lager").1, 5.0);
+
+        let (version, updated_count): (i64, i64) = conn
+            .query_row(
+                "SELECT version, updated_count FROM catalog_imports WHERE version = 2",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((version, updated_count), (2, 1));
+    }
+}
@@ -1,77 +1,115 @@
+use crate::app_lock::{self, AppLock};
+use crate::attributes;
+use crate::backup::BackupStore;
+use crate::catalog;
+use crate::dates;
+use crate::encryption;
 use crate::db::Database;
-use crate::error::AppError;
+use crate::error::{AppError, ErrorDetail};
+use crate::i18n::{self, NameTable};
+use crate::live_session;
+use crate::merge;
+use crate::messages::{self, ErrorCode, Label, Locale};
+use crate::profiles::Profiles;
+use crate::recipes;
+use crate::sessions;
+use crate::settings;
+use crate::stats;
+use crate::suggestions;
+use crate::templates;
+use crate::validation::{self, Validator};
+use crate::wellbeing;
 use crate::models::*;
-use rusqlite::params;
-use std::sync::{Mutex, MutexGuard};
+use crate::pool::{DbConfig, DbPool};
+use chrono::NaiveDate;
+use rusqlite::{params, OptionalExtension};
+use std::path::{Path, PathBuf};
 use tauri::State;
 
 #[tauri::command]
-pub fn get_posts(db: State<'_, Mutex<Database>>) -> Result<Vec<PostWithBeverages>, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let mut stmt = db.conn().prepare(
-        "SELECT 
-            p.id,
-            p.date,
-            p.comment,
-            p.created_at,
-            p.updated_at
-        FROM posts p
-        ORDER BY p.date DESC, p.created_at DESC"
-    )?;
+pub async fn get_posts(pool: State<'_, DbPool>) -> Result<Vec<PostWithBeverages>, AppError> {
+    pool.read(move |db| {
+        // 記録中のセッションは含めない（get_active_sessionで取得する）
+        let mut stmt = db.conn().prepare(&format!(
+            "SELECT 
+                p.id,
+                p.date,
+                p.comment,
+                p.started_at,
+                p.ended_at,
+                p.created_at,
+                p.updated_at,
+                {} AS duration_minutes
+            FROM posts p
+            WHERE p.active = 0
+            ORDER BY p.date DESC, COALESCE(p.started_at, p.created_at) DESC, p.id DESC",
+            sessions::DURATION_MINUTES_SQL
+        ))?;
 
-    let posts_iter = stmt.query_map([], |row| {
-        Ok(PostWithBeverages {
-            id: row.get(0)?,
-            date: row.get(1)?,
-            comment: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-            beverages: vec![],
-        })
-    })?;
+        let posts_iter = stmt.query_map([], |row| {
+            Ok((
+                PostWithBeverages {
+                    id: row.get(0)?,
+                    date: row.get(1)?,
+                    comment: row.get(2)?,
+                    started_at: row.get(3)?,
+                    ended_at: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                    beverages: vec![],
+                    pace: SessionPace::default(),
+                },
+                row.get::<_, Option<f64>>(7)?,
+            ))
+        })?;
 
-    let mut posts: Vec<PostWithBeverages> = posts_iter.collect::<Result<Vec<_>, _>>()?;
+        let mut posts: Vec<(PostWithBeverages, Option<f64>)> = posts_iter.collect::<Result<Vec<_>, _>>()?;
 
-    // 各投稿のお酒を取得
-    for post in &mut posts {
-        let mut beverage_stmt = db.conn().prepare(
-            "SELECT 
-                pb.beverage_id,
-                b.name,
-                pb.amount,
-                b.alcohol_content
-            FROM post_beverages pb
-            INNER JOIN beverages b ON pb.beverage_id = b.id
-            WHERE pb.post_id = ?"
-        )?;
-
-        let beverages = beverage_stmt.query_map(params![post.id], |row| {
-            Ok(BeverageAmount {
-                beverage_id: row.get(0)?,
-                beverage_name: row.get(1)?,
-                amount: row.get(2)?,
-                alcohol_content: row.get(3)?,
-            })
-        })?;
+        // 各投稿のお酒を取得
+        for (post, duration_minutes) in &mut posts {
+            let mut beverage_stmt = db.conn().prepare(&format!(
+                "SELECT 
+                    pb.beverage_id,
+                    {},
+                    pb.amount,
+                    b.alcohol_content,
+                    pb.consumed_at
+                FROM post_beverages pb
+                INNER JOIN beverages b ON pb.beverage_id = b.id
+                WHERE pb.post_id = ?
+                ORDER BY pb.consumed_at IS NULL, pb.consumed_at, pb.id",
+                i18n::localized_name_sql(NameTable::Beverages, "b")
+            ))?;
 
-        post.beverages = beverages.collect::<Result<Vec<_>, _>>()?;
-    }
+            let beverages = beverage_stmt.query_map(params![post.id], |row| {
+                Ok(BeverageAmount {
+                    beverage_id: row.get(0)?,
+                    beverage_name: row.get(1)?,
+                    amount: row.get(2)?,
+                    alcohol_content: row.get(3)?,
+                    consumed_at: row.get(4)?,
+                })
+            })?;
 
-    Ok(posts)
+            post.beverages = beverages.collect::<Result<Vec<_>, _>>()?;
+            post.pace = sessions::pace(*duration_minutes, &post.beverages);
+        }
+
+        Ok(posts.into_iter().map(|(post, _)| post).collect())
+    })
+    .await
 }
 
-#[tauri::command]
-pub fn create_post(
-    db: State<'_, Mutex<Database>>,
-    request: CreatePostRequest,
-) -> Result<i64, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let tx = db.conn().unchecked_transaction()?;
+/// 投稿とお酒との関連を1つのトランザクションで作成する
+fn insert_post(conn: &rusqlite::Connection, request: CreatePostRequest) -> Result<i64, AppError> {
+    validation::validate(conn, &request)?;
+    let request = sessions::normalize_post_request(conn, request)?;
+    let tx = conn.unchecked_transaction()?;
 
     // 投稿を作成
     tx.execute(
-        "INSERT INTO posts (date, comment) VALUES (?1, ?2)",
-        params![request.date, request.comment],
+        "INSERT INTO posts (date, comment, started_at, ended_at) VALUES (?1, ?2, ?3, ?4)",
+        params![request.date, request.comment, request.started_at, request.ended_at],
     )?;
 
     let post_id = tx.last_insert_rowid();
@@ -79,8 +117,8 @@ pub fn create_post(
     // お酒との関連を追加
     for beverage in request.beverages {
         tx.execute(
-            "INSERT INTO post_beverages (post_id, beverage_id, amount) VALUES (?1, ?2, ?3)",
-            params![post_id, beverage.beverage_id, beverage.amount],
+            "INSERT INTO post_beverages (post_id, beverage_id, amount, consumed_at) VALUES (?1, ?2, ?3, ?4)",
+            params![post_id, beverage.beverage_id, beverage.amount, beverage.consumed_at],
         )?;
     }
 
@@ -89,376 +127,1913 @@ pub fn create_post(
 }
 
 #[tauri::command]
-pub fn update_post(
-    db: State<'_, Mutex<Database>>,
+pub async fn create_post(
+    pool: State<'_, DbPool>,
+    request: CreatePostRequest,
+) -> Result<i64, AppError> {
+    pool.write(move |db| {
+        let post_id = insert_post(db.conn(), request)?;
+        db.changes().posts(db.conn(), &[post_id])?;
+        Ok(post_id)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn update_post(
+    pool: State<'_, DbPool>,
     id: i64,
     request: CreatePostRequest,
 ) -> Result<(), AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let tx = db.conn().unchecked_transaction()?;
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        let request = sessions::normalize_post_request(db.conn(), request)?;
+        // 記録中のセッションはsession_drinksから合算するため、直接は変更しない
+        let active: Option<bool> = db
+            .conn()
+            .query_row("SELECT active FROM posts WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()?;
+        if active == Some(true) {
+            return Err(ErrorDetail::new(ErrorCode::PostInActiveSession).into());
+        }
+        // 変更前の日付も再集計の範囲に含める
+        db.changes().posts(db.conn(), &[id])?;
+        let tx = db.conn().unchecked_transaction()?;
 
-    // 投稿を更新
-    tx.execute(
-        "UPDATE posts SET date = ?1, comment = ?2, updated_at = datetime('now', 'localtime') WHERE id = ?3",
-        params![request.date, request.comment, id],
-    )?;
+        // 投稿を更新
+        tx.execute(
+            "UPDATE posts SET
+                date = ?1,
+                comment = ?2,
+                started_at = ?3,
+                ended_at = ?4,
+                updated_at = datetime('now', 'localtime')
+            WHERE id = ?5",
+            params![request.date, request.comment, request.started_at, request.ended_at, id],
+        )?;
 
-    // 既存のお酒との関連を削除
-    tx.execute(
-        "DELETE FROM post_beverages WHERE post_id = ?1",
-        params![id],
-    )?;
+        // 既存のお酒との関連を削除
+        tx.execute(
+            "DELETE FROM post_beverages WHERE post_id = ?1",
+            params![id],
+        )?;
 
-    // 新しいお酒との関連を追加
-    for beverage in request.beverages {
+        // 新しいお酒との関連を追加
+        for beverage in request.beverages {
+            tx.execute(
+                "INSERT INTO post_beverages (post_id, beverage_id, amount, consumed_at) VALUES (?1, ?2, ?3, ?4)",
+                params![id, beverage.beverage_id, beverage.amount, beverage.consumed_at],
+            )?;
+        }
+
+        tx.commit()?;
+        db.changes().posts(db.conn(), &[id])?;
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_post(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| {
+        db.changes().posts(db.conn(), &[id])?;
+        let tx = db.conn().unchecked_transaction()?;
         tx.execute(
-            "INSERT INTO post_beverages (post_id, beverage_id, amount) VALUES (?1, ?2, ?3)",
-            params![id, beverage.beverage_id, beverage.amount],
+            "DELETE FROM session_drinks WHERE post_id = ?1",
+            params![id],
         )?;
-    }
+        tx.execute(
+            "DELETE FROM posts WHERE id = ?1",
+            params![id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })
+    .await
+}
 
-    tx.commit()?;
-    Ok(())
+#[tauri::command]
+pub async fn get_active_session(pool: State<'_, DbPool>) -> Result<Option<ActiveSession>, AppError> {
+    pool.read(move |db| live_session::get_active_session(db.conn())).await
 }
 
 #[tauri::command]
-pub fn delete_post(db: State<'_, Mutex<Database>>, id: i64) -> Result<(), AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    db.conn().execute(
-        "DELETE FROM posts WHERE id = ?1",
-        params![id],
-    )?;
-    Ok(())
+pub async fn start_drinking_session(
+    pool: State<'_, DbPool>,
+    date: Option<String>,
+    comment: Option<String>,
+) -> Result<ActiveSession, AppError> {
+    pool.write(move |db| {
+        let date = dates::parse_optional_date(date.as_deref())?;
+        let session = live_session::start_session(db.conn(), date, comment)?;
+        db.changes().posts(db.conn(), &[session.post_id])?;
+        Ok(session)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_categories(db: State<'_, Mutex<Database>>) -> Result<Vec<Category>, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let mut stmt = db.conn().prepare(
-        "SELECT id, name, display_order, created_at, updated_at
-        FROM categories
-        ORDER BY display_order, name"
-    )?;
+pub async fn add_session_drink(
+    pool: State<'_, DbPool>,
+    beverage_id: i64,
+    amount: f64,
+) -> Result<ActiveSession, AppError> {
+    pool.write(move |db| {
+        let session = live_session::add_drink(db.conn(), beverage_id, amount)?;
+        db.changes().posts(db.conn(), &[session.post_id])?;
+        Ok(session)
+    })
+    .await
+}
 
-    let categories = stmt.query_map([], |row| {
-        Ok(Category {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            display_order: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-        })
-    })?;
+#[tauri::command]
+pub async fn remove_session_drink(
+    pool: State<'_, DbPool>,
+    drink_id: i64,
+) -> Result<ActiveSession, AppError> {
+    pool.write(move |db| {
+        let session = live_session::remove_drink(db.conn(), drink_id)?;
+        db.changes().posts(db.conn(), &[session.post_id])?;
+        Ok(session)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn end_drinking_session(pool: State<'_, DbPool>) -> Result<i64, AppError> {
+    pool.write(move |db| {
+        let post_id = live_session::end_session(db.conn())?;
+        db.changes().posts(db.conn(), &[post_id])?;
+        Ok(post_id)
+    })
+    .await
+}
 
-    Ok(categories.collect::<Result<Vec<_>, _>>()?)
+#[tauri::command]
+pub async fn discard_drinking_session(pool: State<'_, DbPool>) -> Result<(), AppError> {
+    pool.write(move |db| {
+        if let Some(session) = live_session::get_active_session(db.conn())? {
+            db.changes().posts(db.conn(), &[session.post_id])?;
+        }
+        live_session::discard_session(db.conn())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn create_category(
-    db: State<'_, Mutex<Database>>,
-    request: CreateCategoryRequest,
+pub async fn get_post_templates(pool: State<'_, DbPool>) -> Result<Vec<PostTemplate>, AppError> {
+    pool.read(move |db| templates::get_templates(db.conn())).await
+}
+
+#[tauri::command]
+pub async fn create_post_template(
+    pool: State<'_, DbPool>,
+    request: CreatePostTemplateRequest,
 ) -> Result<i64, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let display_order = request.display_order.unwrap_or(0);
-    
-    db.conn().execute(
-        "INSERT INTO categories (name, display_order) VALUES (?1, ?2)",
-        params![request.name, display_order],
-    )?;
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        let request = templates::normalize_template_request(db.conn(), None, request)?;
+        let tx = db.conn().unchecked_transaction()?;
+
+        tx.execute(
+            "INSERT INTO post_templates (name, comment, tags) VALUES (?1, ?2, ?3)",
+            params![
+                request.name,
+                request.comment,
+                request.tags.as_ref().map(serde_json::to_string).transpose()?,
+            ],
+        )?;
+
+        let template_id = tx.last_insert_rowid();
+        templates::replace_template_beverages(&tx, template_id, &request.beverages)?;
+
+        tx.commit()?;
+        db.changes().templates(&[template_id]);
+        Ok(template_id)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn update_post_template(
+    pool: State<'_, DbPool>,
+    id: i64,
+    request: CreatePostTemplateRequest,
+) -> Result<(), AppError> {
+    pool.write(move |db| {
+        templates::get_template(db.conn(), id)?;
+        validation::validate(db.conn(), &request)?;
+        let request = templates::normalize_template_request(db.conn(), Some(id), request)?;
+        let tx = db.conn().unchecked_transaction()?;
+
+        tx.execute(
+            "UPDATE post_templates SET
+                name = ?1,
+                comment = ?2,
+                tags = ?3,
+                updated_at = datetime('now', 'localtime')
+            WHERE id = ?4",
+            params![
+                request.name,
+                request.comment,
+                request.tags.as_ref().map(serde_json::to_string).transpose()?,
+                id,
+            ],
+        )?;
+        templates::replace_template_beverages(&tx, id, &request.beverages)?;
 
-    Ok(db.conn().last_insert_rowid())
+        tx.commit()?;
+        db.changes().templates(&[id]);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_post_template(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| {
+        let tx = db.conn().unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM post_template_beverages WHERE template_id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM post_templates WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        db.changes().templates(&[id]);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn create_post_from_template(
+    pool: State<'_, DbPool>,
+    template_id: i64,
+    date: Option<String>,
+    overrides: Option<PostTemplateOverrides>,
+) -> Result<i64, AppError> {
+    pool.write(move |db| {
+        let template = templates::get_template(db.conn(), template_id)?;
+        let overrides = overrides.unwrap_or_default();
+        validation::validate(db.conn(), &overrides)?;
+        // 日付を省略した場合は日付の切り替え時刻を考慮した今日とする
+        let date = match dates::parse_optional_date(date.as_deref())? {
+            Some(date) => date,
+            None => settings::drinking_today(db.conn())?,
+        };
+        let request = templates::build_post_request(template, date, overrides)?;
+        let post_id = insert_post(db.conn(), request)?;
+        db.changes().posts(db.conn(), &[post_id])?;
+        Ok(post_id)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn delete_category(db: State<'_, Mutex<Database>>, id: i64) -> Result<bool, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    
-    // 使用されているかチェック
-    let count: i64 = db.conn().query_row(
-        "SELECT COUNT(*) FROM beverages WHERE category_id = ?1",
-        params![id],
+pub async fn get_categories(
+    pool: State<'_, DbPool>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Category>, AppError> {
+    pool.read(move |db| {
+        // カテゴリー名は言語の設定に合わせる
+        let mut stmt = db.conn().prepare(&format!(
+            "SELECT id, {} AS localized_name, display_order, parent_id, archived, created_at, updated_at
+            FROM categories c
+            WHERE ?1 OR archived = 0
+            ORDER BY display_order, localized_name",
+            i18n::localized_name_sql(NameTable::Categories, "c")
+        ))?;
+
+        let categories = stmt.query_map(params![include_archived.unwrap_or(false)], |row| {
+            Ok(Category {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                display_order: row.get(2)?,
+                parent_id: row.get(3)?,
+                archived: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })?;
+
+        Ok(categories.collect::<Result<Vec<_>, _>>()?)
+    })
+    .await
+}
+
+/// カテゴリー名を検証し、前後の空白を除去して返す（同名のカテゴリーは登録不可）
+///
+/// 更新時は表示中の言語の名前から変更されていなければ登録名のままにする。
+fn normalize_category_name(
+    conn: &rusqlite::Connection,
+    name: &str,
+    category_id: Option<i64>,
+) -> Result<String, AppError> {
+    let name = match category_id {
+        Some(id) => i18n::stored_name(conn, NameTable::Categories, id, name.trim())?,
+        None => name.trim().to_string(),
+    };
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM categories WHERE name = ?1 AND id != ?2",
+        params![name, category_id.unwrap_or(0)],
         |row| row.get(0),
     )?;
 
     if count > 0 {
-        return Err(AppError::InvalidInput(
-            "このカテゴリーは使用中のため削除できません".to_string(),
-        ));
+        return Err(validation::field_error("name", ErrorDetail::new(ErrorCode::CategoryNameExists)));
     }
 
-    db.conn().execute(
-        "DELETE FROM categories WHERE id = ?1",
-        params![id],
-    )?;
+    Ok(name)
+}
+
+/// 親カテゴリーが存在し、自身や子孫カテゴリーを親にしていないことを確認する
+fn validate_parent_category(
+    conn: &rusqlite::Connection,
+    parent_id: i64,
+    category_id: Option<i64>,
+) -> Result<(), AppError> {
+    let mut current = Some(parent_id);
+    while let Some(id) = current {
+        if Some(id) == category_id {
+            return Err(validation::field_error(
+                "parent_id",
+                ErrorDetail::new(ErrorCode::CategoryParentCycle),
+            ));
+        }
+
+        current = conn
+            .query_row(
+                "SELECT parent_id FROM categories WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::from(ErrorDetail::new(ErrorCode::ParentCategoryNotFound)))?;
+    }
 
-    Ok(true)
+    Ok(())
 }
 
 #[tauri::command]
-pub fn get_beverages(db: State<'_, Mutex<Database>>) -> Result<Vec<Beverage>, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let mut stmt = db.conn().prepare(
-        "SELECT 
-            b.id,
-            b.name,
-            b.alcohol_content,
-            b.category_id,
-            c.name as category_name,
-            b.created_at,
-            b.updated_at
-        FROM beverages b
-        INNER JOIN categories c ON b.category_id = c.id
-        ORDER BY b.name"
-    )?;
+pub async fn create_category(
+    pool: State<'_, DbPool>,
+    request: CreateCategoryRequest,
+) -> Result<i64, AppError> {
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        let name = normalize_category_name(db.conn(), &request.name, None)?;
+        let display_order = request.display_order.unwrap_or(0);
 
-    let beverages = stmt.query_map([], |row| {
-        Ok(Beverage {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            alcohol_content: row.get(2)?,
-            category_id: row.get(3)?,
-            category_name: row.get(4)?,
-            created_at: row.get(5)?,
-            updated_at: row.get(6)?,
-        })
-    })?;
+        db.conn().execute(
+            "INSERT INTO categories (name, display_order, parent_id) VALUES (?1, ?2, ?3)",
+            params![name, display_order, request.parent_id],
+        )?;
 
-    Ok(beverages.collect::<Result<Vec<_>, _>>()?)
+        let category_id = db.conn().last_insert_rowid();
+        db.changes().categories(&[category_id]);
+        Ok(category_id)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_beverages_by_category(
-    db: State<'_, Mutex<Database>>,
-    category_id: i64,
-) -> Result<Vec<Beverage>, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    let mut stmt = db.conn().prepare(
-        "SELECT 
-            b.id,
-            b.name,
-            b.alcohol_content,
-            b.category_id,
-            c.name as category_name,
-            b.created_at,
-            b.updated_at
-        FROM beverages b
-        INNER JOIN categories c ON b.category_id = c.id
-        WHERE b.category_id = ?1
-        ORDER BY b.name"
-    )?;
+pub async fn update_category(
+    pool: State<'_, DbPool>,
+    id: i64,
+    request: CreateCategoryRequest,
+) -> Result<(), AppError> {
+    pool.write(move |db| {
+        // カテゴリーが存在するか確認
+        let current_order: Option<i64> = db
+            .conn()
+            .query_row(
+                "SELECT display_order FROM categories WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
 
-    let beverages = stmt.query_map(params![category_id], |row| {
-        Ok(Beverage {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            alcohol_content: row.get(2)?,
-            category_id: row.get(3)?,
-            category_name: row.get(4)?,
-            created_at: row.get(5)?,
-            updated_at: row.get(6)?,
-        })
-    })?;
+        let Some(current_order) = current_order else {
+            return Err(ErrorDetail::new(ErrorCode::CategoryNotFound).into());
+        };
+
+        validation::validate(db.conn(), &request)?;
+        let name = normalize_category_name(db.conn(), &request.name, Some(id))?;
+
+        if let Some(parent_id) = request.parent_id {
+            validate_parent_category(db.conn(), parent_id, Some(id))?;
+        }
+
+        db.conn().execute(
+            "UPDATE categories SET
+                name = ?1,
+                display_order = ?2,
+                parent_id = ?3,
+                updated_at = datetime('now', 'localtime')
+            WHERE id = ?4",
+            params![
+                name,
+                request.display_order.unwrap_or(current_order),
+                request.parent_id,
+                id,
+            ],
+        )?;
+        i18n::drop_stale_translations(db.conn(), NameTable::Categories, id)?;
+        db.changes().categories(&[id]);
 
-    Ok(beverages.collect::<Result<Vec<_>, _>>()?)
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn create_beverage(
-    db: State<'_, Mutex<Database>>,
-    request: CreateBeverageRequest,
-) -> Result<i64, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    
-    // バリデーション: 名称が空でないことを確認
-    if request.name.trim().is_empty() {
-        return Err(AppError::InvalidInput("お酒の名称を入力してください".to_string()));
-    }
-    
-    // カテゴリーが存在するか確認
-    let category_count: i64 = db.conn().query_row(
-        "SELECT COUNT(*) FROM categories WHERE id = ?1",
-        params![request.category_id],
-        |row| row.get(0),
-    )?;
-    
-    if category_count == 0 {
-        return Err(AppError::InvalidInput("指定されたカテゴリーが見つかりません".to_string()));
-    }
-    
-    db.conn().execute(
-        "INSERT INTO beverages (name, alcohol_content, category_id) VALUES (?1, ?2, ?3)",
-        params![request.name.trim(), request.alcohol_content, request.category_id],
-    )?;
+pub async fn rename_category(
+    pool: State<'_, DbPool>,
+    id: i64,
+    name: String,
+) -> Result<(), AppError> {
+    let mut v = Validator::new();
+    v.name("name", &name, Label::CategoryName);
+    v.finish()?;
+
+    pool.write(move |db| {
+        let name = normalize_category_name(db.conn(), &name, Some(id))?;
+
+        let updated = db.conn().execute(
+            "UPDATE categories SET name = ?1, updated_at = datetime('now', 'localtime') WHERE id = ?2",
+            params![name, id],
+        )?;
 
-    Ok(db.conn().last_insert_rowid())
+        if updated == 0 {
+            return Err(ErrorDetail::new(ErrorCode::CategoryNotFound).into());
+        }
+        i18n::drop_stale_translations(db.conn(), NameTable::Categories, id)?;
+        db.changes().categories(&[id]);
+
+        Ok(())
+    })
+    .await
 }
 
+/// 指定された順にdisplay_orderを振り直す（すべて成功するか、何も変更しない）
 #[tauri::command]
-pub fn update_beverage(
-    db: State<'_, Mutex<Database>>,
-    id: i64,
-    request: CreateBeverageRequest,
+pub async fn reorder_categories(
+    pool: State<'_, DbPool>,
+    category_ids: Vec<i64>,
 ) -> Result<(), AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    
-    // バリデーション: 名称が空でないことを確認
-    if request.name.trim().is_empty() {
-        return Err(AppError::InvalidInput("お酒の名称を入力してください".to_string()));
-    }
-    
-    // お酒が存在するか確認
-    let count: i64 = db.conn().query_row(
-        "SELECT COUNT(*) FROM beverages WHERE id = ?1",
-        params![id],
-        |row| row.get(0),
-    )?;
-    
-    if count == 0 {
-        return Err(AppError::InvalidInput("指定されたお酒が見つかりません".to_string()));
-    }
-    
-    // カテゴリーが存在するか確認
-    let category_count: i64 = db.conn().query_row(
-        "SELECT COUNT(*) FROM categories WHERE id = ?1",
-        params![request.category_id],
-        |row| row.get(0),
-    )?;
-    
-    if category_count == 0 {
-        return Err(AppError::InvalidInput("指定されたカテゴリーが見つかりません".to_string()));
-    }
-    
-    db.conn().execute(
-        "UPDATE beverages SET name = ?1, alcohol_content = ?2, category_id = ?3, updated_at = datetime('now', 'localtime') WHERE id = ?4",
-        params![request.name.trim(), request.alcohol_content, request.category_id, id],
-    )?;
+    let mut v = Validator::new();
+    v.unique_ids(
+        category_ids.iter().copied(),
+        |index| format!("category_ids[{}]", index),
+        ErrorCode::DuplicateCategory,
+    );
+    v.finish()?;
 
-    Ok(())
+    pool.write(move |db| {
+        let tx = db.conn().unchecked_transaction()?;
+        for (index, id) in category_ids.iter().enumerate() {
+            let updated = tx.execute(
+                "UPDATE categories SET display_order = ?1, updated_at = datetime('now', 'localtime') WHERE id = ?2",
+                params![index as i64 + 1, id],
+            )?;
+
+            if updated == 0 {
+                return Err(ErrorDetail::new(ErrorCode::CategoryNotFound).into());
+            }
+        }
+        tx.commit()?;
+        db.changes().categories(&category_ids);
+
+        Ok(())
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn delete_beverage(db: State<'_, Mutex<Database>>, id: i64) -> Result<(), AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    
-    // お酒が存在するか確認
-    let count: i64 = db.conn().query_row(
-        "SELECT COUNT(*) FROM beverages WHERE id = ?1",
-        params![id],
-        |row| row.get(0),
-    )?;
-    
-    if count == 0 {
-        return Err(AppError::InvalidInput("指定されたお酒が見つかりません".to_string()));
-    }
-    
-    // 使用されているかチェック（投稿に紐づいている場合）
-    let usage_count: i64 = db.conn().query_row(
-        "SELECT COUNT(*) FROM post_beverages WHERE beverage_id = ?1",
-        params![id],
-        |row| row.get(0),
-    )?;
-    
-    if usage_count > 0 {
-        return Err(AppError::InvalidInput(
-            format!("このお酒は{}件の投稿で使用されているため削除できません", usage_count)
-        ));
-    }
-    
-    db.conn().execute(
-        "DELETE FROM beverages WHERE id = ?1",
-        params![id],
-    )?;
+pub async fn delete_category(pool: State<'_, DbPool>, id: i64) -> Result<bool, AppError> {
+    pool.write(move |db| {
+        // 使用されているかチェック
+        let count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM beverages WHERE category_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
 
-    Ok(())
+        if count > 0 {
+            return Err(ErrorDetail::new(ErrorCode::CategoryInUse).into());
+        }
+
+        // 子カテゴリーがあるかチェック
+        let child_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM categories WHERE parent_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+
+        if child_count > 0 {
+            return Err(ErrorDetail::new(ErrorCode::CategoryHasChildren).into());
+        }
+
+        let tx = db.conn().unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM category_attributes WHERE category_id = ?1",
+            params![id],
+        )?;
+        i18n::delete_translations(&tx, NameTable::Categories, id)?;
+        tx.execute(
+            "DELETE FROM categories WHERE id = ?1",
+            params![id],
+        )?;
+        tx.commit()?;
+        db.changes().categories(&[id]);
+
+        Ok(true)
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_monthly_alcohol_intake(
-    db: State<'_, Mutex<Database>>,
-    year: i64,
-    month: i64,
-) -> Result<MonthlyAlcoholIntake, AppError> {
-    let db: MutexGuard<'_, Database> = db.lock().unwrap();
-    
-    // 指定された年月の開始日と終了日を計算
-    let start_date = format!("{:04}-{:02}-01", year, month);
-    let end_date = if month == 12 {
-        format!("{:04}-01-01", year + 1)
-    } else {
-        format!("{:04}-{:02}-01", year, month + 1)
-    };
+pub async fn get_category_attributes(
+    pool: State<'_, DbPool>,
+    category_id: i64,
+) -> Result<Vec<CategoryAttribute>, AppError> {
+    pool.read(move |db| attributes::get_category_attributes(db.conn(), category_id)).await
+}
 
-    // 指定された月の投稿を取得
-    let mut stmt = db.conn().prepare(
-        "SELECT 
-            p.id,
-            p.date
-        FROM posts p
-        WHERE p.date >= ?1 AND p.date < ?2
-        ORDER BY p.date"
-    )?;
+#[tauri::command]
+pub async fn create_category_attribute(
+    pool: State<'_, DbPool>,
+    category_id: i64,
+    request: CreateCategoryAttributeRequest,
+) -> Result<i64, AppError> {
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        let request = attributes::normalize_attribute_request(request)?;
 
-    let posts = stmt.query_map(params![start_date, end_date], |row| {
-        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
-    })?;
+        // カテゴリーが存在するか確認
+        let category_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM categories WHERE id = ?1",
+            params![category_id],
+            |row| row.get(0),
+        )?;
 
-    let posts_data: Vec<(i64, String)> = posts.collect::<Result<Vec<_>, _>>()?;
-    
-    let mut total_intake = 0.0;
-    let mut unique_dates = std::collections::HashSet::new();
+        if category_count == 0 {
+            return Err(ErrorDetail::new(ErrorCode::CategoryNotFound).into());
+        }
 
-    // 各投稿のアルコール摂取量を計算
-    for (post_id, date) in &posts_data {
-        unique_dates.insert(date.clone());
-        
-        let mut beverage_stmt = db.conn().prepare(
-            "SELECT 
-                pb.amount,
-                b.alcohol_content
-            FROM post_beverages pb
-            INNER JOIN beverages b ON pb.beverage_id = b.id
-            WHERE pb.post_id = ?"
+        // 属性名の重複チェック
+        let name_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM category_attributes WHERE category_id = ?1 AND name = ?2",
+            params![category_id, request.name],
+            |row| row.get(0),
         )?;
 
-        let beverages = beverage_stmt.query_map(params![post_id], |row| {
-            Ok((row.get::<_, f64>(0)?, row.get::<_, Option<f64>>(1)?))
-        })?;
+        if name_count > 0 {
+            return Err(validation::field_error("name", ErrorDetail::new(ErrorCode::AttributeNameExists)));
+        }
 
-        for beverage in beverages {
-            let (amount, alcohol_content) = beverage?;
-            if let Some(alc_content) = alcohol_content {
-                if alc_content > 0.0 {
-                    // 飲んだ容量(ml) * アルコール度数(%) / 100 * 0.8
-                    total_intake += amount * (alc_content / 100.0) * 0.8;
-                }
-            }
+        db.conn().execute(
+            "INSERT INTO category_attributes (category_id, name, value_type, unit, allowed_values, display_order)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                category_id,
+                request.name,
+                request.value_type.as_str(),
+                request.unit,
+                serde_json::to_string(&request.allowed_values.unwrap_or_default())?,
+                request.display_order.unwrap_or(0),
+            ],
+        )?;
+
+        db.changes().categories(&[category_id]);
+        Ok(db.conn().last_insert_rowid())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn update_category_attribute(
+    pool: State<'_, DbPool>,
+    id: i64,
+    request: CreateCategoryAttributeRequest,
+) -> Result<(), AppError> {
+    pool.write(move |db| {
+        let current = attributes::get_category_attribute(db.conn(), id)?;
+        validation::validate(db.conn(), &request)?;
+        let request = attributes::normalize_attribute_request(request)?;
+        let allowed_values = request.allowed_values.clone().unwrap_or_default();
+
+        // 属性名の重複チェック
+        let name_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM category_attributes WHERE category_id = ?1 AND name = ?2 AND id != ?3",
+            params![current.category_id, request.name, id],
+            |row| row.get(0),
+        )?;
+
+        if name_count > 0 {
+            return Err(validation::field_error("name", ErrorDetail::new(ErrorCode::AttributeNameExists)));
         }
-    }
 
-    let drinking_days = unique_dates.len() as i64;
-    
-    // 月の日数を計算（うるう年も考慮）
-    let days_in_month = match month {
-        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
-        4 | 6 | 9 | 11 => 30,
-        2 => {
-            // うるう年の判定
-            if (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0) {
-                29
-            } else {
-                28
+        let usage_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM beverage_attributes WHERE attribute_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+
+        // 既存の値が不正にならないよう、使用中の属性は型の変更を認めない
+        if usage_count > 0 && current.value_type != request.value_type {
+            return Err(validation::field_error(
+                "value_type",
+                ErrorDetail::new(ErrorCode::AttributeTypeInUse).param("usage_count", usage_count),
+            ));
+        }
+
+        if request.value_type == AttributeType::Select && usage_count > 0 {
+            let mut stmt = db.conn().prepare(
+                "SELECT DISTINCT value_text FROM beverage_attributes WHERE attribute_id = ?1",
+            )?;
+            let used_values = stmt.query_map(params![id], |row| row.get::<_, Option<String>>(0))?;
+            for value in used_values.collect::<Result<Vec<_>, _>>()?.into_iter().flatten() {
+                if !allowed_values.contains(&value) {
+                    return Err(validation::field_error(
+                        "allowed_values",
+                        ErrorDetail::new(ErrorCode::AllowedValueInUse).param("value", value),
+                    ));
+                }
             }
         }
-        _ => 30, // エラー時のデフォルト値
-    };
-    
-    // 1日当たりの平均摂取量は月の日数で割る（飲酒記録のない日も含む）
-    let average_per_day = total_intake / days_in_month as f64;
 
-    Ok(MonthlyAlcoholIntake {
-        total_intake,
-        average_per_day,
-        drinking_days,
+        db.conn().execute(
+            "UPDATE category_attributes SET
+                name = ?1,
+                value_type = ?2,
+                unit = ?3,
+                allowed_values = ?4,
+                display_order = ?5,
+                updated_at = datetime('now', 'localtime')
+            WHERE id = ?6",
+            params![
+                request.name,
+                request.value_type.as_str(),
+                request.unit,
+                serde_json::to_string(&allowed_values)?,
+                request.display_order.unwrap_or(current.display_order),
+                id,
+            ],
+        )?;
+        db.changes().categories(&[current.category_id]);
+
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_category_attribute(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| {
+        let attribute = attributes::get_category_attribute(db.conn(), id)?;
+
+        // 属性を削除すると、お酒に設定された値も合わせて削除される
+        let tx = db.conn().unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM beverage_attributes WHERE attribute_id = ?1",
+            params![id],
+        )?;
+        tx.execute(
+            "DELETE FROM category_attributes WHERE id = ?1",
+            params![id],
+        )?;
+        tx.commit()?;
+        db.changes().categories(&[attribute.category_id]);
+
+        Ok(())
+    })
+    .await
+}
+
+// お酒一覧取得時の共通カラム（beverage_from_rowと順序を合わせる。お酒・カテゴリー名は言語の設定に合わせる）
+fn beverage_columns() -> String {
+    format!(
+        "
+            b.id,
+            {} as localized_name,
+            b.alcohol_content,
+            b.category_id,
+            {} as category_name,
+            b.producer,
+            b.country,
+            b.region,
+            b.style,
+            b.volume_ml,
+            b.barcode,
+            b.notes,
+            b.serving_ml,
+            b.catalog_key,
+            b.archived,
+            b.favorite,
+            EXISTS (SELECT 1 FROM beverage_ingredients bi WHERE bi.beverage_id = b.id) as is_recipe,
+            b.created_at,
+            b.updated_at",
+        i18n::localized_name_sql(NameTable::Beverages, "b"),
+        i18n::localized_name_sql(NameTable::Categories, "c")
+    )
+}
+
+fn beverage_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Beverage> {
+    Ok(Beverage {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        alcohol_content: row.get(2)?,
+        category_id: row.get(3)?,
+        category_name: row.get(4)?,
+        producer: row.get(5)?,
+        country: row.get(6)?,
+        region: row.get(7)?,
+        style: row.get(8)?,
+        volume_ml: row.get(9)?,
+        barcode: row.get(10)?,
+        notes: row.get(11)?,
+        serving_ml: row.get(12)?,
+        catalog_key: row.get(13)?,
+        archived: row.get(14)?,
+        favorite: row.get(15)?,
+        is_recipe: row.get(16)?,
+        attributes: vec![],
+        created_at: row.get(17)?,
+        updated_at: row.get(18)?,
     })
 }
 
+/// お酒一覧を属性の絞り込み条件を適用して取得し、属性値を付与する（お気に入りを先頭に表示）
+///
+/// include_archivedがfalseの場合、アーカイブ済みのお酒とアーカイブ済みカテゴリーのお酒は除外する。
+/// 属性の絞り込み条件のパラメーターは、conditionのパラメーターに続けて番号を振る。
+fn query_beverages(
+    conn: &rusqlite::Connection,
+    condition: &str,
+    params: &[&dyn rusqlite::ToSql],
+    filters: Option<&[BeverageAttributeFilter]>,
+    include_archived: bool,
+) -> Result<Vec<Beverage>, AppError> {
+    let mut conditions = Vec::new();
+    if !condition.is_empty() {
+        conditions.push(format!("({})", condition));
+    }
+    if !include_archived {
+        conditions.push("b.archived = 0 AND c.archived = 0".to_string());
+    }
+    let (filter_conditions, filter_values) =
+        attributes::filter_conditions(filters.unwrap_or_default(), params.len() + 1);
+    conditions.extend(filter_conditions);
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+        FROM beverages b
+        INNER JOIN categories c ON b.category_id = c.id
+        {}
+        ORDER BY b.favorite DESC, localized_name",
+        beverage_columns(), where_clause
+    ))?;
+
+    let mut all_params = params.to_vec();
+    all_params.extend(filter_values.iter().map(|value| value as &dyn rusqlite::ToSql));
+
+    let beverages = stmt.query_map(all_params.as_slice(), beverage_from_row)?;
+    let mut beverages = beverages.collect::<Result<Vec<_>, _>>()?;
+
+    attributes::attach_attributes(conn, &mut beverages)?;
+
+    Ok(beverages)
+}
+
+#[tauri::command]
+pub async fn get_beverages(
+    pool: State<'_, DbPool>,
+    filters: Option<Vec<BeverageAttributeFilter>>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Beverage>, AppError> {
+    pool.read(move |db| {
+        query_beverages(
+            db.conn(),
+            "",
+            &[],
+            filters.as_deref(),
+            include_archived.unwrap_or(false),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_beverages_by_category(
+    pool: State<'_, DbPool>,
+    category_id: i64,
+    filters: Option<Vec<BeverageAttributeFilter>>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Beverage>, AppError> {
+    pool.read(move |db| {
+        query_beverages(
+            db.conn(),
+            "b.category_id = ?1",
+            params![category_id],
+            filters.as_deref(),
+            include_archived.unwrap_or(false),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn search_beverages(
+    pool: State<'_, DbPool>,
+    query: String,
+    include_archived: Option<bool>,
+) -> Result<Vec<Beverage>, AppError> {
+    pool.read(move |db| {
+        // LIKEのワイルドカード文字をエスケープして部分一致検索する
+        let pattern = format!(
+            "%{}%",
+            query.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+
+        // お酒・カテゴリー名はいずれかの言語の名前に一致すれば検索結果に含める
+        query_beverages(
+            db.conn(),
+            &format!(
+                "b.name LIKE ?1 ESCAPE '\\'
+                OR b.producer LIKE ?1 ESCAPE '\\'
+                OR b.country LIKE ?1 ESCAPE '\\'
+                OR b.region LIKE ?1 ESCAPE '\\'
+                OR b.style LIKE ?1 ESCAPE '\\'
+                OR b.barcode LIKE ?1 ESCAPE '\\'
+                OR b.notes LIKE ?1 ESCAPE '\\'
+                OR c.name LIKE ?1 ESCAPE '\\'
+                OR {}
+                OR {}",
+                i18n::any_translation_sql(NameTable::Beverages, "b", "t.name LIKE ?1 ESCAPE '\\'"),
+                i18n::any_translation_sql(NameTable::Categories, "c", "t.name LIKE ?1 ESCAPE '\\'")
+            ),
+            params![pattern],
+            None,
+            include_archived.unwrap_or(false),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_beverage_by_barcode(
+    pool: State<'_, DbPool>,
+    barcode: String,
+) -> Result<Option<Beverage>, AppError> {
+    pool.read(move |db| {
+        let barcode = validate_barcode(&barcode)?;
+
+        // スキャンしたバーコードはアーカイブ済みのお酒も解決する
+        let beverages = query_beverages(db.conn(), "b.barcode = ?1", params![barcode], None, true)?;
+
+        Ok(beverages.into_iter().next())
+    })
+    .await
+}
+
+/// 任意入力のテキストを前後の空白を除去して正規化する（空文字はNoneとして扱う）
+fn normalize_optional_text(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// JAN/EANバーコード（8桁または13桁）・UPC-A（12桁）の形式とチェックディジットを検証する
+///
+/// UPC-Aは先頭に0を付けたEAN-13にする（同じ商品をどちらの形式で読み取っても同じお酒になるように）。
+fn validate_barcode(barcode: &str) -> Result<String, AppError> {
+    let barcode = barcode.trim();
+    if !matches!(barcode.len(), 8 | 12 | 13) || !barcode.chars().all(|c| c.is_ascii_digit()) {
+        return Err(validation::field_error(
+            "barcode",
+            ErrorDetail::new(ErrorCode::BarcodeFormat),
+        ));
+    }
+
+    let digits: Vec<u32> = barcode.chars().filter_map(|c| c.to_digit(10)).collect();
+    let (body, check) = digits.split_at(digits.len() - 1);
+    // 右端（チェックディジットの左隣）から3,1,3,1...の重みで合計する
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+
+    if (10 - sum % 10) % 10 != check[0] {
+        return Err(validation::field_error(
+            "barcode",
+            ErrorDetail::new(ErrorCode::BarcodeChecksum),
+        ));
+    }
+
+    if barcode.len() == 12 {
+        return Ok(format!("0{}", barcode));
+    }
+    Ok(barcode.to_string())
+}
+
+/// お酒の登録・更新リクエストを検証し、保存用に正規化する
+fn normalize_beverage_request(
+    conn: &rusqlite::Connection,
+    request: CreateBeverageRequest,
+    beverage_id: Option<i64>,
+) -> Result<CreateBeverageRequest, AppError> {
+    validation::validate(conn, &request)?;
+
+    let barcode = match normalize_optional_text(request.barcode) {
+        Some(barcode) => Some(validate_barcode(&barcode)?),
+        None => None,
+    };
+
+    // バーコードの重複チェック（スキャン時に一意に特定できるようにする）
+    if let Some(barcode) = &barcode {
+        let existing: Option<String> = conn
+            .query_row(
+                "SELECT name FROM beverages WHERE barcode = ?1 AND id != ?2",
+                params![barcode, beverage_id.unwrap_or(0)],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if let Some(name) = existing {
+            return Err(validation::field_error(
+                "barcode",
+                ErrorDetail::new(ErrorCode::BarcodeExists).param("name", name),
+            ));
+        }
+    }
+
+    // 更新時は表示中の言語の名前から変更されていなければ登録名のままにする
+    let name = match beverage_id {
+        Some(id) => i18n::stored_name(conn, NameTable::Beverages, id, request.name.trim())?,
+        None => request.name.trim().to_string(),
+    };
+
+    Ok(CreateBeverageRequest {
+        name,
+        alcohol_content: request.alcohol_content,
+        category_id: request.category_id,
+        producer: normalize_optional_text(request.producer),
+        country: normalize_optional_text(request.country),
+        region: normalize_optional_text(request.region),
+        style: normalize_optional_text(request.style),
+        volume_ml: request.volume_ml,
+        barcode,
+        notes: normalize_optional_text(request.notes),
+        serving_ml: request.serving_ml,
+        attributes: request.attributes,
+    })
+}
+
+#[tauri::command]
+pub async fn create_beverage(
+    pool: State<'_, DbPool>,
+    request: CreateBeverageRequest,
+) -> Result<i64, AppError> {
+    pool.write(move |db| {
+        let request = normalize_beverage_request(db.conn(), request, None)?;
+        let tx = db.conn().unchecked_transaction()?;
+
+        tx.execute(
+            "INSERT INTO beverages (
+                name, alcohol_content, category_id,
+                producer, country, region, style, volume_ml, barcode, notes, serving_ml
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                request.name,
+                request.alcohol_content,
+                request.category_id,
+                request.producer,
+                request.country,
+                request.region,
+                request.style,
+                request.volume_ml,
+                request.barcode,
+                request.notes,
+                request.serving_ml,
+            ],
+        )?;
+
+        let beverage_id = tx.last_insert_rowid();
+
+        // カテゴリーの属性スキーマに沿って属性値を保存
+        if let Some(inputs) = &request.attributes {
+            attributes::replace_beverage_attributes(&tx, beverage_id, request.category_id, inputs)?;
+        }
+
+        tx.commit()?;
+        db.changes().beverages(&[beverage_id]);
+        Ok(beverage_id)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn update_beverage(
+    pool: State<'_, DbPool>,
+    id: i64,
+    request: CreateBeverageRequest,
+) -> Result<(), AppError> {
+    pool.write(move |db| {
+        // お酒が存在するか確認
+        let count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM beverages WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+
+        if count == 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageNotFound).into());
+        }
+
+        let request = normalize_beverage_request(db.conn(), request, Some(id))?;
+        let tx = db.conn().unchecked_transaction()?;
+
+        tx.execute(
+            "UPDATE beverages SET
+                name = ?1,
+                alcohol_content = ?2,
+                category_id = ?3,
+                producer = ?4,
+                country = ?5,
+                region = ?6,
+                style = ?7,
+                volume_ml = ?8,
+                barcode = ?9,
+                notes = ?10,
+                serving_ml = ?11,
+                updated_at = datetime('now', 'localtime')
+            WHERE id = ?12",
+            params![
+                request.name,
+                request.alcohol_content,
+                request.category_id,
+                request.producer,
+                request.country,
+                request.region,
+                request.style,
+                request.volume_ml,
+                request.barcode,
+                request.notes,
+                request.serving_ml,
+                id,
+            ],
+        )?;
+        i18n::drop_stale_translations(&tx, NameTable::Beverages, id)?;
+
+        // 属性値の指定がなければ既存の値を残し、カテゴリー変更で対象外になった値のみ削除する
+        match &request.attributes {
+            Some(inputs) => {
+                attributes::replace_beverage_attributes(&tx, id, request.category_id, inputs)?
+            }
+            None => attributes::remove_foreign_attributes(&tx, id, request.category_id)?,
+        }
+
+        // レシピの場合は度数を材料から再計算し、このお酒を材料に使うレシピにも反映する
+        recipes::refresh(&tx, id)?;
+
+        tx.commit()?;
+        db.changes().beverages(&[id]);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_beverage(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| {
+        // お酒が存在するか確認
+        let count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM beverages WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        
+        if count == 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageNotFound).into());
+        }
+        
+        // 使用されているかチェック（投稿に紐づいている場合）
+        let usage_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM post_beverages WHERE beverage_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        
+        if usage_count > 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageUsedInPosts)
+                .param("usage_count", usage_count)
+                .into());
+        }
+
+        // 他のお酒のレシピの材料として使われているかチェック
+        let ingredient_count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM beverage_ingredients WHERE ingredient_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+
+        if ingredient_count > 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageUsedInRecipes)
+                .param("usage_count", ingredient_count)
+                .into());
+        }
+
+        // 投稿テンプレートで使われているかチェック
+        let template_count: i64 = db.conn().query_row(
+            "SELECT COUNT(DISTINCT template_id) FROM post_template_beverages WHERE beverage_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+
+        if template_count > 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageUsedInTemplates)
+                .param("usage_count", template_count)
+                .into());
+        }
+        
+        let tx = db.conn().unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM beverage_attributes WHERE beverage_id = ?1",
+            params![id],
+        )?;
+        tx.execute(
+            "DELETE FROM beverage_ingredients WHERE beverage_id = ?1",
+            params![id],
+        )?;
+        i18n::delete_translations(&tx, NameTable::Beverages, id)?;
+        tx.execute(
+            "DELETE FROM beverages WHERE id = ?1",
+            params![id],
+        )?;
+        tx.commit()?;
+        db.changes().beverages(&[id]);
+
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn set_beverage_favorite(
+    pool: State<'_, DbPool>,
+    id: i64,
+    favorite: bool,
+) -> Result<(), AppError> {
+    pool.write(move |db| {
+        let updated = db.conn().execute(
+            "UPDATE beverages SET favorite = ?1, updated_at = datetime('now', 'localtime') WHERE id = ?2",
+            params![favorite, id],
+        )?;
+
+        if updated == 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageNotFound).into());
+        }
+        db.changes().beverages(&[id]);
+
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_suggested_beverages(
+    pool: State<'_, DbPool>,
+    limit: Option<i64>,
+) -> Result<Vec<SuggestedBeverage>, AppError> {
+    pool.read(move |db| {
+        let beverages = query_beverages(db.conn(), "", &[], None, false)?;
+        suggestions::suggest(db.conn(), beverages, limit.unwrap_or(10).max(1) as usize)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_beverage_recipe(
+    pool: State<'_, DbPool>,
+    beverage_id: i64,
+) -> Result<Option<BeverageRecipe>, AppError> {
+    pool.read(move |db| recipes::get_recipe(db.conn(), beverage_id)).await
+}
+
+/// お酒を材料のレシピとして定義する（空の場合はレシピを解除する）
+#[tauri::command]
+pub async fn set_beverage_recipe(
+    pool: State<'_, DbPool>,
+    beverage_id: i64,
+    ingredients: Vec<RecipeIngredientInput>,
+) -> Result<Option<BeverageRecipe>, AppError> {
+    pool.write(move |db| {
+        // お酒が存在するか確認
+        let count: i64 = db.conn().query_row(
+            "SELECT COUNT(*) FROM beverages WHERE id = ?1",
+            params![beverage_id],
+            |row| row.get(0),
+        )?;
+
+        if count == 0 {
+            return Err(ErrorDetail::new(ErrorCode::BeverageNotFound).into());
+        }
+
+        validation::validate(db.conn(), ingredients.as_slice())?;
+
+        let tx = db.conn().unchecked_transaction()?;
+        let recipe = recipes::set_recipe(&tx, beverage_id, &ingredients)?;
+        tx.commit()?;
+        db.changes().beverages(&[beverage_id]);
+
+        Ok(recipe)
+    })
+    .await
+}
+
+/// お酒・カテゴリーのアーカイブ状態を切り替える
+fn set_archived(
+    db: &Database,
+    table: &str,
+    id: i64,
+    archived: bool,
+) -> Result<(), AppError> {
+    let updated = db.conn().execute(
+        &format!(
+            "UPDATE {} SET archived = ?1, updated_at = datetime('now', 'localtime') WHERE id = ?2",
+            table
+        ),
+        params![archived, id],
+    )?;
+
+    if updated == 0 {
+        let code = if table == "beverages" {
+            ErrorCode::BeverageNotFound
+        } else {
+            ErrorCode::CategoryNotFound
+        };
+        return Err(ErrorDetail::new(code).into());
+    }
+
+    if table == "beverages" {
+        db.changes().beverages(&[id]);
+    } else {
+        db.changes().categories(&[id]);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn archive_beverage(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| set_archived(db, "beverages", id, true)).await
+}
+
+#[tauri::command]
+pub async fn unarchive_beverage(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| set_archived(db, "beverages", id, false)).await
+}
+
+#[tauri::command]
+pub async fn archive_category(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| set_archived(db, "categories", id, true)).await
+}
+
+#[tauri::command]
+pub async fn unarchive_category(pool: State<'_, DbPool>, id: i64) -> Result<(), AppError> {
+    pool.write(move |db| set_archived(db, "categories", id, false)).await
+}
+
+#[tauri::command]
+pub async fn preview_merge_beverages(
+    pool: State<'_, DbPool>,
+    source_ids: Vec<i64>,
+    target_id: i64,
+) -> Result<MergeBeveragesReport, AppError> {
+    // プレビューも書き込んでからロールバックするため書き込み用の接続で実行する
+    pool.write(move |db| merge::merge_beverages(db.conn(), &source_ids, target_id, false)).await
+}
+
+#[tauri::command]
+pub async fn merge_beverages(
+    pool: State<'_, DbPool>,
+    source_ids: Vec<i64>,
+    target_id: i64,
+) -> Result<MergeBeveragesReport, AppError> {
+    pool.write(move |db| {
+        let report = merge::merge_beverages(db.conn(), &source_ids, target_id, true)?;
+        db.changes().beverages(&source_ids);
+        db.changes().beverages(&[target_id]);
+        db.changes().posts(db.conn(), &report.affected_post_ids)?;
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_catalog_info(pool: State<'_, DbPool>) -> Result<CatalogInfo, AppError> {
+    pool.read(move |db| catalog::info(db.conn())).await
+}
+
+#[tauri::command]
+pub async fn import_catalog(
+    pool: State<'_, DbPool>,
+    categories: Option<Vec<String>>,
+) -> Result<CatalogImportResult, AppError> {
+    pool.write(move |db| {
+        let result = catalog::import(db.conn(), categories.as_deref())?;
+        // 追加したお酒・カテゴリーに加え、既存のお酒・カテゴリーの表示名（翻訳）も変わる場合がある
+        db.changes().all();
+        Ok(result)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_monthly_alcohol_intake(
+    pool: State<'_, DbPool>,
+    year: i64,
+    month: i64,
+) -> Result<MonthlyAlcoholIntake, AppError> {
+    pool.read(move |db| {
+        // 指定された年月の開始日と終了日を計算
+        let (start_date, end_date) = dates::month_range(year, month)?;
+
+        // 指定された月の投稿を取得（投稿日は日付の切り替え時刻を考慮した飲酒日）
+        let mut stmt = db.conn().prepare(
+            "SELECT 
+                p.id,
+                p.date
+            FROM posts p
+            WHERE p.active = 0 AND p.date >= ?1 AND p.date < ?2
+            ORDER BY p.date",
+        )?;
+
+        let posts = stmt.query_map(params![start_date, end_date], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, NaiveDate>(1)?))
+        })?;
+
+        let posts_data: Vec<(i64, NaiveDate)> = posts.collect::<Result<Vec<_>, _>>()?;
+        
+        let mut total_intake = 0.0;
+        let mut unique_dates = std::collections::HashSet::new();
+
+        // 各投稿のアルコール摂取量を計算
+        for (post_id, date) in &posts_data {
+            unique_dates.insert(*date);
+            
+            let mut beverage_stmt = db.conn().prepare(
+                "SELECT 
+                    pb.amount,
+                    b.alcohol_content
+                FROM post_beverages pb
+                INNER JOIN beverages b ON pb.beverage_id = b.id
+                WHERE pb.post_id = ?"
+            )?;
+
+            let beverages = beverage_stmt.query_map(params![post_id], |row| {
+                Ok((row.get::<_, f64>(0)?, row.get::<_, Option<f64>>(1)?))
+            })?;
+
+            for beverage in beverages {
+                let (amount, alcohol_content) = beverage?;
+                // 飲んだ容量(ml) * アルコール度数(%) / 100 * 0.8
+                total_intake += stats::pure_alcohol(amount, alcohol_content);
+            }
+        }
+
+        let drinking_days = unique_dates.len() as i64;
+        
+        // 月の日数を計算（うるう年も考慮）
+        let days_in_month = (end_date - start_date).num_days();
+        
+        // 1日当たりの平均摂取量は月の日数で割る（飲酒記録のない日も含む）
+        let average_per_day = total_intake / days_in_month as f64;
+
+        Ok(MonthlyAlcoholIntake {
+            total_intake,
+            average_per_day,
+            drinking_days,
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_drinking_calendar(
+    pool: State<'_, DbPool>,
+    year: i64,
+    month: i64,
+) -> Result<Vec<DailyAlcoholIntake>, AppError> {
+    pool.read(move |db| {
+        let (start_date, end_date) = dates::month_range(year, month)?;
+        stats::daily_intake(db.conn(), Some(start_date), Some(end_date))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_drinking_streaks(pool: State<'_, DbPool>) -> Result<DrinkingStreaks, AppError> {
+    pool.read(move |db| {
+        let today = settings::drinking_today(db.conn())?;
+        stats::drinking_streaks(db.conn(), today)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_drinking_day(pool: State<'_, DbPool>) -> Result<NaiveDate, AppError> {
+    pool.read(move |db| settings::drinking_today(db.conn())).await
+}
+
+/// エラーメッセージの雛形と項目名の一覧（localeは"ja"・"en"などの言語タグ。省略・未対応の場合は日本語）
+#[tauri::command]
+pub fn get_message_catalog(locale: Option<String>) -> messages::MessageCatalog {
+    let locale = locale
+        .as_deref()
+        .and_then(Locale::from_tag)
+        .unwrap_or_default();
+    messages::catalog(locale)
+}
+
+#[tauri::command]
+pub async fn get_settings(pool: State<'_, DbPool>) -> Result<AppSettings, AppError> {
+    pool.read(move |db| settings::get_settings(db.conn())).await
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    pool: State<'_, DbPool>,
+    request: UpdateSettingsRequest,
+) -> Result<AppSettings, AppError> {
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        let tx = db.conn().unchecked_transaction()?;
+        let settings = settings::update_settings(&tx, request)?;
+        tx.commit()?;
+        Locale::set_current(settings.language);
+        // 言語・日付の切り替え時刻の変更は、表示名・飲酒日の集計のすべてに影響する
+        db.changes().all();
+        Ok(settings)
+    })
+    .await
+}
+
+/// データベースの状態（lockedの間はunlock_databaseでパスフレーズを入力し、in_use_byがある場合は使用中のアプリの終了後にreopen_databaseで開き直す）
+#[tauri::command]
+pub fn get_database_status(pool: State<'_, DbPool>) -> DatabaseStatus {
+    match pool.config() {
+        Ok(config) => DatabaseStatus {
+            locked: false,
+            encrypted: config.passphrase.is_some(),
+            in_use_by: None,
+        },
+        Err(AppError::DatabaseInUse(host)) => DatabaseStatus {
+            locked: false,
+            encrypted: false,
+            in_use_by: Some(host),
+        },
+        Err(error) => {
+            let locked = matches!(error, AppError::PassphraseRequired);
+            DatabaseStatus { locked, encrypted: locked, in_use_by: None }
+        }
+    }
+}
+
+/// 他のアプリが使用中で開けなかったデータベースを開き直す（そのアプリを終了した後）
+#[tauri::command]
+pub async fn reopen_database(
+    pool: State<'_, DbPool>,
+    app_lock: State<'_, AppLock>,
+) -> Result<DatabaseStatus, AppError> {
+    if !pool.is_open() {
+        let path = pool.path().ok_or(AppError::DatabaseClosed)?;
+        match pool.reopen(move |_| Ok(DbConfig { path, passphrase: None })).await {
+            Ok(()) | Err(AppError::PassphraseRequired) => {}
+            Err(error) => return Err(error),
+        }
+        if pool.is_open() {
+            let app_lock = app_lock.inner().clone();
+            pool.read(move |db| app_lock.load(db.conn(), true)).await?;
+        }
+    }
+    Ok(get_database_status(pool))
+}
+
+/// パスフレーズを入力して、暗号化されたデータベースを開く（起動時・プロファイルの切り替え後は、PINが設定されていればロックする）
+#[tauri::command]
+pub async fn unlock_database(
+    pool: State<'_, DbPool>,
+    app_lock: State<'_, AppLock>,
+    passphrase: String,
+) -> Result<(), AppError> {
+    encryption::unlock(&pool, passphrase).await?;
+    let app_lock = app_lock.inner().clone();
+    pool.read(move |db| app_lock.load(db.conn(), false)).await
+}
+
+/// PINを入力してアプリのロックを解除する（ロック中も実行できる唯一のコマンド）
+#[tauri::command]
+pub async fn unlock_app(
+    pool: State<'_, DbPool>,
+    app_lock: State<'_, AppLock>,
+    pin: String,
+) -> Result<(), AppError> {
+    pool.write(move |db| app_lock::verify_pin(db.conn(), &pin)).await?;
+    app_lock.unlock();
+    Ok(())
+}
+
+/// PINが設定されていれば、すぐにアプリをロックする
+#[tauri::command]
+pub fn lock_app(app_lock: State<'_, AppLock>) {
+    app_lock.lock();
+}
+
+#[tauri::command]
+pub async fn get_app_lock_settings(pool: State<'_, DbPool>) -> Result<AppLockSettings, AppError> {
+    pool.read(move |db| app_lock_settings(db.conn())).await
+}
+
+fn app_lock_settings(conn: &rusqlite::Connection) -> Result<AppLockSettings, AppError> {
+    let idle_timeout_minutes = app_lock::idle_timeout_minutes(conn)?;
+    Ok(AppLockSettings {
+        enabled: idle_timeout_minutes.is_some(),
+        idle_timeout_minutes,
+    })
+}
+
+/// PINを設定・変更する（既にPINが設定されている場合は現在のPINが必要）
+#[tauri::command]
+pub async fn set_app_lock_pin(
+    pool: State<'_, DbPool>,
+    app_lock: State<'_, AppLock>,
+    request: SetAppLockPinRequest,
+) -> Result<AppLockSettings, AppError> {
+    let app_lock = app_lock.inner().clone();
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        app_lock::verify_pin(db.conn(), request.current_pin.as_deref().unwrap_or_default())?;
+        let idle_timeout_minutes = match request.idle_timeout_minutes {
+            Some(minutes) => minutes,
+            None => app_lock::idle_timeout_minutes(db.conn())?.unwrap_or(app_lock::DEFAULT_IDLE_TIMEOUT_MINUTES),
+        };
+        app_lock::set_pin(db.conn(), &request.pin, idle_timeout_minutes)?;
+        app_lock.load(db.conn(), false)?;
+        app_lock_settings(db.conn())
+    })
+    .await
+}
+
+/// PINを削除してアプリのロックを無効にする
+#[tauri::command]
+pub async fn disable_app_lock(
+    pool: State<'_, DbPool>,
+    app_lock: State<'_, AppLock>,
+    current_pin: String,
+) -> Result<AppLockSettings, AppError> {
+    let app_lock = app_lock.inner().clone();
+    pool.write(move |db| {
+        app_lock::verify_pin(db.conn(), &current_pin)?;
+        app_lock::clear_pin(db.conn())?;
+        app_lock.load(db.conn(), false)?;
+        app_lock_settings(db.conn())
+    })
+    .await
+}
+
+/// データベースのパスフレーズを変更する（暗号化していない場合はcurrent_passphrase、暗号化を解除する場合はnew_passphraseを省略）
+#[tauri::command]
+pub async fn change_database_passphrase(
+    pool: State<'_, DbPool>,
+    backups: State<'_, BackupStore>,
+    current_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+) -> Result<(), AppError> {
+    if new_passphrase.as_deref().is_some_and(str::is_empty) {
+        return Err(validation::field_error(
+            "new_passphrase",
+            ErrorDetail::new(ErrorCode::Required).label(Label::Passphrase),
+        ));
+    }
+    encryption::change_passphrase(&pool, &backups, current_passphrase, new_passphrase).await
+}
+
+/// バックアップの一覧（新しい順）
+#[tauri::command]
+pub async fn list_backups(
+    pool: State<'_, DbPool>,
+    backups: State<'_, BackupStore>,
+) -> Result<Vec<BackupInfo>, AppError> {
+    let passphrase = pool.config()?.passphrase;
+    backups.list(passphrase.as_deref())
+}
+
+#[tauri::command]
+pub async fn create_backup_now(
+    pool: State<'_, DbPool>,
+    backups: State<'_, BackupStore>,
+) -> Result<BackupInfo, AppError> {
+    backups.create(&pool).await
+}
+
+/// バックアップから復元する（復元前のデータもバックアップとして残す）
+#[tauri::command]
+pub async fn restore_backup(
+    pool: State<'_, DbPool>,
+    backups: State<'_, BackupStore>,
+    id: String,
+) -> Result<BackupInfo, AppError> {
+    backups.restore(&pool, &id).await
+}
+
+#[tauri::command]
+pub fn get_profiles(profiles: State<'_, Profiles>) -> Vec<Profile> {
+    profiles.list()
+}
+
+/// プロファイルを作成する（database_pathを指定した場合は、同期フォルダーなどのそのデータベースを使う）
+#[tauri::command]
+pub fn create_profile(
+    profiles: State<'_, Profiles>,
+    name: String,
+    database_path: Option<String>,
+) -> Result<Profile, AppError> {
+    let mut v = Validator::new();
+    v.name("name", &name, Label::ProfileName);
+    if database_path.as_deref().is_some_and(|path| !Path::new(path).is_absolute()) {
+        v.add("database_path", ErrorDetail::new(ErrorCode::DatabasePathNotAbsolute));
+    }
+    v.finish()?;
+
+    profiles.create(&name, database_path.map(PathBuf::from))
+}
+
+#[tauri::command]
+pub fn rename_profile(profiles: State<'_, Profiles>, id: String, name: String) -> Result<Profile, AppError> {
+    let mut v = Validator::new();
+    v.name("name", &name, Label::ProfileName);
+    v.finish()?;
+
+    profiles.rename(&id, &name)
+}
+
+/// プロファイルを切り替える（切り替え先が暗号化されている場合は、返した状態のlockedがtrueになる）
+#[tauri::command]
+pub async fn switch_profile(
+    pool: State<'_, DbPool>,
+    backups: State<'_, BackupStore>,
+    app_lock: State<'_, AppLock>,
+    profiles: State<'_, Profiles>,
+    id: String,
+) -> Result<DatabaseStatus, AppError> {
+    profiles.switch(&pool, &backups, &app_lock, &id).await?;
+    Ok(get_database_status(pool))
+}
+
+/// プロファイルとそのデータを削除する（使用中のプロファイルは削除できない）
+#[tauri::command]
+pub fn delete_profile(profiles: State<'_, Profiles>, id: String) -> Result<(), AppError> {
+    profiles.delete(&id)
+}
+
+#[tauri::command]
+pub async fn get_wellbeing_logs(
+    pool: State<'_, DbPool>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<Vec<WellbeingLog>, AppError> {
+    pool.read(move |db| {
+        wellbeing::get_logs(
+            db.conn(),
+            dates::parse_optional_date(start_date.as_deref())?,
+            dates::parse_optional_date(end_date.as_deref())?,
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn save_wellbeing_log(
+    pool: State<'_, DbPool>,
+    request: SaveWellbeingLogRequest,
+) -> Result<WellbeingLog, AppError> {
+    pool.write(move |db| {
+        validation::validate(db.conn(), &request)?;
+        let log = wellbeing::save_log(db.conn(), request)?;
+        db.changes().wellbeing(log.date);
+        Ok(log)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_wellbeing_log(pool: State<'_, DbPool>, date: String) -> Result<(), AppError> {
+    let date = dates::parse_date(&date)?;
+    pool.write(move |db| {
+        db.conn().execute(
+            "DELETE FROM wellbeing_logs WHERE date = ?1",
+            params![date],
+        )?;
+        db.changes().wellbeing(date);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_wellbeing_analysis(pool: State<'_, DbPool>) -> Result<WellbeingAnalysis, AppError> {
+    pool.read(move |db| wellbeing::analyze(db.conn())).await
+}
+
+#[tauri::command]
+pub async fn get_category_alcohol_intake(
+    pool: State<'_, DbPool>,
+    year: i64,
+    month: i64,
+    rollup: Option<bool>,
+    attribute_recipes: Option<bool>,
+) -> Result<Vec<CategoryAlcoholIntake>, AppError> {
+    pool.read(move |db| {
+        let (start_date, end_date) = dates::month_range(year, month)?;
+        stats::category_intake(
+            db.conn(),
+            start_date,
+            end_date,
+            rollup.unwrap_or(false),
+            attribute_recipes.unwrap_or(false),
+        )
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_beverage_alcohol_intake(
+    pool: State<'_, DbPool>,
+    year: i64,
+    month: i64,
+    attribute_recipes: Option<bool>,
+) -> Result<Vec<BeverageAlcoholIntake>, AppError> {
+    pool.read(move |db| {
+        let (start_date, end_date) = dates::month_range(year, month)?;
+        stats::beverage_intake(db.conn(), start_date, end_date, attribute_recipes.unwrap_or(false))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_code(result: Result<String, AppError>) -> ErrorCode {
+        match result {
+            Err(AppError::Validation(errors)) => errors[0].code,
+            other => panic!("検証エラーになりませんでした: {:?}", other.ok()),
+        }
+    }
+
+    #[test]
+    fn validate_barcode_accepts_valid_codes() {
+        let cases = [
+            // EAN-8
+            ("49123456", "49123456"),
+            // EAN-13（JAN）
+            ("4901777018686", "4901777018686"),
+            ("4006381333931", "4006381333931"),
+            // UPC-AはEAN-13にする
+            ("036000291452", "0036000291452"),
+            // 前後の空白は除く
+            (" 4901777018686 ", "4901777018686"),
+        ];
+        for (barcode, expected) in cases {
+            assert_eq!(validate_barcode(barcode).unwrap(), expected, "{}", barcode);
+        }
+    }
+
+    #[test]
+    fn validate_barcode_rejects_invalid_codes() {
+        let cases = [
+            // チェックディジットが違う
+            ("49123450", ErrorCode::BarcodeChecksum),
+            ("4901777018687", ErrorCode::BarcodeChecksum),
+            ("036000291453", ErrorCode::BarcodeChecksum),
+            // 桁数が違う
+            ("", ErrorCode::BarcodeFormat),
+            ("4912345", ErrorCode::BarcodeFormat),
+            ("49017770186", ErrorCode::BarcodeFormat),
+            ("49017770186860", ErrorCode::BarcodeFormat),
+            // 数字以外を含む
+            ("4901777O18686", ErrorCode::BarcodeFormat),
+            ("4901-777-01868", ErrorCode::BarcodeFormat),
+            ("４９０１７７７０１８６８６", ErrorCode::BarcodeFormat),
+        ];
+        for (barcode, expected) in cases {
+            assert_eq!(error_code(validate_barcode(barcode)), expected, "{}", barcode);
+        }
+    }
+
+    #[test]
+    fn query_beverages_filters_by_attributes() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let category_id: i64 = conn
+            .query_row("SELECT MIN(id) FROM categories", [], |row| row.get(0))
+            .unwrap();
+        let attribute = |name: &str, value_type: &str| {
+            conn.execute(
+                "INSERT INTO category_attributes (category_id, name, value_type) VALUES (?1, ?2, ?3)",
+                params![category_id, name, value_type],
+            )
+            .unwrap();
+            conn.last_insert_rowid()
+        };
+        let polish = attribute("精米歩合", "integer");
+        let grade = attribute("特定名称", "text");
+        let sparkling = attribute("発泡", "boolean");
+
+        let beverage = |name: &str, values: &[(i64, Option<&str>, Option<f64>)]| {
+            conn.execute(
+                "INSERT INTO beverages (name, alcohol_content, category_id) VALUES (?1, 15.0, ?2)",
+                params![name, category_id],
+            )
+            .unwrap();
+            let beverage_id = conn.last_insert_rowid();
+            for (attribute_id, text, number) in values {
+                conn.execute(
+                    "INSERT INTO beverage_attributes (beverage_id, attribute_id, value_text, value_number)
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![beverage_id, attribute_id, text, number],
+                )
+                .unwrap();
+            }
+            beverage_id
+        };
+        let daiginjo = beverage("大吟醸", &[(polish, None, Some(40.0)), (grade, Some("大吟醸"), None)]);
+        let junmai = beverage(
+            "純米",
+            &[(polish, None, Some(65.0)), (grade, Some("純米"), None), (sparkling, None, Some(1.0))],
+        );
+        let futsu = beverage("普通酒", &[]);
+
+        let names = |condition: &str, params: &[&dyn rusqlite::ToSql], filters: serde_json::Value| {
+            let filters: Vec<BeverageAttributeFilter> = serde_json::from_value(filters).unwrap();
+            let mut ids: Vec<i64> = query_beverages(conn, condition, params, Some(&filters), false)
+                .unwrap()
+                .iter()
+                .map(|beverage| beverage.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(names("", &[], serde_json::json!([])), vec![daiginjo, junmai, futsu]);
+        // 範囲は両端を含み、属性値のないお酒は除く
+        assert_eq!(
+            names("", &[], serde_json::json!([{ "attribute_id": polish, "max": 40 }])),
+            vec![daiginjo]
+        );
+        assert_eq!(
+            names("", &[], serde_json::json!([{ "attribute_id": polish, "min": 40, "max": 65 }])),
+            vec![daiginjo, junmai]
+        );
+        // 完全一致は属性の型に応じて比較する
+        assert_eq!(
+            names("", &[], serde_json::json!([{ "attribute_id": grade, "value": "純米" }])),
+            vec![junmai]
+        );
+        assert_eq!(
+            names("", &[], serde_json::json!([{ "attribute_id": polish, "value": 40 }])),
+            vec![daiginjo]
+        );
+        assert_eq!(
+            names("", &[], serde_json::json!([{ "attribute_id": sparkling, "value": true }])),
+            vec![junmai]
+        );
+        assert!(names("", &[], serde_json::json!([{ "attribute_id": sparkling, "value": 1 }])).is_empty());
+        assert!(names("", &[], serde_json::json!([{ "attribute_id": grade, "min": 0 }])).is_empty());
+        // 複数の条件はすべて満たすものに絞り込み、条件のパラメーターと番号が重ならない
+        assert_eq!(
+            names(
+                "b.name <> ?1",
+                params!["大吟醸"],
+                serde_json::json!([
+                    { "attribute_id": polish, "min": 30 },
+                    { "attribute_id": grade, "value": "純米" }
+                ])
+            ),
+            vec![junmai]
+        );
+
+        // 属性値は一覧に含まれるお酒の分だけ付与する
+        let beverages = query_beverages(conn, "b.id = ?1", params![junmai], None, false).unwrap();
+        assert_eq!(beverages.len(), 1);
+        assert_eq!(beverages[0].attributes.len(), 3);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::{ErrorCode, Label};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+// 日付・日時の保存形式（SQLiteのdate()・datetime()と同じ）
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+pub const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// 日時・時刻として受け付ける入力形式（秒以下は切り捨てる）
+const DATETIME_INPUT_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+];
+const TIME_INPUT_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M"];
+// マイグレーションで既存の日付を修復する際に解釈を試みる形式
+const LEGACY_DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d", "%Y%m%d"];
+
+fn invalid_date(value: &str) -> ErrorDetail {
+    ErrorDetail::new(ErrorCode::InvalidDate).param("value", value)
+}
+
+fn invalid_datetime(value: &str, label: Label) -> ErrorDetail {
+    ErrorDetail::new(ErrorCode::InvalidDatetime)
+        .label(label)
+        .param("value", value)
+}
+
+/// 日付（YYYY-MM-DD）の入力を検証する
+pub fn parse_date(value: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+        .map_err(|_| invalid_date(value).into())
+}
+
+/// 省略可能な日付の入力を検証する
+pub fn parse_optional_date(value: Option<&str>) -> Result<Option<NaiveDate>, AppError> {
+    value.map(parse_date).transpose()
+}
+
+/// 日時（YYYY-MM-DD HH:MM[:SS]）の入力を検証する（labelはエラーメッセージに使う項目名）
+pub fn parse_datetime(value: &str, label: Label) -> Result<NaiveDateTime, AppError> {
+    let trimmed = value.trim();
+    DATETIME_INPUT_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(trimmed, format).ok())
+        .and_then(|datetime| datetime.with_nanosecond(0))
+        .ok_or_else(|| invalid_datetime(value, label).into())
+}
+
+/// 時刻のみ（HH:MM[:SS]）の入力であれば時刻として解釈する
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    let trimmed = value.trim();
+    TIME_INPUT_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(trimmed, format).ok())
+}
+
+/// 既存データの日付を、よくある別形式や日時の先頭部分も含めて解釈する（修復できない場合はNone）
+pub fn parse_legacy_date(value: &str) -> Option<NaiveDate> {
+    let trimmed = value.trim();
+    LEGACY_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(trimmed, format).ok())
+        .or_else(|| {
+            let prefix = trimmed.get(..10)?;
+            LEGACY_DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(prefix, format).ok())
+        })
+}
+
+/// 指定された年月の開始日（含む）と翌月の開始日（含まない）を返す
+pub fn month_range(year: i64, month: i64) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let invalid = || -> AppError {
+        ErrorDetail::new(ErrorCode::InvalidMonth)
+            .param("year", year)
+            .param("month", month)
+            .into()
+    };
+    let year = i32::try_from(year).map_err(|_| invalid())?;
+    let month = u32::try_from(month).map_err(|_| invalid())?;
+
+    let start = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid)?;
+    let end = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .ok_or_else(invalid)?;
+    Ok((start, end))
+}
+
+/// 日付をYYYY-MM-DD形式でやり取りする（serde(with)用、不正な入力は日本語のエラーにする）
+pub mod date_format {
+    use super::*;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&value.format(DATE_FORMAT))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+            .map_err(|_| de::Error::custom(invalid_date(&value)))
+    }
+}
+
+/// 日時をYYYY-MM-DD HH:MM:SS形式でやり取りする（serde(with)用）
+pub mod datetime_format {
+    use super::*;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&value.format(DATETIME_FORMAT))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse_datetime(&value, Label::Datetime)
+            .map_err(|_| de::Error::custom(invalid_datetime(&value, Label::Datetime)))
+    }
+}
+
+/// 省略可能な日時をYYYY-MM-DD HH:MM:SS形式でやり取りする（serde(with)用）
+pub mod option_datetime_format {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::datetime_format::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::datetime_format")] NaiveDateTime);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
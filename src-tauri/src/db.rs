@@ -1,28 +1,283 @@
-use rusqlite::{Connection, Result, params};
-use rusqlite_migration::{Migrations, M};
-use std::path::PathBuf;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, ErrorCode, OpenFlags, Result, Transaction, params};
+use rusqlite_migration::{HookError, HookResult, Migrations, M};
+use std::cell::{RefCell, RefMut};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::catalog;
+use crate::dates;
 use crate::error::AppError;
+use crate::events::ChangeSet;
+use crate::i18n::{self, NameTable};
+use crate::messages::Locale;
+use crate::settings;
+
+// 他の接続（sqlite3コマンドなど）が書き込み中の場合に待つ時間
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// バックアップ・復元で1回にコピーするページ数
+const BACKUP_PAGES_PER_STEP: std::os::raw::c_int = 256;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
+
+// マイグレーション一覧（必ず時系列順に末尾へ追加する）
+fn migrations() -> Vec<M<'static>> {
+    vec![
+        M::up(include_str!("migrations/001_remove_title_add_date.sql")),
+        M::up(include_str!("migrations/002_add_beverage_metadata.sql")),
+        M::up(include_str!("migrations/003_add_category_attributes.sql")),
+        M::up(include_str!("migrations/004_add_beverage_catalog.sql")),
+        M::up(include_str!("migrations/005_add_archived_flags.sql")),
+        M::up(include_str!("migrations/006_add_category_parent.sql")),
+        M::up(include_str!("migrations/007_add_beverage_ingredients.sql")),
+        M::up(include_str!("migrations/008_add_beverage_favorite.sql")),
+        M::up(include_str!("migrations/009_add_post_templates.sql")),
+        M::up(include_str!("migrations/010_add_post_sessions.sql")),
+        M::up(include_str!("migrations/011_add_settings.sql")),
+        M::up(include_str!("migrations/012_add_live_sessions.sql")),
+        M::up(include_str!("migrations/013_add_wellbeing_logs.sql")),
+        M::up_with_hook(include_str!("migrations/014_normalize_dates.sql"), repair_malformed_dates),
+        M::up_with_hook(include_str!("migrations/015_add_localized_names.sql"), link_bundled_translations),
+        M::up(include_str!("migrations/016_add_catalog_values.sql")),
+    ]
+}
+
+/// 既存の同梱カテゴリー・カタログのお酒にキーと言語ごとの名前を付与する（名前を変更したものはそのまま）
+fn link_bundled_translations(tx: &Transaction) -> HookResult {
+    i18n::link_bundled_translations(tx).map_err(|error| HookError::Hook(error.to_string()))
+}
+
+/// 日付（posts.date・wellbeing_logs.date）が不正な形式の行を修復する
+///
+/// YYYY/MM/DDなどの別形式は解釈して正規化し、解釈できない場合（存在しない日付を含む）は作成日時の日付にする。
+/// 体調の記録で修復後の日付が既存の記録と重複する場合は、不正な行を削除する。
+fn repair_malformed_dates(tx: &Transaction) -> HookResult {
+    let today: String = tx.query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))?;
+
+    for table in ["posts", "wellbeing_logs"] {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT id, date, created_at FROM {} WHERE date IS NOT date(date)",
+            table
+        ))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        for (id, date, created_at) in rows {
+            let original = date.unwrap_or_default();
+            let repaired = dates::parse_legacy_date(&original)
+                .or_else(|| dates::parse_legacy_date(&created_at))
+                .map(|date| date.format(dates::DATE_FORMAT).to_string())
+                .unwrap_or_else(|| today.clone());
+
+            if table == "wellbeing_logs" {
+                let duplicate: i64 = tx.query_row(
+                    "SELECT COUNT(*) FROM wellbeing_logs WHERE date = ?1 AND id != ?2",
+                    params![repaired, id],
+                    |row| row.get(0),
+                )?;
+                if duplicate > 0 {
+                    log::warn!(
+                        "wellbeing_logs(id={})の日付「{}」を修復できないため削除します（{}の記録が既に存在します）",
+                        id, original, repaired
+                    );
+                    tx.execute("DELETE FROM wellbeing_logs WHERE id = ?1", params![id])?;
+                    continue;
+                }
+            }
+
+            log::warn!("{}(id={})の日付「{}」を「{}」に修復しました", table, id, original, repaired);
+            tx.execute(
+                &format!("UPDATE {} SET date = ?1 WHERE id = ?2", table),
+                params![repaired, id],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// SQLCipherのsqlcipher_exportで、指定したパスフレーズで暗号化したコピーを作成する（Noneの場合は暗号化しない）
+///
+/// sqlcipher_exportはuser_version（スキーマのバージョン）をコピーしないため、別に設定する。
+pub fn export(conn: &Connection, path: &Path, passphrase: Option<&str>) -> Result<(), AppError> {
+    let user_version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS export KEY ?2",
+        params![path.to_string_lossy(), passphrase.unwrap_or("")],
+    )?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))
+        .and_then(|_| conn.execute_batch(&format!("PRAGMA export.user_version = {}", user_version)));
+    conn.execute("DETACH DATABASE export", [])?;
+    Ok(exported?)
+}
+
+/// データベースのファイル名の後ろに付けたパス（-wal・-shmファイルやロックファイルなど）
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
 
 pub struct Database {
     conn: Connection,
+    path: PathBuf,
+    // SQLCipherで暗号化している場合のパスフレーズ（バックアップも同じパスフレーズで暗号化する）
+    passphrase: Option<String>,
+    // 書き込み処理中に記録した変更（DbPool::writeが処理の成功後にイベントとして送る）
+    changes: RefCell<ChangeSet>,
 }
 
 impl Database {
-    pub fn new(db_path: PathBuf) -> Result<Self, AppError> {
-        let mut conn = Connection::open(db_path)?;
-        
+    pub fn new(db_path: PathBuf, passphrase: Option<String>) -> Result<Self, AppError> {
+        let conn = Self::open_connection(&db_path, OpenFlags::default(), passphrase.as_deref())?;
+        // 書き込み中も読み取り用の接続から読めるようWALモードにする（設定はファイルに保存される）
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        let mut db = Database { conn, path: db_path, passphrase, changes: RefCell::default() };
+        db.upgrade()?;
+        Ok(db)
+    }
+
+    /// 接続を開き、暗号化されている場合はパスフレーズを設定する
+    ///
+    /// SQLCipherはパスフレーズが違っても開く時点ではエラーにならないため、読み取れるか確認する。
+    pub fn open_connection(path: &Path, flags: OpenFlags, passphrase: Option<&str>) -> Result<Connection, AppError> {
+        let conn = Connection::open_with_flags(path, flags)?;
+        if let Some(passphrase) = passphrase {
+            conn.pragma_update(None, "key", passphrase)?;
+        }
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+
+        match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+            Ok(_) => Ok(conn),
+            Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == ErrorCode::NotADatabase => {
+                Err(match passphrase {
+                    Some(_) => AppError::WrongPassphrase,
+                    None => AppError::PassphraseRequired,
+                })
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// このアプリのスキーマのバージョン（適用するマイグレーションの数。PRAGMA user_versionと比較する）
+    pub fn schema_version() -> i64 {
+        migrations().len() as i64
+    }
+
+    /// 最新のスキーマに更新し、初期データを作成する（起動時・バックアップからの復元後）
+    fn upgrade(&mut self) -> Result<(), AppError> {
+        // 新規インストール時は最新スキーマでテーブルを作成し、全マイグレーションを適用済みとして扱う
+        // （マイグレーションは既存テーブルの存在を前提としているため）
+        if self.is_new_database()? {
+            log::info!("新規データベースを作成します");
+            self.create_tables()?;
+            self.conn.pragma_update(None, "user_version", Self::schema_version())?;
+        }
+
         // マイグレーション実行（&mutが必要）
-        let migrations = Migrations::new(vec![
-            M::up(include_str!("migrations/001_remove_title_add_date.sql")),
-        ]);
-        
+        let migrations = Migrations::new(migrations());
+
         log::info!("マイグレーションを実行中...");
-        migrations.to_latest(&mut conn)?;
+        migrations.to_latest(&mut self.conn)?;
         log::info!("マイグレーションが完了しました");
-        
-        let db = Database { conn };
-        db.init()?;
-        Ok(db)
+
+        self.init()?;
+        Locale::set_current(settings::language(&self.conn)?);
+        Ok(())
+    }
+
+    /// SQLiteのオンラインバックアップでファイルにコピーする（書き込み中でも一貫した内容になる）
+    pub fn backup_to(&self, path: &Path) -> Result<(), AppError> {
+        let mut dest = Self::open_connection(path, OpenFlags::default(), self.passphrase.as_deref())?;
+        {
+            let backup = Backup::new(&self.conn, &mut dest)?;
+            backup.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)?;
+        }
+        // WALモードのままだと開くたびに-wal・-shmファイルが作られるため、単体のファイルにする
+        dest.pragma_update_and_check(None, "journal_mode", "DELETE", |row| row.get::<_, String>(0))?;
+        Ok(())
+    }
+
+    /// 指定したパスフレーズで暗号化したコピーを作成する（Noneの場合は暗号化しない）
+    pub fn export_to(&self, path: &Path, passphrase: Option<&str>) -> Result<(), AppError> {
+        export(&self.conn, path, passphrase)
+    }
+
+    /// バックアップの内容で置き換え、最新のスキーマに更新する（バックアップの検証は呼び出し側で行う）
+    pub fn restore_from(&mut self, path: &Path) -> Result<(), AppError> {
+        let source = Self::open_connection(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            self.passphrase.as_deref(),
+        )?;
+        {
+            let backup = Backup::new(&source, &mut self.conn)?;
+            backup.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)?;
+        }
+        self.upgrade()
+    }
+
+    /// 読み取り専用の接続を開く（マイグレーション・初期データの作成はDatabase::newで済ませておく）
+    pub fn open_reader(db_path: &Path, passphrase: Option<&str>) -> Result<Self, AppError> {
+        let conn = Self::open_connection(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            passphrase,
+        )?;
+        Ok(Database {
+            conn,
+            path: db_path.to_path_buf(),
+            passphrase: passphrase.map(str::to_string),
+            changes: RefCell::default(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn passphrase(&self) -> Option<&str> {
+        self.passphrase.as_deref()
+    }
+
+    /// 他の接続による書き込みのたびに変わる値（この接続自身の書き込みでは変わらない）
+    pub fn data_version(&self) -> Result<i64, AppError> {
+        Ok(self.conn.pragma_query_value(None, "data_version", |row| row.get(0))?)
+    }
+
+    /// 変更を記録する（例: db.changes().beverages(&[id])）
+    pub fn changes(&self) -> RefMut<'_, ChangeSet> {
+        self.changes.borrow_mut()
+    }
+
+    pub fn take_changes(&self) -> ChangeSet {
+        self.changes.take()
+    }
+
+    /// 処理の途中でパニックした接続を使える状態に戻す（終了していないトランザクションはロールバックする）
+    pub fn recover(&self) -> Result<(), AppError> {
+        self.take_changes();
+        if !self.conn.is_autocommit() {
+            self.conn.execute_batch("ROLLBACK")?;
+        }
+        Ok(())
+    }
+
+    fn is_new_database(&self) -> Result<bool, AppError> {
+        let table_count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'posts'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(table_count == 0)
     }
 
     pub fn conn(&self) -> &Connection {
@@ -43,8 +298,12 @@ impl Database {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 name TEXT NOT NULL UNIQUE,
                 display_order INTEGER NOT NULL DEFAULT 0,
+                archived INTEGER NOT NULL DEFAULT 0,
+                parent_id INTEGER,
+                seed_key TEXT,
                 created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
-                updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+                updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+                FOREIGN KEY (parent_id) REFERENCES categories(id)
             )",
             [],
         )?;
@@ -55,6 +314,9 @@ impl Database {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 date TEXT NOT NULL,
                 comment TEXT,
+                started_at TEXT,
+                ended_at TEXT,
+                active INTEGER NOT NULL DEFAULT 0,
                 created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
                 updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
             )",
@@ -68,6 +330,18 @@ impl Database {
                 name TEXT NOT NULL UNIQUE,
                 alcohol_content REAL,
                 category_id INTEGER NOT NULL,
+                producer TEXT,
+                country TEXT,
+                region TEXT,
+                style TEXT,
+                volume_ml REAL,
+                barcode TEXT,
+                notes TEXT,
+                serving_ml REAL,
+                catalog_key TEXT,
+                catalog_values TEXT,
+                archived INTEGER NOT NULL DEFAULT 0,
+                favorite INTEGER NOT NULL DEFAULT 0,
                 created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
                 updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
                 FOREIGN KEY (category_id) REFERENCES categories(id)
@@ -82,6 +356,7 @@ impl Database {
                 post_id INTEGER NOT NULL,
                 beverage_id INTEGER NOT NULL,
                 amount REAL NOT NULL,
+                consumed_at TEXT,
                 created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
                 FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE,
                 FOREIGN KEY (beverage_id) REFERENCES beverages(id) ON DELETE CASCADE,
@@ -90,6 +365,159 @@ impl Database {
             [],
         )?;
 
+        // category_attributes テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS category_attributes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                category_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                value_type TEXT NOT NULL,
+                unit TEXT,
+                allowed_values TEXT,
+                display_order INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+                FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE,
+                UNIQUE(category_id, name)
+            )",
+            [],
+        )?;
+
+        // beverage_attributes テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS beverage_attributes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                beverage_id INTEGER NOT NULL,
+                attribute_id INTEGER NOT NULL,
+                value_text TEXT,
+                value_number REAL,
+                FOREIGN KEY (beverage_id) REFERENCES beverages(id) ON DELETE CASCADE,
+                FOREIGN KEY (attribute_id) REFERENCES category_attributes(id) ON DELETE CASCADE,
+                UNIQUE(beverage_id, attribute_id)
+            )",
+            [],
+        )?;
+
+        // beverage_ingredients テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS beverage_ingredients (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                beverage_id INTEGER NOT NULL,
+                ingredient_id INTEGER NOT NULL,
+                amount REAL NOT NULL,
+                display_order INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (beverage_id) REFERENCES beverages(id) ON DELETE CASCADE,
+                FOREIGN KEY (ingredient_id) REFERENCES beverages(id),
+                UNIQUE(beverage_id, ingredient_id)
+            )",
+            [],
+        )?;
+
+        // post_templates テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS post_templates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                comment TEXT,
+                tags TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+            )",
+            [],
+        )?;
+
+        // post_template_beverages テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS post_template_beverages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                template_id INTEGER NOT NULL,
+                beverage_id INTEGER NOT NULL,
+                amount REAL NOT NULL,
+                display_order INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (template_id) REFERENCES post_templates(id) ON DELETE CASCADE,
+                FOREIGN KEY (beverage_id) REFERENCES beverages(id),
+                UNIQUE(template_id, beverage_id)
+            )",
+            [],
+        )?;
+
+        // session_drinks テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_drinks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                post_id INTEGER NOT NULL,
+                beverage_id INTEGER NOT NULL,
+                amount REAL NOT NULL,
+                consumed_at TEXT NOT NULL,
+                FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE,
+                FOREIGN KEY (beverage_id) REFERENCES beverages(id)
+            )",
+            [],
+        )?;
+
+        // wellbeing_logs テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS wellbeing_logs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                date TEXT NOT NULL UNIQUE,
+                hangover_severity INTEGER,
+                sleep_quality INTEGER,
+                mood INTEGER,
+                notes TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+            )",
+            [],
+        )?;
+
+        // settings テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+            )",
+            [],
+        )?;
+
+        // category_translations テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS category_translations (
+                category_id INTEGER NOT NULL,
+                locale TEXT NOT NULL,
+                name TEXT NOT NULL,
+                PRIMARY KEY (category_id, locale),
+                FOREIGN KEY (category_id) REFERENCES categories(id)
+            )",
+            [],
+        )?;
+
+        // beverage_translations テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS beverage_translations (
+                beverage_id INTEGER NOT NULL,
+                locale TEXT NOT NULL,
+                name TEXT NOT NULL,
+                PRIMARY KEY (beverage_id, locale),
+                FOREIGN KEY (beverage_id) REFERENCES beverages(id)
+            )",
+            [],
+        )?;
+
+        // catalog_imports テーブル
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS catalog_imports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                version INTEGER NOT NULL,
+                categories TEXT,
+                inserted_count INTEGER NOT NULL DEFAULT 0,
+                linked_count INTEGER NOT NULL DEFAULT 0,
+                updated_count INTEGER NOT NULL DEFAULT 0,
+                imported_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+            )",
+            [],
+        )?;
+
         Ok(())
     }
 
@@ -102,14 +530,42 @@ impl Database {
             "CREATE INDEX IF NOT EXISTS idx_posts_date ON posts(date DESC)",
             [],
         )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_posts_started_at ON posts(started_at)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_posts_active ON posts(active) WHERE active = 1",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_session_drinks_post_id ON session_drinks(post_id)",
+            [],
+        )?;
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_beverages_category_id ON beverages(category_id)",
             [],
         )?;
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_beverages_barcode ON beverages(barcode)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_beverages_catalog_key ON beverages(catalog_key)",
+            [],
+        )?;
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_categories_display_order ON categories(display_order)",
             [],
         )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_categories_parent_id ON categories(parent_id)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_seed_key ON categories(seed_key)",
+            [],
+        )?;
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_post_beverages_post_id ON post_beverages(post_id)",
             [],
@@ -118,6 +574,22 @@ impl Database {
             "CREATE INDEX IF NOT EXISTS idx_post_beverages_beverage_id ON post_beverages(beverage_id)",
             [],
         )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_category_attributes_category_id ON category_attributes(category_id)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_beverage_attributes_attribute_id ON beverage_attributes(attribute_id)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_beverage_ingredients_ingredient_id ON beverage_ingredients(ingredient_id)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_post_template_beverages_beverage_id ON post_template_beverages(beverage_id)",
+            [],
+        )?;
         Ok(())
     }
 
@@ -134,22 +606,46 @@ impl Database {
             return Ok(());
         }
 
-        let initial_categories = vec![
-            ("ビール", 1),
-            ("ウイスキー", 2),
-            ("リキュール", 3),
-            ("梅酒", 4),
-            ("焼酎", 5),
-            ("ワイン", 6),
-            ("日本酒", 7),
-            ("ラム酒", 8),
-            ("ノンアルコール", 9),
+        // 初回起動時はOSの言語（対応していない場合は日本語）を表示する言語に設定し、その言語の名前で作成する
+        let locale = match settings::get_value(&self.conn, settings::LANGUAGE)? {
+            Some(_) => settings::language(&self.conn)?,
+            None => {
+                let locale = Locale::from_system().unwrap_or_default();
+                settings::set_value(&self.conn, settings::LANGUAGE, locale.as_str())?;
+                locale
+            }
+        };
+
+        // 同梱のカテゴリーはキーで識別し、言語ごとの名前を保存する（表示順はカタログの順）
+        for (index, category) in catalog::load()?.categories.iter().enumerate() {
+            self.conn.execute(
+                "INSERT INTO categories (name, seed_key, display_order) VALUES (?1, ?2, ?3)",
+                params![i18n::pick_name(&category.names, locale), category.key, index as i64 + 1],
+            )?;
+            let id = self.conn.last_insert_rowid();
+            i18n::set_translations(&self.conn, NameTable::Categories, id, &category.names)?;
+        }
+
+        // 代表的なカテゴリーには標準の属性スキーマを用意する（カテゴリーのキー, 名称（日本語・英語）, 型, 単位（日本語・英語）, 表示順）
+        let initial_attributes = vec![
+            ("sake", ("精米歩合", "Rice polishing ratio"), "number", Some(("%", "%")), 1),
+            ("sake", ("日本酒度", "Sake meter value"), "number", None, 2),
+            ("wine", ("品種", "Grape variety"), "text", None, 1),
+            ("wine", ("ヴィンテージ", "Vintage"), "integer", Some(("年", "year")), 2),
+            ("beer", ("IBU", "IBU"), "number", None, 1),
+            ("whisky", ("樽", "Cask"), "text", None, 1),
+            ("whisky", ("熟成年数", "Age"), "integer", Some(("年", "years")), 2),
         ];
 
-        for (name, display_order) in initial_categories {
+        for (category_key, names, value_type, units, display_order) in initial_attributes {
+            let pick = |(ja, en): (&'static str, &'static str)| match locale {
+                Locale::Ja => ja,
+                Locale::En => en,
+            };
             self.conn.execute(
-                "INSERT INTO categories (name, display_order) VALUES (?1, ?2)",
-                params![name, display_order],
+                "INSERT INTO category_attributes (category_id, name, value_type, unit, display_order)
+                SELECT id, ?2, ?3, ?4, ?5 FROM categories WHERE seed_key = ?1",
+                params![category_key, pick(names), value_type, units.map(pick), display_order],
             )?;
         }
 
@@ -157,3 +653,61 @@ impl Database {
     }
 }
 
+
+#[cfg(test)]
+impl Database {
+    /// テスト用のメモリ上のデータベース（最新のスキーマと初期データを作成する）
+    pub fn open_in_memory() -> Self {
+        Database::new(PathBuf::from(":memory:"), None).expect("メモリ上のデータベースを作成できません")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dates(conn: &Connection, table: &str) -> Vec<(i64, String)> {
+        let mut stmt = conn.prepare(&format!("SELECT id, date FROM {} ORDER BY id", table)).unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn repair_malformed_dates_normalizes_legacy_formats() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        for (id, date) in [(1, "2024-05-03"), (2, "2024/5/1"), (3, "20240501"), (4, "garbage")] {
+            conn.execute(
+                "INSERT INTO posts (id, date, created_at) VALUES (?1, ?2, '2024-04-20 10:00:00')",
+                params![id, date],
+            )
+            .unwrap();
+        }
+        for (id, date) in [(1, "2024-05-01"), (2, "2024/5/1"), (3, "20240502")] {
+            conn.execute("INSERT INTO wellbeing_logs (id, date) VALUES (?1, ?2)", params![id, date])
+                .unwrap();
+        }
+
+        let tx = conn.unchecked_transaction().unwrap();
+        repair_malformed_dates(&tx).unwrap();
+        tx.commit().unwrap();
+
+        // 正しい形式の行はそのまま、解釈できない日付は作成日時の日付にする
+        assert_eq!(
+            dates(conn, "posts"),
+            vec![
+                (1, "2024-05-03".to_string()),
+                (2, "2024-05-01".to_string()),
+                (3, "2024-05-01".to_string()),
+                (4, "2024-04-20".to_string()),
+            ]
+        );
+        // 修復後の日付が既存の記録と重複する体調の記録は削除する
+        assert_eq!(
+            dates(conn, "wellbeing_logs"),
+            vec![(1, "2024-05-01".to_string()), (3, "2024-05-02".to_string())]
+        );
+    }
+}
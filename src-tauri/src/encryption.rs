@@ -0,0 +1,62 @@
+use crate::backup::BackupStore;
+use crate::db::with_suffix;
+use crate::error::AppError;
+use crate::pool::{DbConfig, DbPool};
+use std::fs;
+
+/// データベースのパスフレーズを変更する（暗号化の有効化はcurrentがNone、解除はnewがNone）
+///
+/// SQLCipherで新しいパスフレーズのコピーを作成し、すべての接続を閉じてから元のファイルと置き換えて開き直す。
+/// バックアップも新しいパスフレーズで暗号化し直す。
+pub async fn change_passphrase(
+    pool: &DbPool,
+    backups: &BackupStore,
+    current: Option<String>,
+    new: Option<String>,
+) -> Result<(), AppError> {
+    let config = pool.config()?;
+    if current != config.passphrase {
+        return Err(AppError::WrongPassphrase);
+    }
+
+    let passphrase = new.clone();
+    pool.reopen(move |database| {
+        let database = database.ok_or(AppError::DatabaseClosed)?;
+        let path = database.path().to_path_buf();
+        let temp_path = with_suffix(&path, ".rekey");
+        let _ = fs::remove_file(&temp_path);
+        if let Err(error) = database.export_to(&temp_path, passphrase.as_deref()) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(error);
+        }
+
+        // 最後の接続を閉じると、WALの内容がデータベースファイルに書き戻されて-wal・-shmファイルは削除される
+        drop(database);
+        // 残っている場合は置き換える前のデータ（暗号化されていないページを含む）のため削除する
+        for suffix in ["-wal", "-shm"] {
+            let leftover = with_suffix(&path, suffix);
+            if leftover.exists() {
+                fs::remove_file(&leftover)?;
+            }
+        }
+        fs::rename(&temp_path, &path)?;
+
+        Ok(DbConfig { path, passphrase })
+    })
+    .await?;
+
+    match new {
+        Some(_) => log::info!("データベースのパスフレーズを変更しました"),
+        None => log::info!("データベースの暗号化を解除しました"),
+    }
+    backups.rekey(current.as_deref(), new.as_deref())
+}
+
+/// パスフレーズを入力して、暗号化されたデータベースを開く
+pub async fn unlock(pool: &DbPool, passphrase: String) -> Result<(), AppError> {
+    if pool.is_open() {
+        return Ok(());
+    }
+    let path = pool.path().ok_or(AppError::DatabaseClosed)?;
+    pool.reopen(move |_| Ok(DbConfig { path, passphrase: Some(passphrase) })).await
+}
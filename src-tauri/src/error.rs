@@ -1,18 +1,168 @@
+use crate::messages::{self, ErrorCode, Label, Locale};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
 use thiserror::Error;
 
+/// エラーコードとメッセージのパラメーター（fieldは入力項目のパス。例: beverages[1].amount）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    pub code: ErrorCode,
+    #[serde(default)]
+    pub params: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl ErrorDetail {
+    pub fn new(code: ErrorCode) -> Self {
+        Self {
+            code,
+            params: BTreeMap::new(),
+            field: None,
+        }
+    }
+
+    pub fn param(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.params.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// 項目名のパラメーター（メッセージの{label}）
+    pub fn label(self, label: Label) -> Self {
+        self.param("label", serde_json::to_value(label).unwrap_or_default())
+    }
+
+    pub fn field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        messages::render(self.code, &self.params, locale)
+    }
+}
+
+impl fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::current()))
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
-    
+
     #[error("Migration error: {0}")]
     Migration(#[from] rusqlite_migration::Error),
-    
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
-    
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Database is encrypted and requires a passphrase")]
+    PassphraseRequired,
+
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+
+    #[error("Database is not open")]
+    DatabaseClosed,
+
+    #[error("Database is in use by another instance on {0}")]
+    DatabaseInUse(String),
+
+    #[error("App is locked")]
+    AppLocked,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
     #[error("Invalid input: {0}")]
-    InvalidInput(String),
+    InvalidInput(ErrorDetail),
+
+    #[error("Invalid input: {}", join_messages(.0))]
+    Validation(Vec<ErrorDetail>),
+}
+
+impl From<ErrorDetail> for AppError {
+    fn from(detail: ErrorDetail) -> Self {
+        AppError::InvalidInput(detail)
+    }
+}
+
+fn join_messages(errors: &[ErrorDetail]) -> String {
+    errors
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+impl AppError {
+    /// エラーコード・パラメーター（内部エラーは詳細をparams.detailに入れる）
+    pub fn detail(&self) -> ErrorDetail {
+        match self {
+            AppError::Database(error) => {
+                ErrorDetail::new(ErrorCode::Database).param("detail", error.to_string())
+            }
+            AppError::Migration(error) => {
+                ErrorDetail::new(ErrorCode::Migration).param("detail", error.to_string())
+            }
+            AppError::Serialization(error) => {
+                ErrorDetail::new(ErrorCode::Serialization).param("detail", error.to_string())
+            }
+            AppError::Io(error) => {
+                ErrorDetail::new(ErrorCode::Io).param("detail", error.to_string())
+            }
+            AppError::PassphraseRequired => ErrorDetail::new(ErrorCode::PassphraseRequired),
+            AppError::WrongPassphrase => ErrorDetail::new(ErrorCode::WrongPassphrase),
+            AppError::DatabaseClosed => ErrorDetail::new(ErrorCode::DatabaseClosed),
+            AppError::DatabaseInUse(host) => {
+                ErrorDetail::new(ErrorCode::DatabaseInUse).param("host", host.as_str())
+            }
+            AppError::AppLocked => ErrorDetail::new(ErrorCode::AppLocked),
+            AppError::Internal(detail) => {
+                ErrorDetail::new(ErrorCode::Internal).param("detail", detail.as_str())
+            }
+            AppError::InvalidInput(detail) => detail.clone(),
+            AppError::Validation(_) => ErrorDetail::new(ErrorCode::ValidationFailed),
+        }
+    }
+
+    /// 指定した言語のメッセージ（検証エラーは項目ごとのメッセージをつなげる）
+    pub fn message(&self, locale: Locale) -> String {
+        match self {
+            AppError::Validation(errors) => errors
+                .iter()
+                .map(|error| error.message(locale))
+                .collect::<Vec<_>>()
+                .join(" / "),
+            _ => self.detail().message(locale),
+        }
+    }
+}
+
+/// 項目ごとのエラーに表示用のメッセージを添えて返す
+struct FieldErrorOutput<'a>(&'a ErrorDetail);
+
+impl Serialize for FieldErrorOutput<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("FieldError", 4)?;
+        state.serialize_field("code", &self.0.code)?;
+        state.serialize_field("params", &self.0.params)?;
+        state.serialize_field("field", &self.0.field)?;
+        state.serialize_field("message", &self.0.message(Locale::current()))?;
+        state.end()
+    }
 }
 
 impl serde::Serialize for AppError {
@@ -20,7 +170,19 @@ impl serde::Serialize for AppError {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_str())
+        // フロントエンドがコードで処理を分岐・翻訳できるよう、コード・パラメーター・項目名を返す
+        // （messageは設定中の言語で組み立てたもの）
+        let detail = self.detail();
+        let mut state = serializer.serialize_struct("AppError", 5)?;
+        state.serialize_field("code", &detail.code)?;
+        state.serialize_field("params", &detail.params)?;
+        state.serialize_field("field", &detail.field)?;
+        state.serialize_field("message", &self.message(Locale::current()))?;
+        if let AppError::Validation(errors) = self {
+            let field_errors: Vec<_> = errors.iter().map(FieldErrorOutput).collect();
+            state.serialize_field("field_errors", &field_errors)?;
+        }
+        state.end()
     }
 }
 
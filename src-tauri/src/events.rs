@@ -0,0 +1,166 @@
+use crate::error::AppError;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// 変更のあった日付の範囲（両端を含む）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// posts_changedイベントの内容（allがtrueの場合はすべての投稿が変更された可能性がある）
+///
+/// date_rangeは変更前後の投稿日・飲酒日を含む範囲で、集計の再取得が必要な期間を表す。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PostsChanged {
+    pub ids: Vec<i64>,
+    pub date_range: Option<DateRange>,
+    pub all: bool,
+}
+
+/// beverages_changed・categories_changed・templates_changedイベントの内容
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntitiesChanged {
+    pub ids: Vec<i64>,
+    pub all: bool,
+}
+
+/// wellbeing_changedイベントの内容
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WellbeingChanged {
+    pub date_range: Option<DateRange>,
+    pub all: bool,
+}
+
+/// コミットされた変更をフロントエンドに知らせるイベント
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DataChanged {
+    Posts(PostsChanged),
+    Beverages(EntitiesChanged),
+    Categories(EntitiesChanged),
+    Templates(EntitiesChanged),
+    Wellbeing(WellbeingChanged),
+}
+
+impl DataChanged {
+    /// Tauriのイベント名
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataChanged::Posts(_) => "posts_changed",
+            DataChanged::Beverages(_) => "beverages_changed",
+            DataChanged::Categories(_) => "categories_changed",
+            DataChanged::Templates(_) => "templates_changed",
+            DataChanged::Wellbeing(_) => "wellbeing_changed",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Changes {
+    ids: BTreeSet<i64>,
+    dates: BTreeSet<NaiveDate>,
+    all: bool,
+}
+
+impl Changes {
+    fn entities(self) -> EntitiesChanged {
+        EntitiesChanged {
+            ids: self.ids.into_iter().collect(),
+            all: self.all,
+        }
+    }
+
+    fn date_range(&self) -> Option<DateRange> {
+        Some(DateRange {
+            from: *self.dates.first()?,
+            to: *self.dates.last()?,
+        })
+    }
+}
+
+/// 書き込み処理中の変更を種類ごとにまとめる（書き込みが成功した場合のみイベントとして送る）
+#[derive(Debug, Default)]
+pub struct ChangeSet {
+    posts: Option<Changes>,
+    beverages: Option<Changes>,
+    categories: Option<Changes>,
+    templates: Option<Changes>,
+    wellbeing: Option<Changes>,
+}
+
+impl ChangeSet {
+    /// 投稿の変更を記録する（投稿日を範囲に含めるため、削除する場合は削除の前に呼ぶ）
+    pub fn posts(&mut self, conn: &Connection, ids: &[i64]) -> Result<(), AppError> {
+        let changes = self.posts.get_or_insert_with(Changes::default);
+        for &id in ids {
+            let date: Option<NaiveDate> = conn
+                .query_row("SELECT date FROM posts WHERE id = ?1", params![id], |row| row.get(0))
+                .optional()?;
+            if let Some(date) = date {
+                changes.dates.insert(date);
+            }
+            changes.ids.insert(id);
+        }
+        Ok(())
+    }
+
+    pub fn beverages(&mut self, ids: &[i64]) {
+        self.beverages.get_or_insert_with(Changes::default).ids.extend(ids);
+    }
+
+    pub fn categories(&mut self, ids: &[i64]) {
+        self.categories.get_or_insert_with(Changes::default).ids.extend(ids);
+    }
+
+    pub fn templates(&mut self, ids: &[i64]) {
+        self.templates.get_or_insert_with(Changes::default).ids.extend(ids);
+    }
+
+    pub fn wellbeing(&mut self, date: NaiveDate) {
+        self.wellbeing.get_or_insert_with(Changes::default).dates.insert(date);
+    }
+
+    /// 表示名・飲酒日の切り替え、バックアップからの復元など、すべてのデータに影響する変更を記録する
+    pub fn all(&mut self) {
+        for changes in [
+            &mut self.posts,
+            &mut self.beverages,
+            &mut self.categories,
+            &mut self.templates,
+            &mut self.wellbeing,
+        ] {
+            changes.get_or_insert_with(Changes::default).all = true;
+        }
+    }
+
+    pub fn into_events(self) -> Vec<DataChanged> {
+        let mut events = Vec::new();
+        if let Some(changes) = self.posts {
+            events.push(DataChanged::Posts(PostsChanged {
+                date_range: changes.date_range(),
+                ids: changes.ids.into_iter().collect(),
+                all: changes.all,
+            }));
+        }
+        if let Some(changes) = self.beverages {
+            events.push(DataChanged::Beverages(changes.entities()));
+        }
+        if let Some(changes) = self.categories {
+            events.push(DataChanged::Categories(changes.entities()));
+        }
+        if let Some(changes) = self.templates {
+            events.push(DataChanged::Templates(changes.entities()));
+        }
+        if let Some(changes) = self.wellbeing {
+            events.push(DataChanged::Wellbeing(WellbeingChanged {
+                date_range: changes.date_range(),
+                all: changes.all,
+            }));
+        }
+        events
+    }
+}
@@ -0,0 +1,234 @@
+use crate::catalog;
+use crate::error::AppError;
+use crate::messages::Locale;
+use crate::settings;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+
+/// 言語ごとの名前を持てるテーブル
+///
+/// 同梱のカテゴリー・カタログのお酒は言語ごとの名前（翻訳）を持ち、言語の設定に合わせた名前を表示する。
+/// 翻訳がない場合（ユーザーが登録・名前を変更した場合）は登録名をすべての言語で使う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameTable {
+    Categories,
+    Beverages,
+}
+
+impl NameTable {
+    fn table(self) -> &'static str {
+        match self {
+            NameTable::Categories => "categories",
+            NameTable::Beverages => "beverages",
+        }
+    }
+
+    fn translation_table(self) -> &'static str {
+        match self {
+            NameTable::Categories => "category_translations",
+            NameTable::Beverages => "beverage_translations",
+        }
+    }
+
+    fn id_column(self) -> &'static str {
+        match self {
+            NameTable::Categories => "category_id",
+            NameTable::Beverages => "beverage_id",
+        }
+    }
+}
+
+/// 言語の設定に合わせた表示名を表すSQL式（aliasはテーブルの別名。翻訳がない場合は登録名）
+pub fn localized_name_sql(table: NameTable, alias: &str) -> String {
+    format!(
+        "COALESCE((SELECT t.name FROM {} t WHERE t.{} = {}.id
+            AND t.locale = (SELECT value FROM settings WHERE key = '{}')), {}.name)",
+        table.translation_table(),
+        table.id_column(),
+        alias,
+        settings::LANGUAGE,
+        alias
+    )
+}
+
+/// いずれかの言語の翻訳名が条件に一致するかを表すSQL式（検索用。conditionはt.nameに対する条件）
+pub fn any_translation_sql(table: NameTable, alias: &str, condition: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM {} t WHERE t.{} = {}.id AND {})",
+        table.translation_table(),
+        table.id_column(),
+        alias,
+        condition
+    )
+}
+
+/// 言語ごとの名前を置き換える
+pub fn set_translations(
+    conn: &Connection,
+    table: NameTable,
+    id: i64,
+    names: &BTreeMap<Locale, String>,
+) -> Result<(), AppError> {
+    delete_translations(conn, table, id)?;
+    for (locale, name) in names {
+        conn.execute(
+            &format!(
+                "INSERT INTO {} ({}, locale, name) VALUES (?1, ?2, ?3)",
+                table.translation_table(),
+                table.id_column()
+            ),
+            params![id, locale.as_str(), name],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn delete_translations(conn: &Connection, table: NameTable, id: i64) -> Result<(), AppError> {
+    conn.execute(
+        &format!(
+            "DELETE FROM {} WHERE {} = ?1",
+            table.translation_table(),
+            table.id_column()
+        ),
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// 編集で送られた名前を登録名に変換する
+///
+/// 編集画面には表示中の言語の名前が入るため、その名前のまま変更されていなければ登録名を変えない。
+pub fn stored_name(
+    conn: &Connection,
+    table: NameTable,
+    id: i64,
+    submitted: &str,
+) -> Result<String, AppError> {
+    let current: Option<(String, String)> = conn
+        .query_row(
+            &format!(
+                "SELECT x.name, {} FROM {} x WHERE x.id = ?1",
+                localized_name_sql(table, "x"),
+                table.table()
+            ),
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(match current {
+        Some((name, localized)) if localized == submitted => name,
+        _ => submitted.to_string(),
+    })
+}
+
+/// 登録名がどの翻訳とも一致しなくなった（ユーザーが名前を変更した）場合は翻訳を削除する
+pub fn drop_stale_translations(conn: &Connection, table: NameTable, id: i64) -> Result<(), AppError> {
+    conn.execute(
+        &format!(
+            "DELETE FROM {translations} WHERE {id_column} = ?1
+            AND NOT EXISTS (
+                SELECT 1 FROM {translations} t
+                INNER JOIN {table} x ON x.id = t.{id_column}
+                WHERE t.{id_column} = ?1 AND t.name = x.name
+            )",
+            translations = table.translation_table(),
+            id_column = table.id_column(),
+            table = table.table()
+        ),
+        params![id],
+    )?;
+    Ok(())
+}
+
+fn has_translations(conn: &Connection, table: NameTable, id: i64) -> Result<bool, AppError> {
+    let count: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM {} WHERE {} = ?1",
+            table.translation_table(),
+            table.id_column()
+        ),
+        params![id],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// 同梱のカテゴリー・カタログのお酒に、キーと言語ごとの名前を付与する
+///
+/// 名前がいずれかの言語の名前と一致する（ユーザーが名前を変更していない）ものだけを対象にする。
+/// マイグレーションとカタログの取り込み時に呼び出し、既に翻訳があるものは変更しない。
+pub fn link_bundled_translations(conn: &Connection) -> Result<(), AppError> {
+    let catalog = catalog::load()?;
+
+    for category in &catalog.categories {
+        let existing: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, name FROM categories WHERE seed_key = ?1",
+                params![category.key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let existing = match existing {
+            Some(existing) => Some(existing),
+            None => {
+                let mut found = None;
+                for name in category.names.values() {
+                    found = conn
+                        .query_row(
+                            "SELECT id, name FROM categories WHERE seed_key IS NULL AND name = ?1",
+                            params![name],
+                            |row| Ok((row.get(0)?, row.get(1)?)),
+                        )
+                        .optional()?;
+                    if found.is_some() {
+                        break;
+                    }
+                }
+                if let Some((id, _)) = &found {
+                    conn.execute(
+                        "UPDATE categories SET seed_key = ?1 WHERE id = ?2",
+                        params![category.key, id],
+                    )?;
+                }
+                found
+            }
+        };
+
+        if let Some((id, name)) = existing {
+            let unchanged = category.names.values().any(|translated| *translated == name);
+            if unchanged && !has_translations(conn, NameTable::Categories, id)? {
+                set_translations(conn, NameTable::Categories, id, &category.names)?;
+            }
+        }
+    }
+
+    for entry in &catalog.beverages {
+        let existing: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, name FROM beverages WHERE catalog_key = ?1",
+                params![entry.key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        if let Some((id, name)) = existing {
+            let unchanged = entry.names.values().any(|translated| *translated == name);
+            if unchanged && !has_translations(conn, NameTable::Beverages, id)? {
+                set_translations(conn, NameTable::Beverages, id, &entry.names)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 言語ごとの名前から指定した言語の名前を選ぶ（ない場合は日本語、それもなければいずれかの言語）
+pub fn pick_name(names: &BTreeMap<Locale, String>, locale: Locale) -> &str {
+    names
+        .get(&locale)
+        .or_else(|| names.get(&Locale::Ja))
+        .or_else(|| names.values().next())
+        .map(String::as_str)
+        .unwrap_or_default()
+}
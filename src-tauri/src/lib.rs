@@ -1,14 +1,117 @@
 mod db;
 mod models;
 mod error;
+mod events;
+mod i18n;
 mod commands;
+mod dates;
+mod encryption;
+mod app_lock;
+mod attributes;
+mod backup;
+mod catalog;
+mod merge;
+mod messages;
+mod pool;
+mod profiles;
+mod stats;
+mod recipes;
+mod live_session;
+mod lock_file;
+mod sessions;
+mod settings;
+mod suggestions;
+mod templates;
+mod validation;
+mod watcher;
+mod wellbeing;
 
-use db::Database;
-use std::sync::Mutex;
-use tauri::Manager;
+use app_lock::AppLock;
+use backup::BackupStore;
+use error::AppError;
+use pool::{DbConfig, DbPool};
+use profiles::Profiles;
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  let handler = tauri::generate_handler![
+    commands::get_posts,
+    commands::create_post,
+    commands::update_post,
+    commands::delete_post,
+    commands::get_active_session,
+    commands::start_drinking_session,
+    commands::add_session_drink,
+    commands::remove_session_drink,
+    commands::end_drinking_session,
+    commands::discard_drinking_session,
+    commands::get_post_templates,
+    commands::create_post_template,
+    commands::update_post_template,
+    commands::delete_post_template,
+    commands::create_post_from_template,
+    commands::get_categories,
+    commands::create_category,
+    commands::update_category,
+    commands::rename_category,
+    commands::reorder_categories,
+    commands::delete_category,
+    commands::archive_category,
+    commands::unarchive_category,
+    commands::get_category_attributes,
+    commands::create_category_attribute,
+    commands::update_category_attribute,
+    commands::delete_category_attribute,
+    commands::get_beverages,
+    commands::get_beverages_by_category,
+    commands::search_beverages,
+    commands::get_beverage_by_barcode,
+    commands::create_beverage,
+    commands::update_beverage,
+    commands::delete_beverage,
+    commands::archive_beverage,
+    commands::unarchive_beverage,
+    commands::set_beverage_favorite,
+    commands::get_suggested_beverages,
+    commands::get_beverage_recipe,
+    commands::set_beverage_recipe,
+    commands::preview_merge_beverages,
+    commands::merge_beverages,
+    commands::get_catalog_info,
+    commands::import_catalog,
+    commands::get_monthly_alcohol_intake,
+    commands::get_drinking_calendar,
+    commands::get_drinking_streaks,
+    commands::get_drinking_day,
+    commands::get_settings,
+    commands::get_message_catalog,
+    commands::update_settings,
+    commands::get_database_status,
+    commands::reopen_database,
+    commands::unlock_database,
+    commands::change_database_passphrase,
+    commands::unlock_app,
+    commands::lock_app,
+    commands::get_app_lock_settings,
+    commands::set_app_lock_pin,
+    commands::disable_app_lock,
+    commands::list_backups,
+    commands::create_backup_now,
+    commands::restore_backup,
+    commands::get_profiles,
+    commands::create_profile,
+    commands::rename_profile,
+    commands::switch_profile,
+    commands::delete_profile,
+    commands::get_wellbeing_logs,
+    commands::save_wellbeing_log,
+    commands::delete_wellbeing_log,
+    commands::get_wellbeing_analysis,
+    commands::get_category_alcohol_intake,
+    commands::get_beverage_alcohol_intake,
+  ];
+
   tauri::Builder::default()
     .setup(|app| {
       // ログプラグインの設定
@@ -24,28 +127,91 @@ pub fn run() {
       let app_dir = app.path().app_data_dir().expect("Failed to get app data directory");
       std::fs::create_dir_all(&app_dir).expect("Failed to create app data directory");
       
-      let db_path = app_dir.join("nomi-log.db");
-      let database = Database::new(db_path).expect("Failed to initialize database");
-      
-      app.manage(Mutex::new(database));
+      // 前回使っていたプロファイルのデータベースを開く（--database・NOMI_LOG_DATABASEで指定された場合はそのデータベース）
+      let profiles = Profiles::load(app_dir.clone(), profiles::parse_database_override()).expect("Failed to load profiles");
+      let db_path = match profiles.database_override() {
+        Some(path) => {
+          log::info!("指定されたデータベースを開きます: {}", path.display());
+          path.to_path_buf()
+        }
+        None => profiles.active_database_path(),
+      };
+      if let Some(dir) = db_path.parent() {
+        std::fs::create_dir_all(dir).expect("Failed to create database directory");
+      }
+      let pool = DbPool::default();
+
+      // 書き込みがコミットされたら、変更の種類ごとのイベントをすべてのウィンドウに送る
+      let app_handle = app.handle().clone();
+      pool.on_change(move |event| {
+        if let Err(error) = app_handle.emit(event.name(), event) {
+          log::warn!("データの変更の通知に失敗しました: {}", error);
+        }
+      });
+
+      // 暗号化されている場合は、フロントエンドでパスフレーズが入力されてから開く（unlock_database）
+      match pool.open(DbConfig { path: db_path.clone(), passphrase: None }) {
+        Ok(()) => {}
+        Err(AppError::PassphraseRequired) => log::info!("データベースは暗号化されています。パスフレーズの入力を待ちます"),
+        // フロントエンドでエラーを表示し、使用中のアプリが終了してから開き直す（reopen_database）
+        Err(AppError::DatabaseInUse(host)) => log::warn!("データベースは{}の別ののみログで使用中です", host),
+        Err(error) => panic!("Failed to initialize database: {}", error),
+      }
+
+      // 外部（sqlite3コマンドなど）からの書き込みを検出したら、フロントエンドに再読み込みを通知する
+      let app_handle = app.handle().clone();
+      let watched = watcher::watch(pool.clone(), move || {
+        if let Err(error) = app_handle.emit(watcher::DATABASE_CHANGED_EVENT, ()) {
+          log::warn!("データベースの変更の通知に失敗しました: {}", error);
+        }
+      });
+      if let Err(error) = watched {
+        log::warn!("データベースファイルの監視を開始できませんでした: {}", error);
+      }
+
+      // 起動時と1日ごとにバックアップを作成する
+      let backups = BackupStore::new(backup::backup_dir(&app_dir, &db_path));
+      backups.clone().schedule(pool.clone());
+
+      // PINが設定されている場合は、起動時と一定時間操作がなかった場合にロックする
+      let app_lock = AppLock::default();
+      if pool.is_open() {
+        let loading = app_lock.clone();
+        tauri::async_runtime::block_on(pool.read(move |db| loading.load(db.conn(), true)))
+          .expect("Failed to load app lock settings");
+      } else {
+        // パスフレーズの入力待ち・他のアプリが使用中の場合は、開いた後にPINの設定を読み込んでロックする
+        app_lock.reset();
+      }
+      let app_handle = app.handle().clone();
+      app_lock.watch_idle(move || {
+        if let Err(error) = app_handle.emit(app_lock::APP_LOCKED_EVENT, ()) {
+          log::warn!("アプリのロックの通知に失敗しました: {}", error);
+        }
+      });
+
+      app.manage(pool);
+      app.manage(backups);
+      app.manage(app_lock);
+      app.manage(profiles);
 
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![
-      commands::get_posts,
-      commands::create_post,
-      commands::update_post,
-      commands::delete_post,
-      commands::get_categories,
-      commands::create_category,
-      commands::delete_category,
-      commands::get_beverages,
-      commands::get_beverages_by_category,
-      commands::create_beverage,
-      commands::update_beverage,
-      commands::delete_beverage,
-      commands::get_monthly_alcohol_intake,
-    ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .invoke_handler(move |invoke| {
+      // アプリのロック中は、ロックの解除以外のコマンドを拒否する
+      let webview = invoke.message.webview();
+      if let Err(error) = webview.state::<AppLock>().check(invoke.message.command()) {
+        invoke.resolver.reject(error);
+        return true;
+      }
+      handler(invoke)
+    })
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app_handle, event| {
+      // 終了時に接続を閉じてロックファイルを解放する（同期フォルダーを共有する他のコンピューターがすぐに開けるように）
+      if let tauri::RunEvent::Exit = event {
+        app_handle.state::<DbPool>().close();
+      }
+    });
 }
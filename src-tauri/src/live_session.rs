@@ -0,0 +1,260 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::i18n::{self, NameTable};
+use crate::messages::{ErrorCode, Label};
+use crate::models::*;
+use crate::sessions;
+use crate::settings;
+use crate::validation::Validator;
+use chrono::{NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension};
+
+// Widmarkの式の体内水分係数（男性・女性）
+const WIDMARK_R_MALE: f64 = 0.68;
+const WIDMARK_R_FEMALE: f64 = 0.55;
+// 1時間あたりの血中アルコール濃度の減少量（%）
+const ELIMINATION_PER_HOUR: f64 = 0.015;
+
+fn now(conn: &Connection) -> Result<NaiveDateTime, AppError> {
+    Ok(conn.query_row("SELECT datetime('now', 'localtime')", [], |row| row.get(0))?)
+}
+
+fn active_post_id(conn: &Connection) -> Result<Option<i64>, AppError> {
+    Ok(conn
+        .query_row("SELECT id FROM posts WHERE active = 1", [], |row| row.get(0))
+        .optional()?)
+}
+
+fn require_active_post_id(conn: &Connection) -> Result<i64, AppError> {
+    active_post_id(conn)?
+        .ok_or_else(|| ErrorDetail::new(ErrorCode::NoActiveSession).into())
+}
+
+/// 記録中の1杯ごとの履歴を、お酒ごとに合算してpost_beveragesに反映する
+fn sync_post_beverages(conn: &Connection, post_id: i64) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM post_beverages WHERE post_id = ?1",
+        params![post_id],
+    )?;
+    conn.execute(
+        "INSERT INTO post_beverages (post_id, beverage_id, amount, consumed_at)
+        SELECT post_id, beverage_id, SUM(amount), MIN(consumed_at)
+        FROM session_drinks
+        WHERE post_id = ?1
+        GROUP BY beverage_id",
+        params![post_id],
+    )?;
+    Ok(())
+}
+
+/// Widmarkの式で現在の血中アルコール濃度（%）を推定する（体重・性別が未設定の場合はNone）
+fn projected_bac(
+    conn: &Connection,
+    total_pure_alcohol: f64,
+    hours_since_first_drink: Option<f64>,
+) -> Result<Option<f64>, AppError> {
+    let profile = settings::get_settings(conn)?;
+    let (Some(weight_kg), Some(sex)) = (profile.body_weight_kg, profile.body_sex) else {
+        return Ok(None);
+    };
+    let Some(hours) = hours_since_first_drink else {
+        return Ok(Some(0.0));
+    };
+
+    let r = match sex {
+        BodySex::Male => WIDMARK_R_MALE,
+        BodySex::Female => WIDMARK_R_FEMALE,
+    };
+    let bac = total_pure_alcohol / (weight_kg * 1000.0 * r) * 100.0 - ELIMINATION_PER_HOUR * hours;
+    Ok(Some(bac.max(0.0)))
+}
+
+/// 記録中のセッションと現在までの集計を取得する
+pub fn get_active_session(conn: &Connection) -> Result<Option<ActiveSession>, AppError> {
+    let Some(post_id) = active_post_id(conn)? else {
+        return Ok(None);
+    };
+
+    let (date, comment, started_at): (NaiveDate, Option<String>, NaiveDateTime) = conn.query_row(
+        "SELECT date, comment, started_at FROM posts WHERE id = ?1",
+        params![post_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let now = now(conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT
+            sd.id,
+            sd.beverage_id,
+            {},
+            sd.amount,
+            b.alcohol_content,
+            sd.consumed_at
+        FROM session_drinks sd
+        INNER JOIN beverages b ON sd.beverage_id = b.id
+        WHERE sd.post_id = ?1
+        ORDER BY sd.consumed_at, sd.id",
+        i18n::localized_name_sql(NameTable::Beverages, "b")
+    ))?;
+    let drinks = stmt
+        .query_map(params![post_id], |row| {
+            Ok(SessionDrink {
+                id: row.get(0)?,
+                beverage_id: row.get(1)?,
+                beverage_name: row.get(2)?,
+                amount: row.get(3)?,
+                alcohol_content: row.get(4)?,
+                consumed_at: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let minutes_since = |from: NaiveDateTime| (now - from).num_seconds() as f64 / 60.0;
+
+    let amounts: Vec<BeverageAmount> = drinks
+        .iter()
+        .map(|drink| BeverageAmount {
+            beverage_id: drink.beverage_id,
+            beverage_name: drink.beverage_name.clone(),
+            amount: drink.amount,
+            alcohol_content: drink.alcohol_content,
+            consumed_at: Some(drink.consumed_at),
+        })
+        .collect();
+    let pace = sessions::pace(Some(minutes_since(started_at)), &amounts);
+
+    let hours_since_first_drink = drinks
+        .first()
+        .map(|drink| minutes_since(drink.consumed_at).max(0.0) / 60.0);
+    let projected_bac = projected_bac(conn, pace.total_pure_alcohol, hours_since_first_drink)?;
+
+    Ok(Some(ActiveSession {
+        post_id,
+        date,
+        comment,
+        started_at,
+        now,
+        total_amount: drinks.iter().map(|drink| drink.amount).sum(),
+        drinks,
+        pace,
+        projected_bac,
+    }))
+}
+
+fn require_active_session(conn: &Connection) -> Result<ActiveSession, AppError> {
+    get_active_session(conn)?
+        .ok_or_else(|| ErrorDetail::new(ErrorCode::NoActiveSession).into())
+}
+
+/// セッションを開始する（開始時刻は現在時刻、日付はその飲酒日）
+///
+/// 日付を指定した場合は、開始時刻の飲酒日と一致するか検証する。
+pub fn start_session(
+    conn: &Connection,
+    date: Option<NaiveDate>,
+    comment: Option<String>,
+) -> Result<ActiveSession, AppError> {
+    if active_post_id(conn)?.is_some() {
+        return Err(ErrorDetail::new(ErrorCode::SessionAlreadyActive).into());
+    }
+
+    let started_at = now(conn)?;
+    let rollover_hour = settings::day_rollover_hour(conn)?;
+    if let Some(date) = date {
+        sessions::check_drinking_day(date, started_at, rollover_hour)?;
+    }
+    conn.execute(
+        "INSERT INTO posts (date, comment, started_at, active)
+        VALUES (?1, ?2, ?3, 1)",
+        params![
+            settings::drinking_day(started_at, rollover_hour),
+            comment.map(|comment| comment.trim().to_string()).filter(|comment| !comment.is_empty()),
+            started_at,
+        ],
+    )?;
+
+    require_active_session(conn)
+}
+
+/// 記録中のセッションに1杯を追加する（飲んだ時刻は現在時刻）
+pub fn add_drink(conn: &Connection, beverage_id: i64, amount: f64) -> Result<ActiveSession, AppError> {
+    let post_id = require_active_post_id(conn)?;
+
+    let mut v = Validator::new();
+    v.amount("amount", amount, Label::Amount);
+    v.exists(conn, "beverages", beverage_id, "beverage_id", ErrorCode::BeverageNotFound)?;
+    v.finish()?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO session_drinks (post_id, beverage_id, amount, consumed_at)
+        VALUES (?1, ?2, ?3, datetime('now', 'localtime'))",
+        params![post_id, beverage_id, amount],
+    )?;
+    sync_post_beverages(&tx, post_id)?;
+    tx.commit()?;
+
+    require_active_session(conn)
+}
+
+/// 記録中のセッションから1杯を取り消す
+pub fn remove_drink(conn: &Connection, drink_id: i64) -> Result<ActiveSession, AppError> {
+    let post_id = require_active_post_id(conn)?;
+
+    let tx = conn.unchecked_transaction()?;
+    let deleted = tx.execute(
+        "DELETE FROM session_drinks WHERE id = ?1 AND post_id = ?2",
+        params![drink_id, post_id],
+    )?;
+    if deleted == 0 {
+        return Err(ErrorDetail::new(ErrorCode::SessionDrinkNotFound).into());
+    }
+    sync_post_beverages(&tx, post_id)?;
+    tx.commit()?;
+
+    require_active_session(conn)
+}
+
+/// セッションを終了し、通常の投稿として確定する
+pub fn end_session(conn: &Connection) -> Result<i64, AppError> {
+    let post_id = require_active_post_id(conn)?;
+
+    let drink_count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM session_drinks WHERE post_id = ?1",
+        params![post_id],
+        |row| row.get(0),
+    )?;
+    if drink_count == 0 {
+        return Err(ErrorDetail::new(ErrorCode::SessionEmpty).into());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    sync_post_beverages(&tx, post_id)?;
+    tx.execute(
+        "UPDATE posts SET
+            active = 0,
+            ended_at = datetime('now', 'localtime'),
+            updated_at = datetime('now', 'localtime')
+        WHERE id = ?1",
+        params![post_id],
+    )?;
+    tx.execute(
+        "DELETE FROM session_drinks WHERE post_id = ?1",
+        params![post_id],
+    )?;
+    tx.commit()?;
+
+    Ok(post_id)
+}
+
+/// 記録中のセッションを投稿せずに破棄する
+pub fn discard_session(conn: &Connection) -> Result<(), AppError> {
+    let post_id = require_active_post_id(conn)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM session_drinks WHERE post_id = ?1", params![post_id])?;
+    tx.execute("DELETE FROM post_beverages WHERE post_id = ?1", params![post_id])?;
+    tx.execute("DELETE FROM posts WHERE id = ?1", params![post_id])?;
+    tx.commit()?;
+
+    Ok(())
+}
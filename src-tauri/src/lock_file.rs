@@ -0,0 +1,221 @@
+use crate::db::with_suffix;
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::Duration;
+
+// データベースと同じディレクトリに作成するロックファイル（nomi-log.db.lock）
+const LOCK_FILE_SUFFIX: &str = ".lock";
+// 使用中であることを示すため、ロックファイルの時刻を更新する間隔
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+// 他のコンピューターのロックの時刻がこれより古い場合は、終了せずに止まったものとして引き継ぐ
+const STALE_AFTER_SECONDS: i64 = 5 * 60;
+
+/// データベースの書き込みを1つのアプリに限るための助言ロック
+///
+/// 同じコンピューターの別のアプリはOSのファイルロックで、同期フォルダーを共有する別のコンピューターは
+/// ロックファイルに書き込んだコンピューター名と時刻で検出する（OSのファイルロックは同期されないため）。
+/// 開いている間は一定間隔で時刻を更新し、閉じる際に内容を消して解放する。
+pub struct LockFile {
+    path: PathBuf,
+    file: Arc<Mutex<Option<File>>>,
+}
+
+/// ロックファイルの内容
+#[derive(Serialize, Deserialize)]
+struct LockOwner {
+    host: String,
+    pid: u32,
+    acquired_at: DateTime<Utc>,
+    heartbeat_at: DateTime<Utc>,
+}
+
+impl LockFile {
+    /// データベースのロックを取得する（他のアプリが使用中の場合はDatabaseInUseエラー）
+    pub fn acquire(db_path: &Path) -> Result<Self, AppError> {
+        let path = with_suffix(db_path, LOCK_FILE_SUFFIX);
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+
+        if let Err(error) = file.try_lock_exclusive() {
+            if error.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                let owner = read_owner(&mut file).map(|owner| owner.host).unwrap_or_else(host_name);
+                return Err(AppError::DatabaseInUse(owner));
+            }
+            // ネットワークドライブなどでOSのファイルロックが使えない場合は、ロックファイルの内容だけで確認する
+            log::warn!("ロックファイルをロックできませんでした（{}）: {}", path.display(), error);
+        }
+
+        let now = Utc::now();
+        if let Some(owner) = read_owner(&mut file) {
+            // 同じコンピューターのロックが残っている場合は、OSのファイルロックを取得できた時点で終了している
+            if owner.host != host_name() {
+                if (now - owner.heartbeat_at).num_seconds() < STALE_AFTER_SECONDS {
+                    let _ = FileExt::unlock(&file);
+                    return Err(AppError::DatabaseInUse(owner.host));
+                }
+                log::warn!(
+                    "{}のロックは{}から更新されていないため引き継ぎます",
+                    owner.host,
+                    owner.heartbeat_at.to_rfc3339()
+                );
+            }
+        }
+
+        let owner = LockOwner {
+            host: host_name(),
+            pid: std::process::id(),
+            acquired_at: now,
+            heartbeat_at: now,
+        };
+        write_owner(&mut file, Some(&owner))?;
+
+        let lock = LockFile {
+            path,
+            file: Arc::new(Mutex::new(Some(file))),
+        };
+        lock.keep_alive(owner);
+        Ok(lock)
+    }
+
+    /// 指定したデータベースのロックか
+    pub fn is_for(&self, db_path: &Path) -> bool {
+        self.path == with_suffix(db_path, LOCK_FILE_SUFFIX)
+    }
+
+    /// 解放されるまで、一定間隔でロックファイルの時刻を更新する
+    fn keep_alive(&self, mut owner: LockOwner) {
+        let file = Arc::clone(&self.file);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(HEARTBEAT_INTERVAL);
+            let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+            let Some(file) = file.as_mut() else { return };
+            owner.heartbeat_at = Utc::now();
+            if let Err(error) = write_owner(file, Some(&owner)) {
+                log::warn!("ロックファイルを更新できませんでした: {}", error);
+            }
+        });
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let Some(mut file) = self.file.lock().unwrap_or_else(PoisonError::into_inner).take() else {
+            return;
+        };
+        // 削除すると、削除する前に開いた別のアプリとの間でロックが重複しうるため、内容を消すだけにする
+        if let Err(error) = write_owner(&mut file, None) {
+            log::warn!("ロックファイルを解放できませんでした: {}", error);
+        }
+        let _ = FileExt::unlock(&file);
+    }
+}
+
+/// ロックファイルの内容（空や読み取れない場合は、使用中のアプリはない）
+fn read_owner(file: &mut File) -> Option<LockOwner> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_owner(file: &mut File, owner: Option<&LockOwner>) -> Result<(), AppError> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    if let Some(owner) = owner {
+        file.write_all(serde_json::to_string(owner)?.as_bytes())?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
+/// ロックファイルに記録するコンピューター名
+fn host_name() -> String {
+    static HOST_NAME: OnceLock<String> = OnceLock::new();
+    HOST_NAME
+        .get_or_init(|| {
+            std::process::Command::new("hostname")
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+                .filter(|name| !name.is_empty())
+                .or_else(|| std::env::var("COMPUTERNAME").ok())
+                .or_else(|| std::env::var("HOSTNAME").ok())
+                .unwrap_or_else(|| "unknown".to_string())
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_foreign_owner(db_path: &Path, heartbeat_at: DateTime<Utc>) {
+        let owner = LockOwner {
+            host: "other-host".to_string(),
+            pid: 1,
+            acquired_at: heartbeat_at,
+            heartbeat_at,
+        };
+        fs::write(with_suffix(db_path, LOCK_FILE_SUFFIX), serde_json::to_string(&owner).unwrap()).unwrap();
+    }
+
+    fn lock_content(db_path: &Path) -> String {
+        fs::read_to_string(with_suffix(db_path, LOCK_FILE_SUFFIX)).unwrap()
+    }
+
+    #[test]
+    fn held_lock_is_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("nomi-log.db");
+        let lock = LockFile::acquire(&db_path).unwrap();
+        assert!(lock.is_for(&db_path));
+
+        match LockFile::acquire(&db_path) {
+            Err(AppError::DatabaseInUse(host)) => assert_eq!(host, host_name()),
+            other => panic!("使用中になりませんでした: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn fresh_foreign_lock_is_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("nomi-log.db");
+        write_foreign_owner(&db_path, Utc::now() - chrono::Duration::seconds(30));
+
+        match LockFile::acquire(&db_path) {
+            Err(AppError::DatabaseInUse(host)) => assert_eq!(host, "other-host"),
+            other => panic!("使用中になりませんでした: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn stale_foreign_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("nomi-log.db");
+        write_foreign_owner(&db_path, Utc::now() - chrono::Duration::seconds(STALE_AFTER_SECONDS + 60));
+
+        let _lock = LockFile::acquire(&db_path).unwrap();
+        let owner: LockOwner = serde_json::from_str(&lock_content(&db_path)).unwrap();
+        assert_eq!(owner.host, host_name());
+        assert_eq!(owner.pid, std::process::id());
+    }
+
+    #[test]
+    fn drop_clears_the_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("nomi-log.db");
+        let lock = LockFile::acquire(&db_path).unwrap();
+        assert!(!lock_content(&db_path).is_empty());
+
+        drop(lock);
+        assert!(lock_content(&db_path).is_empty());
+        // 解放した後は再び取得できる
+        LockFile::acquire(&db_path).unwrap();
+    }
+}
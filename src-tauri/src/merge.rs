@@ -0,0 +1,381 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::i18n::{self, NameTable};
+use crate::messages::ErrorCode;
+use crate::models::*;
+use crate::recipes;
+use rusqlite::{params, Connection, OptionalExtension};
+
+fn beverage_name(conn: &Connection, id: i64) -> Result<String, AppError> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM beverages b WHERE b.id = ?1",
+            i18n::localized_name_sql(NameTable::Beverages, "b")
+        ),
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| ErrorDetail::new(ErrorCode::BeverageNotFound).into())
+}
+
+/// 統合元のお酒の投稿履歴を統合先へ付け替え、統合元を削除する
+///
+/// 同じ投稿に統合元と統合先が両方含まれる場合は飲んだ量を合算する。
+/// commitがfalseの場合はトランザクションをロールバックし、結果のみを返す（プレビュー）。
+pub fn merge_beverages(
+    conn: &Connection,
+    source_ids: &[i64],
+    target_id: i64,
+    commit: bool,
+) -> Result<MergeBeveragesReport, AppError> {
+    if source_ids.is_empty() {
+        return Err(ErrorDetail::new(ErrorCode::MergeSourcesRequired).into());
+    }
+    if source_ids.contains(&target_id) {
+        return Err(ErrorDetail::new(ErrorCode::MergeTargetInSources).into());
+    }
+    if (1..source_ids.len()).any(|i| source_ids[i..].contains(&source_ids[i - 1])) {
+        return Err(ErrorDetail::new(ErrorCode::DuplicateBeverage).into());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let mut report = MergeBeveragesReport {
+        target_id,
+        target_name: beverage_name(&tx, target_id)?,
+        sources: vec![],
+        moved_count: 0,
+        combined_count: 0,
+        affected_post_ids: vec![],
+        committed: commit,
+    };
+
+    for &source_id in source_ids {
+        let name = beverage_name(&tx, source_id)?;
+
+        if recipes::contains_beverage(&tx, target_id, source_id, 0)? {
+            return Err(ErrorDetail::new(ErrorCode::MergeRecipeConflict)
+                .param("name", name)
+                .into());
+        }
+
+        let mut stmt = tx.prepare(
+            "SELECT id, post_id, amount FROM post_beverages WHERE beverage_id = ?1 ORDER BY post_id",
+        )?;
+        let rows = stmt
+            .query_map(params![source_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (row_id, post_id, amount) in &rows {
+            let target_row: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM post_beverages WHERE post_id = ?1 AND beverage_id = ?2",
+                    params![post_id, target_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            match target_row {
+                // UNIQUE(post_id, beverage_id)に抵触するため、統合先の行に量を合算する
+                Some(target_row_id) => {
+                    tx.execute(
+                        "UPDATE post_beverages SET amount = amount + ?1 WHERE id = ?2",
+                        params![amount, target_row_id],
+                    )?;
+                    tx.execute("DELETE FROM post_beverages WHERE id = ?1", params![row_id])?;
+                    report.combined_count += 1;
+                }
+                None => {
+                    tx.execute(
+                        "UPDATE post_beverages SET beverage_id = ?1 WHERE id = ?2",
+                        params![target_id, row_id],
+                    )?;
+                    report.moved_count += 1;
+                }
+            }
+
+            if !report.affected_post_ids.contains(post_id) {
+                report.affected_post_ids.push(*post_id);
+            }
+        }
+
+        // レシピの材料としての参照も統合先に付け替える（同じレシピに統合先があれば量を合算）
+        let mut stmt = tx.prepare(
+            "SELECT id, beverage_id, amount FROM beverage_ingredients WHERE ingredient_id = ?1",
+        )?;
+        let ingredient_rows = stmt
+            .query_map(params![source_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (row_id, recipe_id, amount) in ingredient_rows {
+            let updated = tx.execute(
+                "UPDATE beverage_ingredients SET amount = amount + ?1
+                WHERE beverage_id = ?2 AND ingredient_id = ?3",
+                params![amount, recipe_id, target_id],
+            )?;
+            if updated > 0 {
+                tx.execute("DELETE FROM beverage_ingredients WHERE id = ?1", params![row_id])?;
+            } else {
+                tx.execute(
+                    "UPDATE beverage_ingredients SET ingredient_id = ?1 WHERE id = ?2",
+                    params![target_id, row_id],
+                )?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM beverage_ingredients WHERE beverage_id = ?1",
+            params![source_id],
+        )?;
+
+        // 記録中のセッションの1杯も統合先に付け替える
+        tx.execute(
+            "UPDATE session_drinks SET beverage_id = ?1 WHERE beverage_id = ?2",
+            params![target_id, source_id],
+        )?;
+
+        // 投稿テンプレートの参照も統合先に付け替える（同じテンプレートに統合先があれば量を合算）
+        let mut stmt = tx.prepare(
+            "SELECT id, template_id, amount FROM post_template_beverages WHERE beverage_id = ?1",
+        )?;
+        let template_rows = stmt
+            .query_map(params![source_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (row_id, template_id, amount) in template_rows {
+            let updated = tx.execute(
+                "UPDATE post_template_beverages SET amount = amount + ?1
+                WHERE template_id = ?2 AND beverage_id = ?3",
+                params![amount, template_id, target_id],
+            )?;
+            if updated > 0 {
+                tx.execute("DELETE FROM post_template_beverages WHERE id = ?1", params![row_id])?;
+            } else {
+                tx.execute(
+                    "UPDATE post_template_beverages SET beverage_id = ?1 WHERE id = ?2",
+                    params![target_id, row_id],
+                )?;
+            }
+        }
+
+        // バーコードとカタログキーは統合先に未設定の場合のみ引き継ぐ（カタログキーと取り込んだ時点の内容は一緒に引き継ぐ）
+        let (barcode, catalog_key, catalog_values): (Option<String>, Option<String>, Option<String>) = tx.query_row(
+            "SELECT barcode, catalog_key, catalog_values FROM beverages WHERE id = ?1",
+            params![source_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        tx.execute(
+            "DELETE FROM beverage_attributes WHERE beverage_id = ?1",
+            params![source_id],
+        )?;
+        i18n::delete_translations(&tx, NameTable::Beverages, source_id)?;
+        tx.execute("DELETE FROM beverages WHERE id = ?1", params![source_id])?;
+
+        tx.execute(
+            "UPDATE beverages SET
+                barcode = COALESCE(barcode, ?1),
+                catalog_values = CASE WHEN catalog_key IS NULL THEN ?3 ELSE catalog_values END,
+                catalog_key = COALESCE(catalog_key, ?2),
+                updated_at = datetime('now', 'localtime')
+            WHERE id = ?4",
+            params![barcode, catalog_key, catalog_values, target_id],
+        )?;
+
+        report.sources.push(MergeSourceSummary {
+            beverage_id: source_id,
+            name,
+            post_count: rows.len() as i64,
+        });
+    }
+
+    report.affected_post_ids.sort_unstable();
+
+    // 統合先を材料に使うレシピの度数を再計算する
+    recipes::refresh(&tx, target_id)?;
+
+    if commit {
+        tx.commit()?;
+    } else {
+        tx.rollback()?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn beverage(conn: &Connection, name: &str) -> i64 {
+        conn.execute(
+            "INSERT INTO beverages (name, alcohol_content, category_id)
+            VALUES (?1, 5.0, (SELECT MIN(id) FROM categories))",
+            params![name],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn post(conn: &Connection, beverages: &[(i64, f64)]) -> i64 {
+        conn.execute("INSERT INTO posts (date) VALUES ('2024-05-01')", []).unwrap();
+        let post_id = conn.last_insert_rowid();
+        for (beverage_id, amount) in beverages {
+            conn.execute(
+                "INSERT INTO post_beverages (post_id, beverage_id, amount) VALUES (?1, ?2, ?3)",
+                params![post_id, beverage_id, amount],
+            )
+            .unwrap();
+        }
+        post_id
+    }
+
+    fn template(conn: &Connection, name: &str, beverages: &[(i64, f64)]) -> i64 {
+        conn.execute("INSERT INTO post_templates (name) VALUES (?1)", params![name]).unwrap();
+        let template_id = conn.last_insert_rowid();
+        for (beverage_id, amount) in beverages {
+            conn.execute(
+                "INSERT INTO post_template_beverages (template_id, beverage_id, amount) VALUES (?1, ?2, ?3)",
+                params![template_id, beverage_id, amount],
+            )
+            .unwrap();
+        }
+        template_id
+    }
+
+    /// 投稿またはテンプレートのお酒ごとの量（お酒のid順）
+    fn amounts(conn: &Connection, table: &str, column: &str, id: i64) -> Vec<(i64, f64)> {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT beverage_id, amount FROM {} WHERE {} = ?1 ORDER BY beverage_id",
+                table, column
+            ))
+            .unwrap();
+        stmt.query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn beverage_exists(conn: &Connection, id: i64) -> bool {
+        conn.query_row("SELECT COUNT(*) FROM beverages WHERE id = ?1", params![id], |row| row.get::<_, i64>(0))
+            .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn merge_moves_history_and_sums_conflicting_amounts() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let target = beverage(conn, "アサヒスーパードライ");
+        let source = beverage(conn, "スーパードライ");
+        let other_source = beverage(conn, "スーパードライ（缶）");
+        conn.execute(
+            "UPDATE beverages SET barcode = '4901004000000', catalog_key = 'asahi_super_dry' WHERE id = ?1",
+            params![source],
+        )
+        .unwrap();
+
+        let both = post(conn, &[(target, 350.0), (source, 500.0)]);
+        let source_only = post(conn, &[(source, 350.0)]);
+        let other_only = post(conn, &[(other_source, 250.0)]);
+        let session = post(conn, &[]);
+        conn.execute(
+            "INSERT INTO session_drinks (post_id, beverage_id, amount, consumed_at)
+            VALUES (?1, ?2, 350, '2024-05-01 20:00:00')",
+            params![session, source],
+        )
+        .unwrap();
+        let combined_template = template(conn, "いつもの", &[(target, 350.0), (source, 350.0)]);
+        let moved_template = template(conn, "缶1本", &[(other_source, 350.0)]);
+
+        let report = merge_beverages(conn, &[source, other_source], target, true).unwrap();
+        assert!(report.committed);
+        assert_eq!(report.target_name, "アサヒスーパードライ");
+        assert_eq!((report.moved_count, report.combined_count), (2, 1));
+        assert_eq!(report.affected_post_ids, vec![both, source_only, other_only]);
+        assert_eq!(
+            report.sources.iter().map(|s| (s.beverage_id, s.post_count)).collect::<Vec<_>>(),
+            vec![(source, 2), (other_source, 1)]
+        );
+
+        // UNIQUE(post_id, beverage_id)に抵触する行は量を合算する
+        assert_eq!(amounts(conn, "post_beverages", "post_id", both), vec![(target, 850.0)]);
+        assert_eq!(amounts(conn, "post_beverages", "post_id", source_only), vec![(target, 350.0)]);
+        assert_eq!(amounts(conn, "post_beverages", "post_id", other_only), vec![(target, 250.0)]);
+        assert_eq!(amounts(conn, "session_drinks", "post_id", session), vec![(target, 350.0)]);
+        assert_eq!(amounts(conn, "post_template_beverages", "template_id", combined_template), vec![(target, 700.0)]);
+        assert_eq!(amounts(conn, "post_template_beverages", "template_id", moved_template), vec![(target, 350.0)]);
+
+        // 統合元は削除し、バーコードとカタログキーは統合先に引き継ぐ
+        assert!(!beverage_exists(conn, source));
+        assert!(!beverage_exists(conn, other_source));
+        let (barcode, catalog_key): (Option<String>, Option<String>) = conn
+            .query_row(
+                "SELECT barcode, catalog_key FROM beverages WHERE id = ?1",
+                params![target],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(barcode.as_deref(), Some("4901004000000"));
+        assert_eq!(catalog_key.as_deref(), Some("asahi_super_dry"));
+    }
+
+    #[test]
+    fn preview_rolls_back() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let target = beverage(conn, "アサヒスーパードライ");
+        let source = beverage(conn, "スーパードライ");
+        let both = post(conn, &[(target, 350.0), (source, 500.0)]);
+
+        let report = merge_beverages(conn, &[source], target, false).unwrap();
+        assert!(!report.committed);
+        assert_eq!((report.moved_count, report.combined_count), (0, 1));
+        assert_eq!(report.affected_post_ids, vec![both]);
+
+        assert!(beverage_exists(conn, source));
+        assert_eq!(
+            amounts(conn, "post_beverages", "post_id", both),
+            vec![(target, 350.0), (source, 500.0)]
+        );
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn invalid_merges_are_rejected() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let whisky = beverage(conn, "ウイスキー");
+        let highball = beverage(conn, "ハイボール");
+        let soda = beverage(conn, "ソーダ");
+        let highball_post = post(conn, &[(highball, 350.0)]);
+
+        let error = merge_beverages(conn, &[highball, whisky], whisky, true).unwrap_err();
+        assert_eq!(error.detail().code, ErrorCode::MergeTargetInSources);
+
+        // 統合先のレシピに統合元が含まれる場合は統合できない（統合先が自身を材料にすることになる）
+        recipes::set_recipe(
+            conn,
+            highball,
+            &[
+                RecipeIngredientInput { ingredient_id: whisky, amount: 30.0 },
+                RecipeIngredientInput { ingredient_id: soda, amount: 90.0 },
+            ],
+        )
+        .unwrap();
+        let error = merge_beverages(conn, &[whisky], highball, true).unwrap_err();
+        let detail = error.detail();
+        assert_eq!(detail.code, ErrorCode::MergeRecipeConflict);
+        assert_eq!(detail.params["name"], "ウイスキー");
+
+        assert!(beverage_exists(conn, whisky));
+        assert_eq!(amounts(conn, "post_beverages", "post_id", highball_post), vec![(highball, 350.0)]);
+        assert!(conn.is_autocommit());
+    }
+}
@@ -0,0 +1,683 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 表示する言語（エラーメッセージ・カテゴリー名・カタログのお酒の名前）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+// エラーメッセージに使う言語（言語の設定に合わせて切り替える）
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+impl Locale {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::Ja => "ja",
+            Locale::En => "en",
+        }
+    }
+
+    /// OSの言語設定（LC_ALL・LC_MESSAGES・LANGの順に参照する。対応していない言語はNone）
+    pub fn from_system() -> Option<Locale> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_tag(&value))
+    }
+
+    /// 現在のエラーメッセージの言語
+    pub fn current() -> Locale {
+        match CURRENT_LOCALE.load(Ordering::Relaxed) {
+            1 => Locale::En,
+            _ => Locale::Ja,
+        }
+    }
+
+    pub fn set_current(locale: Locale) {
+        CURRENT_LOCALE.store(locale as u8, Ordering::Relaxed);
+    }
+
+    /// "en-US"・"ja_JP"のような言語タグを解釈する（対応していない言語はNone）
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "ja" => Some(Locale::Ja),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+}
+
+/// エラーの種類を表す安定したコード（フロントエンドはこのコードで処理を分岐・翻訳する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // 内部エラー（params.detailに詳細）
+    Database,
+    Migration,
+    Serialization,
+    Io,
+    Internal,
+    // データベースの暗号化・使用中
+    PassphraseRequired,
+    WrongPassphrase,
+    DatabaseClosed,
+    DatabaseInUse,
+    // アプリのロック
+    AppLocked,
+    WrongPin,
+    TooManyUnlockAttempts,
+    // 入力検証（field_errorsに項目ごとのエラー）
+    ValidationFailed,
+    Required,
+    TooLong,
+    TooShort,
+    MustBePositive,
+    TooLarge,
+    NotANumber,
+    OutOfRange,
+    AlcoholContentRange,
+    BodyWeightRange,
+    InvalidDate,
+    InvalidDatetime,
+    InvalidMonth,
+    EndBeforeStart,
+    DateNotDrinkingDay,
+    // 存在しないデータ
+    BeverageNotFound,
+    CategoryNotFound,
+    ParentCategoryNotFound,
+    AttributeNotFound,
+    AttributeNotInCategory,
+    IngredientNotFound,
+    TemplateNotFound,
+    SessionDrinkNotFound,
+    CatalogCategoryNotFound,
+    // 重複
+    DuplicateBeverage,
+    DuplicateCategory,
+    DuplicateAttribute,
+    DuplicateIngredient,
+    CategoryNameExists,
+    AttributeNameExists,
+    TemplateNameExists,
+    BarcodeExists,
+    // カテゴリー・属性
+    CategoryParentCycle,
+    CategoryInUse,
+    CategoryHasChildren,
+    AllowedValuesRequired,
+    AttributeTypeInUse,
+    AllowedValueInUse,
+    AttributeValueType,
+    AttributeValueNotAllowed,
+    // お酒・レシピ・統合
+    BarcodeFormat,
+    BarcodeChecksum,
+    BeverageUsedInPosts,
+    BeverageUsedInRecipes,
+    BeverageUsedInTemplates,
+    RecipeSelfReference,
+    RecipeTooDeep,
+    MergeSourcesRequired,
+    MergeTargetInSources,
+    MergeRecipeConflict,
+    // 投稿・テンプレート・セッション・体調
+    TemplateBeveragesRequired,
+    PostBeveragesRequired,
+    NoActiveSession,
+    SessionAlreadyActive,
+    SessionEmpty,
+    PostInActiveSession,
+    WellbeingEmpty,
+    // バックアップ
+    BackupNotFound,
+    BackupInvalid,
+    BackupSchemaTooNew,
+    // プロファイル
+    ProfileNotFound,
+    ProfileNameExists,
+    ProfileNotDeletable,
+    DatabasePathNotAbsolute,
+    ProfileSwitchOverridden,
+}
+
+impl ErrorCode {
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::Database,
+        ErrorCode::Migration,
+        ErrorCode::Serialization,
+        ErrorCode::Io,
+        ErrorCode::Internal,
+        ErrorCode::PassphraseRequired,
+        ErrorCode::WrongPassphrase,
+        ErrorCode::DatabaseClosed,
+        ErrorCode::DatabaseInUse,
+        ErrorCode::AppLocked,
+        ErrorCode::WrongPin,
+        ErrorCode::TooManyUnlockAttempts,
+        ErrorCode::ValidationFailed,
+        ErrorCode::Required,
+        ErrorCode::TooLong,
+        ErrorCode::TooShort,
+        ErrorCode::MustBePositive,
+        ErrorCode::TooLarge,
+        ErrorCode::NotANumber,
+        ErrorCode::OutOfRange,
+        ErrorCode::AlcoholContentRange,
+        ErrorCode::BodyWeightRange,
+        ErrorCode::InvalidDate,
+        ErrorCode::InvalidDatetime,
+        ErrorCode::InvalidMonth,
+        ErrorCode::EndBeforeStart,
+        ErrorCode::DateNotDrinkingDay,
+        ErrorCode::BeverageNotFound,
+        ErrorCode::CategoryNotFound,
+        ErrorCode::ParentCategoryNotFound,
+        ErrorCode::AttributeNotFound,
+        ErrorCode::AttributeNotInCategory,
+        ErrorCode::IngredientNotFound,
+        ErrorCode::TemplateNotFound,
+        ErrorCode::SessionDrinkNotFound,
+        ErrorCode::CatalogCategoryNotFound,
+        ErrorCode::DuplicateBeverage,
+        ErrorCode::DuplicateCategory,
+        ErrorCode::DuplicateAttribute,
+        ErrorCode::DuplicateIngredient,
+        ErrorCode::CategoryNameExists,
+        ErrorCode::AttributeNameExists,
+        ErrorCode::TemplateNameExists,
+        ErrorCode::BarcodeExists,
+        ErrorCode::CategoryParentCycle,
+        ErrorCode::CategoryInUse,
+        ErrorCode::CategoryHasChildren,
+        ErrorCode::AllowedValuesRequired,
+        ErrorCode::AttributeTypeInUse,
+        ErrorCode::AllowedValueInUse,
+        ErrorCode::AttributeValueType,
+        ErrorCode::AttributeValueNotAllowed,
+        ErrorCode::BarcodeFormat,
+        ErrorCode::BarcodeChecksum,
+        ErrorCode::BeverageUsedInPosts,
+        ErrorCode::BeverageUsedInRecipes,
+        ErrorCode::BeverageUsedInTemplates,
+        ErrorCode::RecipeSelfReference,
+        ErrorCode::RecipeTooDeep,
+        ErrorCode::MergeSourcesRequired,
+        ErrorCode::MergeTargetInSources,
+        ErrorCode::MergeRecipeConflict,
+        ErrorCode::TemplateBeveragesRequired,
+        ErrorCode::PostBeveragesRequired,
+        ErrorCode::NoActiveSession,
+        ErrorCode::SessionAlreadyActive,
+        ErrorCode::SessionEmpty,
+        ErrorCode::PostInActiveSession,
+        ErrorCode::WellbeingEmpty,
+        ErrorCode::BackupNotFound,
+        ErrorCode::BackupInvalid,
+        ErrorCode::BackupSchemaTooNew,
+        ErrorCode::ProfileNotFound,
+        ErrorCode::ProfileNameExists,
+        ErrorCode::ProfileNotDeletable,
+        ErrorCode::DatabasePathNotAbsolute,
+        ErrorCode::ProfileSwitchOverridden,
+    ];
+
+    /// メッセージの雛形（{name}の部分はパラメーターで置き換える）
+    pub fn template(self, locale: Locale) -> &'static str {
+        use ErrorCode::*;
+        match locale {
+            Locale::Ja => match self {
+                Database => "データベースエラー: {detail}",
+                Migration => "データベースの更新に失敗しました: {detail}",
+                Serialization => "データの変換に失敗しました: {detail}",
+                Io => "ファイルの操作に失敗しました: {detail}",
+                Internal => "処理中に予期しないエラーが発生しました: {detail}",
+                PassphraseRequired => "データベースは暗号化されています。パスフレーズを入力してください",
+                WrongPassphrase => "パスフレーズが正しくありません",
+                DatabaseClosed => "データベースが開かれていません",
+                DatabaseInUse => "データベースは{host}の別ののみログで使用中です。そちらを終了してから開き直してください",
+                AppLocked => "アプリはロックされています。PINを入力してください",
+                WrongPin => "PINが正しくありません（あと{remaining_attempts}回間違えると、しばらく入力できなくなります）",
+                TooManyUnlockAttempts => "PINを続けて間違えたため、{retry_after_seconds}秒後にもう一度入力してください",
+                ValidationFailed => "入力内容に誤りがあります",
+                Required => "{label}を入力してください",
+                TooLong => "{label}は{max}文字以内で入力してください",
+                TooShort => "{label}は{min}文字以上で入力してください",
+                MustBePositive => "{label}は0より大きい数値を入力してください",
+                TooLarge => "{label}は{max}ml以下で入力してください",
+                NotANumber => "{label}は数値で入力してください",
+                OutOfRange => "{label}は{min}〜{max}で入力してください",
+                AlcoholContentRange => "アルコール度数は0〜100%の範囲で入力してください",
+                BodyWeightRange => "体重は0より大きい数値（kg）を入力してください",
+                InvalidDate => "日付「{value}」が正しくありません（YYYY-MM-DD形式の実在する日付を入力してください）",
+                InvalidDatetime => "{label}「{value}」が正しくありません（YYYY-MM-DD HH:MM形式の実在する日時を入力してください）",
+                InvalidMonth => "{year}年{month}月は正しい年月ではありません",
+                EndBeforeStart => "終了時刻は開始時刻より後の時刻を入力してください",
+                DateNotDrinkingDay => "開始時刻（{started_at}）の飲酒日は{drinking_day}です（日付の切り替え時刻: {rollover_hour}時）",
+                BeverageNotFound => "指定されたお酒が見つかりません",
+                CategoryNotFound => "指定されたカテゴリーが見つかりません",
+                ParentCategoryNotFound => "指定された親カテゴリーが見つかりません",
+                AttributeNotFound => "指定された属性が見つかりません",
+                AttributeNotInCategory => "指定された属性はこのカテゴリーに存在しません",
+                IngredientNotFound => "指定された材料のお酒が見つかりません",
+                TemplateNotFound => "指定されたテンプレートが見つかりません",
+                SessionDrinkNotFound => "指定された記録が見つかりません",
+                CatalogCategoryNotFound => "カタログにカテゴリー「{category}」は存在しません",
+                DuplicateBeverage => "同じお酒が複数指定されています",
+                DuplicateCategory => "同じカテゴリーが複数指定されています",
+                DuplicateAttribute => "同じ属性が複数指定されています",
+                DuplicateIngredient => "同じ材料が複数指定されています",
+                CategoryNameExists => "同じ名前のカテゴリーが既に存在します",
+                AttributeNameExists => "同じ名前の属性が既に存在します",
+                TemplateNameExists => "テンプレート「{name}」は既に存在します",
+                BarcodeExists => "このバーコードは既に「{name}」に登録されています",
+                CategoryParentCycle => "自身またはその子カテゴリーを親カテゴリーにすることはできません",
+                CategoryInUse => "このカテゴリーは使用中のため削除できません（アーカイブすると選択肢から非表示にできます）",
+                CategoryHasChildren => "子カテゴリーがあるため削除できません",
+                AllowedValuesRequired => "選択肢型の属性には選択肢を1つ以上指定してください",
+                AttributeTypeInUse => "この属性は{usage_count}件のお酒で使用されているため型を変更できません",
+                AllowedValueInUse => "選択肢「{value}」は使用中のため削除できません",
+                AttributeValueType => "「{name}」には{expected}を入力してください",
+                AttributeValueNotAllowed => "「{name}」は{allowed_values}のいずれかを選択してください",
+                BarcodeFormat => "バーコードは8桁・12桁・13桁のいずれかの数字で入力してください",
+                BarcodeChecksum => "バーコードのチェックディジットが正しくありません",
+                BeverageUsedInPosts => "このお酒は{usage_count}件の投稿で使用されているため削除できません（アーカイブすると選択肢から非表示にできます）",
+                BeverageUsedInRecipes => "このお酒は{usage_count}件のレシピの材料として使用されているため削除できません",
+                BeverageUsedInTemplates => "このお酒は{usage_count}件の投稿テンプレートで使用されているため削除できません",
+                RecipeSelfReference => "自身を含むお酒を材料にすることはできません",
+                RecipeTooDeep => "レシピの入れ子が深すぎます",
+                MergeSourcesRequired => "統合するお酒を選択してください",
+                MergeTargetInSources => "統合先のお酒は統合元に含めることはできません",
+                MergeRecipeConflict => "統合先のレシピに「{name}」が材料として含まれているため統合できません",
+                TemplateBeveragesRequired => "テンプレートにお酒を1つ以上追加してください",
+                PostBeveragesRequired => "投稿するお酒がありません",
+                NoActiveSession => "記録中のセッションがありません",
+                SessionAlreadyActive => "既に記録中のセッションがあります。終了してから開始してください",
+                SessionEmpty => "お酒が記録されていません（記録しない場合はセッションを破棄してください）",
+                PostInActiveSession => "記録中のセッションは編集できません。セッションを終了してから編集してください",
+                WellbeingEmpty => "体調の項目を1つ以上入力してください",
+                BackupNotFound => "指定されたバックアップが見つかりません",
+                BackupInvalid => "バックアップ「{id}」は壊れているか、のみログのデータではありません",
+                BackupSchemaTooNew => "バックアップ「{id}」は新しいバージョンのアプリで作成されたため復元できません（バックアップ: v{backup_version}、アプリ: v{app_version}）",
+                ProfileNotFound => "指定されたプロファイルが見つかりません",
+                ProfileNameExists => "プロファイル「{name}」は既に存在します",
+                ProfileNotDeletable => "プロファイル「{name}」は使用中か既定のプロファイルのため削除できません",
+                DatabasePathNotAbsolute => "データベースの場所は絶対パスで指定してください",
+                ProfileSwitchOverridden => "起動時に指定されたデータベース（{path}）を使用中のため、プロファイルを切り替えられません。指定せずに起動し直してください",
+            },
+            Locale::En => match self {
+                Database => "Database error: {detail}",
+                Migration => "Failed to upgrade the database: {detail}",
+                Serialization => "Failed to convert data: {detail}",
+                Io => "File operation failed: {detail}",
+                Internal => "An unexpected error occurred: {detail}",
+                PassphraseRequired => "The database is encrypted. Enter the passphrase",
+                WrongPassphrase => "The passphrase is incorrect",
+                DatabaseClosed => "The database is not open",
+                DatabaseInUse => "The database is in use by another nomi-log instance on {host}. Quit it and then reopen the database",
+                AppLocked => "The app is locked. Enter your PIN",
+                WrongPin => "The PIN is incorrect ({remaining_attempts} attempts left before a waiting period)",
+                TooManyUnlockAttempts => "Too many incorrect PINs. Try again in {retry_after_seconds} seconds",
+                ValidationFailed => "Some fields are invalid",
+                Required => "{label} is required",
+                TooLong => "{label} must be at most {max} characters",
+                TooShort => "{label} must be at least {min} characters",
+                MustBePositive => "{label} must be a number greater than 0",
+                TooLarge => "{label} must be at most {max} ml",
+                NotANumber => "{label} must be a number",
+                OutOfRange => "{label} must be between {min} and {max}",
+                AlcoholContentRange => "Alcohol content must be between 0 and 100%",
+                BodyWeightRange => "Body weight must be a number greater than 0 (kg)",
+                InvalidDate => "Invalid date \"{value}\" (enter an existing date as YYYY-MM-DD)",
+                InvalidDatetime => "Invalid {label} \"{value}\" (enter an existing date and time as YYYY-MM-DD HH:MM)",
+                InvalidMonth => "{year}-{month} is not a valid month",
+                EndBeforeStart => "The end time must be later than the start time",
+                DateNotDrinkingDay => "The drinking day of the start time ({started_at}) is {drinking_day} (the day starts at {rollover_hour}:00)",
+                BeverageNotFound => "The specified beverage was not found",
+                CategoryNotFound => "The specified category was not found",
+                ParentCategoryNotFound => "The specified parent category was not found",
+                AttributeNotFound => "The specified attribute was not found",
+                AttributeNotInCategory => "The specified attribute does not belong to this category",
+                IngredientNotFound => "The beverage specified as an ingredient was not found",
+                TemplateNotFound => "The specified template was not found",
+                SessionDrinkNotFound => "The specified drink was not found",
+                CatalogCategoryNotFound => "The catalog has no category \"{category}\"",
+                DuplicateBeverage => "The same beverage is specified more than once",
+                DuplicateCategory => "The same category is specified more than once",
+                DuplicateAttribute => "The same attribute is specified more than once",
+                DuplicateIngredient => "The same ingredient is specified more than once",
+                CategoryNameExists => "A category with the same name already exists",
+                AttributeNameExists => "An attribute with the same name already exists",
+                TemplateNameExists => "A template named \"{name}\" already exists",
+                BarcodeExists => "This barcode is already registered to \"{name}\"",
+                CategoryParentCycle => "A category cannot be its own parent or a child of its descendants",
+                CategoryInUse => "This category is in use and cannot be deleted (archive it to hide it from the choices)",
+                CategoryHasChildren => "This category has child categories and cannot be deleted",
+                AllowedValuesRequired => "A select attribute needs at least one choice",
+                AttributeTypeInUse => "This attribute is used by {usage_count} beverage(s), so its type cannot be changed",
+                AllowedValueInUse => "The choice \"{value}\" is in use and cannot be removed",
+                AttributeValueType => "\"{name}\" must be {expected}",
+                AttributeValueNotAllowed => "\"{name}\" must be one of {allowed_values}",
+                BarcodeFormat => "A barcode must be 8, 12 or 13 digits",
+                BarcodeChecksum => "The barcode check digit is incorrect",
+                BeverageUsedInPosts => "This beverage is used in {usage_count} post(s) and cannot be deleted (archive it to hide it from the choices)",
+                BeverageUsedInRecipes => "This beverage is an ingredient of {usage_count} recipe(s) and cannot be deleted",
+                BeverageUsedInTemplates => "This beverage is used in {usage_count} post template(s) and cannot be deleted",
+                RecipeSelfReference => "A beverage that contains itself cannot be used as an ingredient",
+                RecipeTooDeep => "Recipes are nested too deeply",
+                MergeSourcesRequired => "Select the beverages to merge",
+                MergeTargetInSources => "The merge target cannot be one of the beverages being merged",
+                MergeRecipeConflict => "Cannot merge because the target's recipe contains \"{name}\" as an ingredient",
+                TemplateBeveragesRequired => "Add at least one beverage to the template",
+                PostBeveragesRequired => "There are no beverages to post",
+                NoActiveSession => "There is no session in progress",
+                SessionAlreadyActive => "A session is already in progress. End it before starting a new one",
+                SessionEmpty => "No drinks have been recorded (discard the session if you don't want to save it)",
+                PostInActiveSession => "A session in progress cannot be edited. End the session before editing it",
+                WellbeingEmpty => "Enter at least one wellbeing item",
+                BackupNotFound => "The specified backup was not found",
+                BackupInvalid => "Backup \"{id}\" is damaged or is not nomi-log data",
+                BackupSchemaTooNew => "Backup \"{id}\" was created by a newer version of the app and cannot be restored (backup: v{backup_version}, app: v{app_version})",
+                ProfileNotFound => "The specified profile was not found",
+                ProfileNameExists => "A profile named \"{name}\" already exists",
+                ProfileNotDeletable => "Profile \"{name}\" cannot be deleted because it is in use or is the default profile",
+                DatabasePathNotAbsolute => "Specify the database location as an absolute path",
+                ProfileSwitchOverridden => "Profiles cannot be switched while using the database specified at startup ({path}). Restart the app without specifying it",
+            },
+        }
+    }
+}
+
+/// メッセージ中で項目名・値の種類として使う語（パラメーターにはこのキーを入れる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Label {
+    BeverageName,
+    CategoryName,
+    AttributeName,
+    TemplateName,
+    AllowedValue,
+    Comment,
+    Notes,
+    Amount,
+    IngredientAmount,
+    Volume,
+    Serving,
+    DisplayOrder,
+    HangoverSeverity,
+    SleepQuality,
+    Mood,
+    DayRolloverHour,
+    BackupKeepDaily,
+    BackupKeepMonthly,
+    Datetime,
+    StartedAt,
+    EndedAt,
+    ConsumedAt,
+    Passphrase,
+    Pin,
+    IdleTimeout,
+    ProfileName,
+    // 属性の値の種類
+    Text,
+    Number,
+    Integer,
+    Boolean,
+    Select,
+}
+
+impl Label {
+    pub const ALL: &'static [Label] = &[
+        Label::BeverageName,
+        Label::CategoryName,
+        Label::AttributeName,
+        Label::TemplateName,
+        Label::AllowedValue,
+        Label::Comment,
+        Label::Notes,
+        Label::Amount,
+        Label::IngredientAmount,
+        Label::Volume,
+        Label::Serving,
+        Label::DisplayOrder,
+        Label::HangoverSeverity,
+        Label::SleepQuality,
+        Label::Mood,
+        Label::DayRolloverHour,
+        Label::BackupKeepDaily,
+        Label::BackupKeepMonthly,
+        Label::Datetime,
+        Label::StartedAt,
+        Label::EndedAt,
+        Label::ConsumedAt,
+        Label::Passphrase,
+        Label::Pin,
+        Label::IdleTimeout,
+        Label::ProfileName,
+        Label::Text,
+        Label::Number,
+        Label::Integer,
+        Label::Boolean,
+        Label::Select,
+    ];
+
+    pub fn text(self, locale: Locale) -> &'static str {
+        use Label::*;
+        match locale {
+            Locale::Ja => match self {
+                BeverageName => "お酒の名称",
+                CategoryName => "カテゴリー名",
+                AttributeName => "属性名",
+                TemplateName => "テンプレート名",
+                AllowedValue => "選択肢",
+                Comment => "コメント",
+                Notes => "メモ",
+                Amount => "飲んだ量",
+                IngredientAmount => "材料の量",
+                Volume => "容量",
+                Serving => "1杯の量",
+                DisplayOrder => "表示順",
+                HangoverSeverity => "二日酔いの程度",
+                SleepQuality => "睡眠の質",
+                Mood => "気分",
+                DayRolloverHour => "日付の切り替え時刻（時）",
+                BackupKeepDaily => "日ごとに残すバックアップの数",
+                BackupKeepMonthly => "月ごとに残すバックアップの数",
+                Datetime => "日時",
+                StartedAt => "開始時刻",
+                EndedAt => "終了時刻",
+                ConsumedAt => "飲んだ時刻",
+                Passphrase => "パスフレーズ",
+                Pin => "PIN",
+                IdleTimeout => "自動ロックまでの時間（分）",
+                ProfileName => "プロファイル名",
+                Text => "文字列",
+                Number => "数値",
+                Integer => "整数",
+                Boolean => "真偽値",
+                Select => "選択肢",
+            },
+            Locale::En => match self {
+                BeverageName => "Beverage name",
+                CategoryName => "Category name",
+                AttributeName => "Attribute name",
+                TemplateName => "Template name",
+                AllowedValue => "Choice",
+                Comment => "Comment",
+                Notes => "Notes",
+                Amount => "Amount",
+                IngredientAmount => "Ingredient amount",
+                Volume => "Volume",
+                Serving => "Serving size",
+                DisplayOrder => "Display order",
+                HangoverSeverity => "Hangover severity",
+                SleepQuality => "Sleep quality",
+                Mood => "Mood",
+                DayRolloverHour => "Day rollover hour",
+                BackupKeepDaily => "Daily backups to keep",
+                BackupKeepMonthly => "Monthly backups to keep",
+                Datetime => "date and time",
+                StartedAt => "start time",
+                EndedAt => "end time",
+                ConsumedAt => "drink time",
+                Passphrase => "Passphrase",
+                Pin => "PIN",
+                IdleTimeout => "Auto-lock timeout (minutes)",
+                ProfileName => "Profile name",
+                Text => "text",
+                Number => "a number",
+                Integer => "an integer",
+                Boolean => "true or false",
+                Select => "a choice",
+            },
+        }
+    }
+}
+
+/// 体調の分析で前日の飲み方をまとめた区分の表示名（{min}・{max}には区分の範囲を入れる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupLabel {
+    NoDrinking,
+    IntakeAtMost,
+    IntakeBetween,
+    IntakeOver,
+    PaceAtMost,
+    PaceBetween,
+    PaceOver,
+    SingleCategory,
+    MixedCategories,
+}
+
+impl GroupLabel {
+    pub const ALL: &'static [GroupLabel] = &[
+        GroupLabel::NoDrinking,
+        GroupLabel::IntakeAtMost,
+        GroupLabel::IntakeBetween,
+        GroupLabel::IntakeOver,
+        GroupLabel::PaceAtMost,
+        GroupLabel::PaceBetween,
+        GroupLabel::PaceOver,
+        GroupLabel::SingleCategory,
+        GroupLabel::MixedCategories,
+    ];
+
+    pub fn template(self, locale: Locale) -> &'static str {
+        use GroupLabel::*;
+        match locale {
+            Locale::Ja => match self {
+                NoDrinking => "飲酒なし",
+                IntakeAtMost => "{max}g以下",
+                IntakeBetween => "{min}〜{max}g",
+                IntakeOver => "{min}g超",
+                PaceAtMost => "{max}g/時以下",
+                PaceBetween => "{min}〜{max}g/時",
+                PaceOver => "{min}g/時超",
+                SingleCategory => "1種類のみ",
+                MixedCategories => "複数の種類（ちゃんぽん）",
+            },
+            Locale::En => match self {
+                NoDrinking => "No drinking",
+                IntakeAtMost => "Up to {max} g",
+                IntakeBetween => "{min}-{max} g",
+                IntakeOver => "Over {min} g",
+                PaceAtMost => "Up to {max} g/h",
+                PaceBetween => "{min}-{max} g/h",
+                PaceOver => "Over {min} g/h",
+                SingleCategory => "One kind only",
+                MixedCategories => "Several kinds (mixing)",
+            },
+        }
+    }
+
+    /// 区分の範囲を埋め込んだ表示名
+    pub fn render(self, min: Option<f64>, max: Option<f64>, locale: Locale) -> String {
+        let mut text = self.template(locale).to_string();
+        for (key, value) in [("min", min), ("max", max)] {
+            if let Some(value) = value {
+                text = text.replace(&format!("{{{}}}", key), &param_text(&Value::from(value), locale, false));
+            }
+        }
+        text
+    }
+}
+
+/// 言語ごとのメッセージの雛形・項目名・体調の分析の区分名（フロントエンドでの翻訳用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageCatalog {
+    pub locale: Locale,
+    pub errors: BTreeMap<ErrorCode, String>,
+    pub labels: BTreeMap<Label, String>,
+    pub groups: BTreeMap<GroupLabel, String>,
+}
+
+pub fn catalog(locale: Locale) -> MessageCatalog {
+    MessageCatalog {
+        locale,
+        errors: ErrorCode::ALL
+            .iter()
+            .map(|code| (*code, code.template(locale).to_string()))
+            .collect(),
+        labels: Label::ALL
+            .iter()
+            .map(|label| (*label, label.text(locale).to_string()))
+            .collect(),
+        groups: GroupLabel::ALL
+            .iter()
+            .map(|label| (*label, label.template(locale).to_string()))
+            .collect(),
+    }
+}
+
+// 値を項目名（Label）のキーとして翻訳するパラメーター
+const LABEL_PARAMS: &[&str] = &["label", "expected"];
+
+/// パラメーターの値をメッセージに埋め込む文字列にする（配列は区切って並べる）
+fn param_text(value: &Value, locale: Locale, is_label: bool) -> String {
+    match value {
+        Value::String(text) if is_label => serde_json::from_value::<Label>(value.clone())
+            .map(|label| label.text(locale).to_string())
+            .unwrap_or_else(|_| text.clone()),
+        Value::String(text) => text.clone(),
+        Value::Array(values) => {
+            let separator = match locale {
+                Locale::Ja => "・",
+                Locale::En => ", ",
+            };
+            values
+                .iter()
+                .map(|value| param_text(value, locale, is_label))
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+        // 整数の値の数値（例: 10000.0）は小数点以下を付けない
+        Value::Number(number) => match number.as_f64() {
+            Some(float) if number.is_f64() && float.fract() == 0.0 => format!("{}", float as i64),
+            _ => number.to_string(),
+        },
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// エラーコードとパラメーターから指定した言語のメッセージを組み立てる
+pub fn render(code: ErrorCode, params: &BTreeMap<String, Value>, locale: Locale) -> String {
+    let mut message = code.template(locale).to_string();
+    for (key, value) in params {
+        let text = param_text(value, locale, LABEL_PARAMS.contains(&key.as_str()));
+        message = message.replace(&format!("{{{}}}", key), &text);
+    }
+    message
+}
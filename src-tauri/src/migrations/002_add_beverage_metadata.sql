@@ -0,0 +1,12 @@
+-- マイグレーション v2 -> v3: beveragesテーブルに詳細情報（製造元・産地・スタイル・容量・バーコード・メモ）を追加
+
+ALTER TABLE beverages ADD COLUMN producer TEXT;
+ALTER TABLE beverages ADD COLUMN country TEXT;
+ALTER TABLE beverages ADD COLUMN region TEXT;
+ALTER TABLE beverages ADD COLUMN style TEXT;
+ALTER TABLE beverages ADD COLUMN volume_ml REAL;
+ALTER TABLE beverages ADD COLUMN barcode TEXT;
+ALTER TABLE beverages ADD COLUMN notes TEXT;
+
+-- スキャンしたバーコードから一意にお酒を引けるようにする（NULLは重複可）
+CREATE UNIQUE INDEX IF NOT EXISTS idx_beverages_barcode ON beverages(barcode);
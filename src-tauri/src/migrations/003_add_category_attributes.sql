@@ -0,0 +1,46 @@
+-- マイグレーション v3 -> v4: カテゴリーごとの属性スキーマ（category_attributes）と
+-- お酒ごとの属性値（beverage_attributes）を追加
+
+CREATE TABLE IF NOT EXISTS category_attributes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    category_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value_type TEXT NOT NULL,
+    unit TEXT,
+    allowed_values TEXT,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE,
+    UNIQUE(category_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS beverage_attributes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    beverage_id INTEGER NOT NULL,
+    attribute_id INTEGER NOT NULL,
+    value_text TEXT,
+    value_number REAL,
+    FOREIGN KEY (beverage_id) REFERENCES beverages(id) ON DELETE CASCADE,
+    FOREIGN KEY (attribute_id) REFERENCES category_attributes(id) ON DELETE CASCADE,
+    UNIQUE(beverage_id, attribute_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_category_attributes_category_id ON category_attributes(category_id);
+CREATE INDEX IF NOT EXISTS idx_beverage_attributes_attribute_id ON beverage_attributes(attribute_id);
+
+-- 初期カテゴリーが残っている場合は標準の属性を追加する
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, '精米歩合', 'number', '%', 1 FROM categories WHERE name = '日本酒';
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, '日本酒度', 'number', NULL, 2 FROM categories WHERE name = '日本酒';
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, '品種', 'text', NULL, 1 FROM categories WHERE name = 'ワイン';
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, 'ヴィンテージ', 'integer', '年', 2 FROM categories WHERE name = 'ワイン';
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, 'IBU', 'number', NULL, 1 FROM categories WHERE name = 'ビール';
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, '樽', 'text', NULL, 1 FROM categories WHERE name = 'ウイスキー';
+INSERT OR IGNORE INTO category_attributes (category_id, name, value_type, unit, display_order)
+SELECT id, '熟成年数', 'integer', '年', 2 FROM categories WHERE name = 'ウイスキー';
@@ -0,0 +1,17 @@
+-- マイグレーション v4 -> v5: 同梱カタログの取り込みに対応
+-- beveragesに標準の1杯量とカタログ項目のキーを追加し、取り込み履歴テーブルを作成
+
+ALTER TABLE beverages ADD COLUMN serving_ml REAL;
+ALTER TABLE beverages ADD COLUMN catalog_key TEXT;
+
+-- 同じカタログ項目を重複して取り込まないようにする（NULLは重複可）
+CREATE UNIQUE INDEX IF NOT EXISTS idx_beverages_catalog_key ON beverages(catalog_key);
+
+CREATE TABLE IF NOT EXISTS catalog_imports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    version INTEGER NOT NULL,
+    categories TEXT,
+    inserted_count INTEGER NOT NULL DEFAULT 0,
+    linked_count INTEGER NOT NULL DEFAULT 0,
+    imported_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
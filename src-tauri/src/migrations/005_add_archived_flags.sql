@@ -0,0 +1,5 @@
+-- マイグレーション v5 -> v6: beverages・categoriesにアーカイブフラグを追加
+-- アーカイブしたお酒・カテゴリーは選択肢から除外されるが、投稿履歴や集計では引き続き参照される
+
+ALTER TABLE beverages ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- マイグレーション v6 -> v7: categoriesに親カテゴリー（parent_id）を追加し、階層化に対応
+-- 例: ワイン → 赤 / 白 / スパークリング
+
+ALTER TABLE categories ADD COLUMN parent_id INTEGER REFERENCES categories(id);
+
+CREATE INDEX IF NOT EXISTS idx_categories_parent_id ON categories(parent_id);
@@ -0,0 +1,15 @@
+-- マイグレーション v7 -> v8: カクテル・割り物のレシピ（材料のお酒と量）を格納するテーブルを追加
+-- レシピを持つお酒のalcohol_content・serving_mlは材料から算出した値で更新される
+
+CREATE TABLE IF NOT EXISTS beverage_ingredients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    beverage_id INTEGER NOT NULL,
+    ingredient_id INTEGER NOT NULL,
+    amount REAL NOT NULL,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (beverage_id) REFERENCES beverages(id) ON DELETE CASCADE,
+    FOREIGN KEY (ingredient_id) REFERENCES beverages(id),
+    UNIQUE(beverage_id, ingredient_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_beverage_ingredients_ingredient_id ON beverage_ingredients(ingredient_id);
@@ -0,0 +1,3 @@
+-- マイグレーション v8 -> v9: beveragesにお気に入り（ピン留め）フラグを追加
+
+ALTER TABLE beverages ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,24 @@
+-- マイグレーション v9 -> v10: 定番の飲み方を投稿テンプレートとして保存するテーブルを追加
+-- tagsはタグ名のJSON配列
+
+CREATE TABLE IF NOT EXISTS post_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    comment TEXT,
+    tags TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
+
+CREATE TABLE IF NOT EXISTS post_template_beverages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    template_id INTEGER NOT NULL,
+    beverage_id INTEGER NOT NULL,
+    amount REAL NOT NULL,
+    display_order INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (template_id) REFERENCES post_templates(id) ON DELETE CASCADE,
+    FOREIGN KEY (beverage_id) REFERENCES beverages(id),
+    UNIQUE(template_id, beverage_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_post_template_beverages_beverage_id ON post_template_beverages(beverage_id);
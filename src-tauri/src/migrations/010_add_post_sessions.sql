@@ -0,0 +1,8 @@
+-- マイグレーション v10 -> v11: 投稿に飲酒の開始・終了時刻、お酒ごとに飲んだ時刻を追加
+-- 時刻はSQLiteのdatetime形式（YYYY-MM-DD HH:MM:SS）で保存する
+
+ALTER TABLE posts ADD COLUMN started_at TEXT;
+ALTER TABLE posts ADD COLUMN ended_at TEXT;
+ALTER TABLE post_beverages ADD COLUMN consumed_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_posts_started_at ON posts(started_at);
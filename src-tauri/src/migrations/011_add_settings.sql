@@ -0,0 +1,7 @@
+-- マイグレーション v11 -> v12: アプリの設定をキーと値で格納するテーブルを追加
+
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
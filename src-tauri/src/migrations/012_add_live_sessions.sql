@@ -0,0 +1,19 @@
+-- マイグレーション v12 -> v13: 記録中の飲酒セッション（ライブモード）を追加
+-- activeが1の投稿は記録中のセッション（同時に1つまで）。session_drinksは記録中の1杯ごとの履歴で、
+-- post_beveragesにはお酒ごとに合算した量を反映する。セッション終了時にsession_drinksは削除する
+
+ALTER TABLE posts ADD COLUMN active INTEGER NOT NULL DEFAULT 0;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_posts_active ON posts(active) WHERE active = 1;
+
+CREATE TABLE IF NOT EXISTS session_drinks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    post_id INTEGER NOT NULL,
+    beverage_id INTEGER NOT NULL,
+    amount REAL NOT NULL,
+    consumed_at TEXT NOT NULL,
+    FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE,
+    FOREIGN KEY (beverage_id) REFERENCES beverages(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_session_drinks_post_id ON session_drinks(post_id);
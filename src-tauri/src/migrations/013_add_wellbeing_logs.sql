@@ -0,0 +1,13 @@
+-- マイグレーション v13 -> v14: 翌朝の体調（二日酔い・睡眠の質・気分）を日付ごとに記録するテーブルを追加
+-- hangover_severityは0（なし）〜5（重い）、sleep_quality・moodは1（悪い）〜5（良い）
+
+CREATE TABLE IF NOT EXISTS wellbeing_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    date TEXT NOT NULL UNIQUE,
+    hangover_severity INTEGER,
+    sleep_quality INTEGER,
+    mood INTEGER,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
@@ -0,0 +1,36 @@
+-- マイグレーション v14 -> v15: 日時を正規化し、不正な形式の日時を修復する
+-- 日時はSQLiteのdatetime形式（YYYY-MM-DD HH:MM:SS）に揃え、解釈できない任意の日時はNULLにする
+-- 作成・更新日時が解釈できない場合は現在時刻（更新日時は作成日時）で補う
+-- 日付（posts.date・wellbeing_logs.date）の修復は形式の推定が必要なため、マイグレーションのフックで行う
+
+UPDATE posts SET started_at = datetime(started_at) WHERE started_at IS NOT datetime(started_at);
+UPDATE posts SET ended_at = datetime(ended_at) WHERE ended_at IS NOT datetime(ended_at);
+UPDATE post_beverages SET consumed_at = datetime(consumed_at) WHERE consumed_at IS NOT datetime(consumed_at);
+UPDATE session_drinks
+SET consumed_at = COALESCE(datetime(consumed_at), datetime('now', 'localtime'))
+WHERE consumed_at IS NOT datetime(consumed_at);
+
+UPDATE posts
+SET created_at = COALESCE(datetime(created_at), datetime('now', 'localtime'))
+WHERE created_at IS NOT datetime(created_at);
+UPDATE posts SET updated_at = COALESCE(datetime(updated_at), created_at) WHERE updated_at IS NOT datetime(updated_at);
+
+UPDATE categories
+SET created_at = COALESCE(datetime(created_at), datetime('now', 'localtime'))
+WHERE created_at IS NOT datetime(created_at);
+UPDATE categories SET updated_at = COALESCE(datetime(updated_at), created_at) WHERE updated_at IS NOT datetime(updated_at);
+
+UPDATE beverages
+SET created_at = COALESCE(datetime(created_at), datetime('now', 'localtime'))
+WHERE created_at IS NOT datetime(created_at);
+UPDATE beverages SET updated_at = COALESCE(datetime(updated_at), created_at) WHERE updated_at IS NOT datetime(updated_at);
+
+UPDATE post_templates
+SET created_at = COALESCE(datetime(created_at), datetime('now', 'localtime'))
+WHERE created_at IS NOT datetime(created_at);
+UPDATE post_templates SET updated_at = COALESCE(datetime(updated_at), created_at) WHERE updated_at IS NOT datetime(updated_at);
+
+UPDATE wellbeing_logs
+SET created_at = COALESCE(datetime(created_at), datetime('now', 'localtime'))
+WHERE created_at IS NOT datetime(created_at);
+UPDATE wellbeing_logs SET updated_at = COALESCE(datetime(updated_at), created_at) WHERE updated_at IS NOT datetime(updated_at);
@@ -0,0 +1,24 @@
+-- マイグレーション v15 -> v16: カテゴリー・お酒の言語ごとの名前（翻訳）に対応
+-- categoriesに同梱のカテゴリーを識別するキーを追加し、言語ごとの名前を保存するテーブルを作成する
+-- 既存の同梱カテゴリー・カタログのお酒へのキーと翻訳の付与は、同梱データが必要なためマイグレーションのフックで行う
+
+ALTER TABLE categories ADD COLUMN seed_key TEXT;
+
+-- 同梱のカテゴリーは1つだけ作成する（NULLは重複可）
+CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_seed_key ON categories(seed_key);
+
+CREATE TABLE IF NOT EXISTS category_translations (
+    category_id INTEGER NOT NULL,
+    locale TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (category_id, locale),
+    FOREIGN KEY (category_id) REFERENCES categories(id)
+);
+
+CREATE TABLE IF NOT EXISTS beverage_translations (
+    beverage_id INTEGER NOT NULL,
+    locale TEXT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (beverage_id, locale),
+    FOREIGN KEY (beverage_id) REFERENCES beverages(id)
+);
@@ -0,0 +1,7 @@
+-- マイグレーション v16 -> v17: カタログの再取り込みで、ユーザーが編集していない項目を更新できるようにする
+-- 取り込んだ時点のカタログの内容（JSON）をお酒ごとに保存し、取り込み履歴に更新した件数を追加する
+-- 既に取り込んだお酒は取り込んだ時点の内容が分からないため、次の取り込みで現在のカタログの内容を記録する
+
+ALTER TABLE beverages ADD COLUMN catalog_values TEXT;
+
+ALTER TABLE catalog_imports ADD COLUMN updated_count INTEGER NOT NULL DEFAULT 0;
@@ -1,22 +1,41 @@
+use crate::messages::Locale;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Post {
     pub id: Option<i64>,
-    pub date: String,
+    pub date: NaiveDate,
     pub comment: Option<String>,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub updated_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PostWithBeverages {
     pub id: i64,
-    pub date: String,
+    pub date: NaiveDate,
     pub comment: Option<String>,
-    pub created_at: String,
-    pub updated_at: String,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub started_at: Option<NaiveDateTime>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub ended_at: Option<NaiveDateTime>,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub updated_at: NaiveDateTime,
     pub beverages: Vec<BeverageAmount>,
+    pub pace: SessionPace,
+}
+
+/// 飲酒セッションのペース（開始・終了時刻が未設定の場合は飲んだ時刻から推定する）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionPace {
+    pub duration_minutes: Option<f64>,
+    pub total_pure_alcohol: f64,
+    pub grams_per_hour: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,12 +44,18 @@ pub struct BeverageAmount {
     pub beverage_name: String,
     pub amount: f64,
     pub alcohol_content: Option<f64>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub consumed_at: Option<NaiveDateTime>,
 }
 
+/// 開始・終了時刻とお酒ごとの飲んだ時刻は、日時（YYYY-MM-DD HH:MM）または時刻のみ（HH:MM）で受け付ける
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreatePostRequest {
-    pub date: String,
+    #[serde(with = "crate::dates::date_format")]
+    pub date: NaiveDate,
     pub comment: Option<String>,
+    pub started_at: Option<String>,
+    pub ended_at: Option<String>,
     pub beverages: Vec<BeverageAmountInput>,
 }
 
@@ -38,6 +63,7 @@ pub struct CreatePostRequest {
 pub struct BeverageAmountInput {
     pub beverage_id: i64,
     pub amount: f64,
+    pub consumed_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,14 +71,19 @@ pub struct Category {
     pub id: i64,
     pub name: String,
     pub display_order: i64,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
+    pub parent_id: Option<i64>,
+    pub archived: bool,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub updated_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateCategoryRequest {
     pub name: String,
     pub display_order: Option<i64>,
+    pub parent_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,8 +93,23 @@ pub struct Beverage {
     pub alcohol_content: Option<f64>,
     pub category_id: i64,
     pub category_name: Option<String>,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
+    pub producer: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub style: Option<String>,
+    pub volume_ml: Option<f64>,
+    pub barcode: Option<String>,
+    pub notes: Option<String>,
+    pub serving_ml: Option<f64>,
+    pub catalog_key: Option<String>,
+    pub archived: bool,
+    pub favorite: bool,
+    pub is_recipe: bool,
+    pub attributes: Vec<BeverageAttributeValue>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default, with = "crate::dates::option_datetime_format")]
+    pub updated_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +117,15 @@ pub struct CreateBeverageRequest {
     pub name: String,
     pub alcohol_content: Option<f64>,
     pub category_id: i64,
+    pub producer: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub style: Option<String>,
+    pub volume_ml: Option<f64>,
+    pub barcode: Option<String>,
+    pub notes: Option<String>,
+    pub serving_ml: Option<f64>,
+    pub attributes: Option<Vec<BeverageAttributeInput>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,3 +135,375 @@ pub struct MonthlyAlcoholIntake {
     pub drinking_days: i64,
 }
 
+
+/// カテゴリー属性の値の型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeType {
+    Text,
+    Number,
+    Integer,
+    Boolean,
+    Select,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryAttribute {
+    pub id: i64,
+    pub category_id: i64,
+    pub name: String,
+    pub value_type: AttributeType,
+    pub unit: Option<String>,
+    pub allowed_values: Vec<String>,
+    pub display_order: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCategoryAttributeRequest {
+    pub name: String,
+    pub value_type: AttributeType,
+    pub unit: Option<String>,
+    pub allowed_values: Option<Vec<String>>,
+    pub display_order: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeverageAttributeValue {
+    pub attribute_id: i64,
+    pub name: String,
+    pub value_type: AttributeType,
+    pub unit: Option<String>,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeverageAttributeInput {
+    pub attribute_id: i64,
+    pub value: serde_json::Value,
+}
+
+/// お酒一覧の属性による絞り込み条件（valueは完全一致、min/maxは数値の範囲）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeverageAttributeFilter {
+    pub attribute_id: i64,
+    pub value: Option<serde_json::Value>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogCategorySummary {
+    pub category_key: String,
+    pub category: String,
+    pub total_count: i64,
+    pub imported_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogInfo {
+    pub version: i64,
+    pub last_imported_version: Option<i64>,
+    pub categories: Vec<CatalogCategorySummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogImportResult {
+    pub version: i64,
+    pub inserted_count: i64,
+    pub linked_count: i64,
+    /// 新しいバージョンのカタログで変わった項目を更新した件数（ユーザーが編集した項目は更新しない）
+    pub updated_count: i64,
+    pub skipped_count: i64,
+    pub created_categories: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeSourceSummary {
+    pub beverage_id: i64,
+    pub name: String,
+    pub post_count: i64,
+}
+
+/// お酒の統合結果（プレビュー時はcommittedがfalseで、データは変更されない）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeBeveragesReport {
+    pub target_id: i64,
+    pub target_name: String,
+    pub sources: Vec<MergeSourceSummary>,
+    pub moved_count: i64,
+    pub combined_count: i64,
+    pub affected_post_ids: Vec<i64>,
+    pub committed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryAlcoholIntake {
+    pub category_id: i64,
+    pub category_name: String,
+    pub parent_id: Option<i64>,
+    pub total_amount: f64,
+    pub total_intake: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeIngredientInput {
+    pub ingredient_id: i64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeIngredient {
+    pub ingredient_id: i64,
+    pub ingredient_name: String,
+    pub amount: f64,
+    pub alcohol_content: Option<f64>,
+}
+
+/// 材料から算出したレシピ（カクテル・割り物）の総量と実効アルコール度数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeverageRecipe {
+    pub beverage_id: i64,
+    pub ingredients: Vec<RecipeIngredient>,
+    pub total_volume: f64,
+    pub alcohol_content: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeverageAlcoholIntake {
+    pub beverage_id: i64,
+    pub beverage_name: String,
+    pub category_id: i64,
+    pub category_name: String,
+    pub total_amount: f64,
+    pub total_intake: f64,
+}
+
+/// クイック入力用のおすすめのお酒（typical_amountは最もよく飲む量）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestedBeverage {
+    pub beverage: Beverage,
+    pub score: f64,
+    pub use_count: i64,
+    pub last_used_date: Option<NaiveDate>,
+    pub typical_amount: Option<f64>,
+}
+
+/// 定番の飲み方を保存した投稿テンプレート
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostTemplate {
+    pub id: i64,
+    pub name: String,
+    pub comment: Option<String>,
+    pub tags: Vec<String>,
+    pub beverages: Vec<BeverageAmount>,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePostTemplateRequest {
+    pub name: String,
+    pub comment: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub beverages: Vec<BeverageAmountInput>,
+}
+
+/// テンプレートから投稿する際の上書き内容
+///
+/// beveragesはお酒ごとにテンプレートの量を上書きし（0以下の場合は除外）、テンプレートにないお酒は追加する。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PostTemplateOverrides {
+    pub comment: Option<String>,
+    pub started_at: Option<String>,
+    pub ended_at: Option<String>,
+    pub beverages: Option<Vec<BeverageAmountInput>>,
+}
+
+/// アプリの設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSettings {
+    pub day_rollover_hour: i64,
+    pub body_weight_kg: Option<f64>,
+    pub body_sex: Option<BodySex>,
+    /// 表示する言語（カテゴリー名・カタログのお酒の名前・エラーメッセージ）
+    pub language: Locale,
+    /// 残すバックアップの数（直近の日ごと・月ごと）
+    pub backup_keep_daily: i64,
+    pub backup_keep_monthly: i64,
+}
+
+/// 血中アルコール濃度の推定に使う性別（体内水分量の係数が異なる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodySex {
+    Male,
+    Female,
+}
+
+/// 設定の更新内容（指定した項目のみ更新する）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateSettingsRequest {
+    pub day_rollover_hour: Option<i64>,
+    pub body_weight_kg: Option<f64>,
+    pub body_sex: Option<BodySex>,
+    pub clear_body_profile: Option<bool>,
+    pub language: Option<Locale>,
+    pub backup_keep_daily: Option<i64>,
+    pub backup_keep_monthly: Option<i64>,
+}
+
+/// アプリのロックの設定（PINが設定されていない場合はidle_timeout_minutesがNone）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockSettings {
+    pub enabled: bool,
+    pub idle_timeout_minutes: Option<i64>,
+}
+
+/// PINの設定・変更（変更する場合はcurrent_pinに現在のPIN。PINを含むためDebugは実装しない）
+#[derive(Clone, Deserialize)]
+pub struct SetAppLockPinRequest {
+    pub current_pin: Option<String>,
+    pub pin: String,
+    pub idle_timeout_minutes: Option<i64>,
+}
+
+/// 飲酒日ごとの飲酒量と純アルコール摂取量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyAlcoholIntake {
+    pub date: NaiveDate,
+    pub post_count: i64,
+    pub total_amount: f64,
+    pub total_intake: f64,
+}
+
+/// 連続飲酒日数と連続休肝日数（純アルコール摂取量が0より大きい日を飲酒日とする）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrinkingStreaks {
+    pub today: NaiveDate,
+    pub current_drinking_days: i64,
+    pub current_sober_days: i64,
+    pub longest_drinking_days: i64,
+    pub longest_sober_days: i64,
+}
+
+/// 記録中のセッションの1杯
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDrink {
+    pub id: i64,
+    pub beverage_id: i64,
+    pub beverage_name: String,
+    pub amount: f64,
+    pub alcohol_content: Option<f64>,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub consumed_at: NaiveDateTime,
+}
+
+/// 記録中の飲酒セッションと現在までの集計
+///
+/// projected_bacは体重・性別が設定されている場合のみ、Widmarkの式で推定した現在の血中アルコール濃度（%）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveSession {
+    pub post_id: i64,
+    pub date: NaiveDate,
+    pub comment: Option<String>,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub started_at: NaiveDateTime,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub now: NaiveDateTime,
+    pub drinks: Vec<SessionDrink>,
+    pub total_amount: f64,
+    pub pace: SessionPace,
+    pub projected_bac: Option<f64>,
+}
+
+/// 翌朝の体調の記録（hangover_severityは0〜5、sleep_quality・moodは1〜5）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellbeingLog {
+    pub id: i64,
+    pub date: NaiveDate,
+    pub hangover_severity: Option<i64>,
+    pub sleep_quality: Option<i64>,
+    pub mood: Option<i64>,
+    pub notes: Option<String>,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveWellbeingLogRequest {
+    #[serde(with = "crate::dates::date_format")]
+    pub date: NaiveDate,
+    pub hangover_severity: Option<i64>,
+    pub sleep_quality: Option<i64>,
+    pub mood: Option<i64>,
+    pub notes: Option<String>,
+}
+
+/// 体調スコアの平均値（相関分析では各スコアと要因の相関係数）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WellbeingScores {
+    pub hangover_severity: Option<f64>,
+    pub sleep_quality: Option<f64>,
+    pub mood: Option<f64>,
+}
+
+/// 前日の飲み方の条件ごとの体調スコアの平均
+///
+/// keyは区分を表す変わらない値（例: intake_20_40）、min・maxは区分の範囲（minは含まず、maxは含む。上限・下限がない場合はNone）。
+/// labelは現在の言語の表示名（カテゴリーの区分はカテゴリー名）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellbeingGroup {
+    pub key: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub label: String,
+    pub category_id: Option<i64>,
+    pub day_count: i64,
+    pub averages: WellbeingScores,
+}
+
+/// 体調の記録と前日の飲酒量・カテゴリー・ペース・ちゃんぽんとの関係
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WellbeingAnalysis {
+    pub log_count: i64,
+    pub intake_correlation: WellbeingScores,
+    pub pace_correlation: WellbeingScores,
+    pub intake_groups: Vec<WellbeingGroup>,
+    pub pace_groups: Vec<WellbeingGroup>,
+    pub category_groups: Vec<WellbeingGroup>,
+    pub mixing_groups: Vec<WellbeingGroup>,
+}
+
+/// バックアップファイルの情報（idはファイル名。schema_versionは読み取れない場合はNone）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub id: String,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub created_at: NaiveDateTime,
+    pub size_bytes: u64,
+    pub schema_version: Option<i64>,
+}
+
+/// データベースの状態（lockedは暗号化されたデータベースのパスフレーズの入力待ち、in_use_byは使用中の別のアプリのコンピューター名）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseStatus {
+    pub locked: bool,
+    pub encrypted: bool,
+    pub in_use_by: Option<String>,
+}
+
+/// プロファイル（プロファイルごとに別のデータベースファイルを使う。activeは現在開いているプロファイル。起動時にデータベースが指定された場合はすべてfalse）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    #[serde(with = "crate::dates::datetime_format")]
+    pub created_at: NaiveDateTime,
+    pub database_path: String,
+    #[serde(default)]
+    pub active: bool,
+}
@@ -0,0 +1,402 @@
+use crate::db::Database;
+use crate::error::AppError;
+use crate::events::{ChangeSet, DataChanged};
+use crate::lock_file::LockFile;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock, RwLockReadGuard, TryLockError};
+use std::time::Duration;
+
+// 読み取り専用の接続の数
+const READER_COUNT: usize = 4;
+
+// 閉じる際に、処理中だったコマンドが接続を手放すのを確認する間隔
+const CLOSE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// データベースの場所と暗号化のパスフレーズ（暗号化しない場合はNone）
+#[derive(Clone)]
+pub struct DbConfig {
+    pub path: PathBuf,
+    pub passphrase: Option<String>,
+}
+
+/// データベース接続のプール
+///
+/// 書き込みは1つの接続で順番に行い、読み取りは複数の読み取り専用の接続で並行して行う（WALモード）。
+/// 処理はブロッキング処理用のスレッドで実行するため、重い集計の実行中も他のコマンドを待たせない。
+/// 暗号化の切り替えなどでは、アプリを再起動せずにデータベースを開き直す（DbPool::reopen）。
+#[derive(Clone, Default)]
+pub struct DbPool {
+    inner: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: RwLock<PoolState>,
+    // 開き直しは同時に1つだけ
+    reopening: Mutex<()>,
+    // 書き込みでデータが変更された際に呼び出す（アプリではTauriのイベントとして送る）
+    listener: OnceLock<Box<dyn Fn(DataChanged) + Send + Sync>>,
+}
+
+#[derive(Default)]
+enum PoolState {
+    #[default]
+    Closed,
+    // 暗号化されたデータベースのパスフレーズの入力待ち
+    Locked(PathBuf),
+    // 他のアプリ（同期フォルダーを共有する別のコンピューターを含む）が使用中（値は使用中のコンピューター名）
+    InUse(PathBuf, String),
+    Open(Arc<Connections>),
+}
+
+struct Connections {
+    config: DbConfig,
+    writer: Mutex<Database>,
+    readers: Vec<Mutex<Database>>,
+    next_reader: AtomicUsize,
+    // 最後に確認した書き込み用の接続のdata_version（外部からの書き込みの検出に使う）
+    data_version: AtomicI64,
+    // 開き直すために閉じた後は、ロックを待っていたコマンドにエラーを返す
+    closed: AtomicBool,
+    // 開いている間は他のアプリが書き込まないようにロックする（接続を閉じてから解放するため最後に置く）
+    lock_file: LockFile,
+}
+
+impl DbPool {
+    /// データベースを開く（起動時。パスフレーズが必要な場合は、エラーを返してパスフレーズの入力を待つ）
+    pub fn open(&self, config: DbConfig) -> Result<(), AppError> {
+        self.inner.reopen(move |_| Ok(config))
+    }
+
+    /// 開いているデータベースを閉じてからfを実行し、fが返した場所・パスフレーズで開き直す
+    ///
+    /// fには閉じたデータベースの書き込み用の接続を渡す（開いていない場合はNone）。
+    /// fが失敗した場合は元のデータベースを開き直す。開き直した後は、すべてのデータの変更を通知する。
+    pub async fn reopen<F>(&self, f: F) -> Result<(), AppError>
+    where
+        F: FnOnce(Option<Database>) -> Result<DbConfig, AppError> + Send + 'static,
+    {
+        let shared = Arc::clone(&self.inner);
+        run_blocking(move || shared.reopen(f)).await
+    }
+
+    /// すべての接続を閉じる（アプリの終了時。ロックファイルを解放する）
+    pub fn close(&self) {
+        self.inner.close();
+    }
+
+    /// 開いているデータベースの場所とパスフレーズ
+    pub fn config(&self) -> Result<DbConfig, AppError> {
+        Ok(self.inner.connections()?.config.clone())
+    }
+
+    /// 開いている、またはパスフレーズの入力を待っているデータベースの場所
+    pub fn path(&self) -> Option<PathBuf> {
+        match &*self.inner.state() {
+            PoolState::Open(connections) => Some(connections.config.path.clone()),
+            PoolState::Locked(path) | PoolState::InUse(path, _) => Some(path.clone()),
+            PoolState::Closed => None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(&*self.inner.state(), PoolState::Open(_))
+    }
+
+    /// 読み取り専用の接続で処理を実行する
+    pub async fn read<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&Database) -> Result<T, AppError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::clone(&self.inner);
+        run_blocking(move || {
+            let connections = shared.connections()?;
+            let db = connections.reader()?;
+            f(&db)
+        })
+        .await
+    }
+
+    /// 書き込み用の接続で処理を実行する（書き込みは同時に1つだけ）
+    ///
+    /// 処理中にdb.changes()へ記録した変更は、処理が成功した場合のみリスナーに送る。
+    pub async fn write<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&Database) -> Result<T, AppError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::clone(&self.inner);
+        run_blocking(move || {
+            let connections = shared.connections()?;
+            let db = connections.writer()?;
+            let result = f(&db);
+            let changes = db.take_changes();
+            drop(db);
+
+            let value = result?;
+            shared.notify(changes);
+            Ok(value)
+        })
+        .await
+    }
+
+    /// 読み取り用の接続も止めた上で、書き込み用の接続で処理を実行する（バックアップからの復元など、データベース全体を置き換える場合）
+    pub async fn write_exclusive<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&mut Database) -> Result<T, AppError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::clone(&self.inner);
+        run_blocking(move || {
+            let connections = shared.connections()?;
+            let mut db = connections.writer()?;
+            let readers = connections.lock_readers()?;
+            let result = f(&mut db);
+            let changes = db.take_changes();
+            drop(readers);
+            drop(db);
+
+            let value = result?;
+            shared.notify(changes);
+            Ok(value)
+        })
+        .await
+    }
+
+    /// データの変更を受け取るリスナーを登録する（登録できるのは1つだけ）
+    pub fn on_change<F>(&self, listener: F)
+    where
+        F: Fn(DataChanged) + Send + Sync + 'static,
+    {
+        if self.inner.listener.set(Box::new(listener)).is_err() {
+            log::warn!("データの変更のリスナーは既に登録されています");
+        }
+    }
+
+    /// 前回の確認以降に、アプリ以外（sqlite3コマンドなど）からの書き込みがあったか
+    ///
+    /// data_versionは他の接続による書き込みでのみ変わる。読み取り用の接続は書き込まないため、
+    /// 書き込み用の接続のdata_versionが変わっていれば外部からの書き込みがあったことになる。
+    pub fn has_external_changes(&self) -> Result<bool, AppError> {
+        let connections = match self.inner.connections() {
+            Ok(connections) => connections,
+            // 開いていない間は確認しない
+            Err(_) => return Ok(false),
+        };
+        let data_version = connections.writer()?.data_version()?;
+        let previous = connections.data_version.swap(data_version, Ordering::Relaxed);
+        Ok(data_version != previous)
+    }
+}
+
+impl Shared {
+    fn state(&self) -> RwLockReadGuard<'_, PoolState> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn connections(&self) -> Result<Arc<Connections>, AppError> {
+        match &*self.state() {
+            PoolState::Open(connections) => Ok(Arc::clone(connections)),
+            PoolState::Locked(_) => Err(AppError::PassphraseRequired),
+            PoolState::InUse(_, host) => Err(AppError::DatabaseInUse(host.clone())),
+            PoolState::Closed => Err(AppError::DatabaseClosed),
+        }
+    }
+
+    fn notify(&self, changes: ChangeSet) {
+        if let Some(listener) = self.listener.get() {
+            for event in changes.into_events() {
+                listener(event);
+            }
+        }
+    }
+
+    fn close(&self) {
+        let _reopening = self.reopening.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = std::mem::take(&mut *self.state.write().unwrap_or_else(PoisonError::into_inner));
+        if let PoolState::Open(connections) = previous {
+            if let Err(error) = Connections::close(connections) {
+                log::warn!("データベースを閉じる際にエラーが発生しました: {}", error);
+            }
+        }
+    }
+
+    fn reopen<F>(&self, f: F) -> Result<(), AppError>
+    where
+        F: FnOnce(Option<Database>) -> Result<DbConfig, AppError>,
+    {
+        let _reopening = self.reopening.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let previous = std::mem::take(&mut *self.state.write().unwrap_or_else(PoisonError::into_inner));
+        // fの実行中（暗号化し直したファイルとの置き換えなど）もロックファイルは解放しない
+        let (database, previous, mut lock_file) = match previous {
+            PoolState::Open(connections) => {
+                let (database, config, lock_file) = Connections::close(connections)?;
+                (Some(database), Some(config), Some(lock_file))
+            }
+            PoolState::Locked(path) | PoolState::InUse(path, _) => {
+                (None, Some(DbConfig { path, passphrase: None }), None)
+            }
+            PoolState::Closed => (None, None, None),
+        };
+
+        let (state, result) = match f(database).map(|config| open_state(config, lock_file.take())) {
+            // パスフレーズの入力待ちになった場合は元に戻さない（別のデータベースに切り替えた場合など）
+            Ok((failed @ (PoolState::Closed | PoolState::InUse(..)), Err(error))) => {
+                (revert(previous, lock_file.take(), failed), Err(error))
+            }
+            Err(error) => (revert(previous, lock_file.take(), PoolState::Closed), Err(error)),
+            Ok(opened) => opened,
+        };
+
+        let opened = matches!(state, PoolState::Open(_));
+        *self.state.write().unwrap_or_else(PoisonError::into_inner) = state;
+        if opened {
+            // 表示中のデータをすべて再取得させる
+            let mut changes = ChangeSet::default();
+            changes.all();
+            self.notify(changes);
+        }
+        result
+    }
+}
+
+/// 開けない場合は、パスフレーズが必要ならパスフレーズの入力待ち、他のアプリが使用中ならその状態、それ以外は閉じた状態にする
+fn open_state(config: DbConfig, lock_file: Option<LockFile>) -> (PoolState, Result<(), AppError>) {
+    let path = config.path.clone();
+    match Connections::open(config, lock_file) {
+        Ok(connections) => (PoolState::Open(Arc::new(connections)), Ok(())),
+        Err(error @ (AppError::PassphraseRequired | AppError::WrongPassphrase)) => {
+            (PoolState::Locked(path), Err(error))
+        }
+        Err(AppError::DatabaseInUse(host)) => (PoolState::InUse(path, host.clone()), Err(AppError::DatabaseInUse(host))),
+        Err(error) => (PoolState::Closed, Err(error)),
+    }
+}
+
+/// 開き直せなかった場合は元のデータベースを開き直す（元のデータベースがない場合は開けなかった状態にする）
+fn revert(previous: Option<DbConfig>, lock_file: Option<LockFile>, failed: PoolState) -> PoolState {
+    match previous.map(|config| open_state(config, lock_file)) {
+        Some((state, Err(error))) if matches!(state, PoolState::Closed) => {
+            log::error!("元のデータベースを開き直せませんでした: {}", error);
+            state
+        }
+        Some((state, _)) => state,
+        None => failed,
+    }
+}
+
+impl Connections {
+    /// ロックファイルを取得し、書き込み用の接続でマイグレーション・初期データの作成を済ませてから、読み取り用の接続を開く
+    ///
+    /// 同じデータベースを開き直す場合は、閉じる前のロックファイルを引き継ぐ。
+    fn open(config: DbConfig, lock_file: Option<LockFile>) -> Result<Self, AppError> {
+        let lock_file = match lock_file {
+            Some(lock_file) if lock_file.is_for(&config.path) => lock_file,
+            _ => LockFile::acquire(&config.path)?,
+        };
+        let writer = Database::new(config.path.clone(), config.passphrase.clone())?;
+        let readers = (0..READER_COUNT)
+            .map(|_| Database::open_reader(&config.path, config.passphrase.as_deref()).map(Mutex::new))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let data_version = writer.data_version()?;
+
+        Ok(Connections {
+            config,
+            writer: Mutex::new(writer),
+            readers,
+            next_reader: AtomicUsize::new(0),
+            data_version: AtomicI64::new(data_version),
+            closed: AtomicBool::new(false),
+            lock_file,
+        })
+    }
+
+    /// 処理中のコマンドが終わるのを待って読み取り用の接続を閉じ、書き込み用の接続とロックファイルを返す
+    fn close(connections: Arc<Self>) -> Result<(Database, DbConfig, LockFile), AppError> {
+        {
+            let _writer = lock(&connections.writer)?;
+            let _readers = connections.lock_readers()?;
+            connections.closed.store(true, Ordering::Relaxed);
+        }
+
+        // ロックを待っていたコマンドはエラーを返して接続を手放す
+        let mut connections = connections;
+        let connections = loop {
+            match Arc::try_unwrap(connections) {
+                Ok(connections) => break connections,
+                Err(shared) => {
+                    connections = shared;
+                    std::thread::sleep(CLOSE_POLL_INTERVAL);
+                }
+            }
+        };
+
+        let writer = connections.writer.into_inner().unwrap_or_else(PoisonError::into_inner);
+        writer.recover()?;
+        Ok((writer, connections.config, connections.lock_file))
+    }
+
+    fn writer(&self) -> Result<MutexGuard<'_, Database>, AppError> {
+        self.check_open(lock(&self.writer)?)
+    }
+
+    fn lock_readers(&self) -> Result<Vec<MutexGuard<'_, Database>>, AppError> {
+        self.readers.iter().map(lock).collect()
+    }
+
+    /// 空いている読み取り用の接続を使い、すべて使用中の場合は順番に割り当てた接続が空くのを待つ
+    fn reader(&self) -> Result<MutexGuard<'_, Database>, AppError> {
+        for reader in &self.readers {
+            match reader.try_lock() {
+                Ok(guard) => return self.check_open(guard),
+                Err(TryLockError::Poisoned(poisoned)) => {
+                    return self.check_open(recover(reader, poisoned.into_inner())?)
+                }
+                Err(TryLockError::WouldBlock) => {}
+            }
+        }
+
+        let index = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.check_open(lock(&self.readers[index])?)
+    }
+
+    fn check_open<'a>(&self, guard: MutexGuard<'a, Database>) -> Result<MutexGuard<'a, Database>, AppError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(AppError::DatabaseClosed);
+        }
+        Ok(guard)
+    }
+}
+
+/// 接続のロックを取得する（処理中のパニックでロックが破損している場合は、接続を復旧して使い続ける）
+fn lock(mutex: &Mutex<Database>) -> Result<MutexGuard<'_, Database>, AppError> {
+    match mutex.lock() {
+        Ok(guard) => Ok(guard),
+        Err(poisoned) => recover(mutex, poisoned.into_inner()),
+    }
+}
+
+fn recover<'a>(
+    mutex: &'a Mutex<Database>,
+    guard: MutexGuard<'a, Database>,
+) -> Result<MutexGuard<'a, Database>, AppError> {
+    log::warn!("処理中のパニックで破損した接続を復旧します");
+    guard.recover()?;
+    mutex.clear_poison();
+    Ok(guard)
+}
+
+/// ブロッキング処理用のスレッドで実行する（処理中のパニックはエラーとして返す）
+async fn run_blocking<T, F>(f: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|error| AppError::Internal(error.to_string()))?
+}
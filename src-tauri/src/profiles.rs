@@ -0,0 +1,328 @@
+use crate::app_lock::AppLock;
+use crate::backup::{self, BackupStore};
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::{ErrorCode, Locale};
+use crate::models::Profile;
+use crate::pool::{DbConfig, DbPool};
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+// プロファイルの一覧を保存するファイル（アプリのデータディレクトリ）
+const REGISTRY_FILE: &str = "profiles.json";
+// 既定のプロファイルはアプリのデータディレクトリのデータベースを使う（プロファイルを追加する前からのデータ）
+const DEFAULT_PROFILE_ID: &str = "default";
+// 追加したプロファイルのデータベースは profiles/<id>/nomi-log.db に作成する
+const PROFILES_DIR: &str = "profiles";
+const DATABASE_FILE: &str = "nomi-log.db";
+// 追加したプロファイルのid（作成日時）
+const ID_FORMAT: &str = "%Y%m%d%H%M%S%3f";
+// 起動時に開くデータベースを指定するコマンドライン引数・環境変数（同期フォルダーのデータベースを使う場合など）
+const DATABASE_ARG: &str = "--database";
+const DATABASE_ENV: &str = "NOMI_LOG_DATABASE";
+
+/// プロファイルの一覧（profiles.json）
+///
+/// プロファイルごとのデータベースは別のファイルのため、一覧はデータベースの外に保存する。
+#[derive(Clone)]
+pub struct Profiles {
+    app_dir: PathBuf,
+    registry: Arc<Mutex<Registry>>,
+    // 起動時に指定されたデータベース（指定された場合は、どのプロファイルも使用中にせず、切り替えもできない）
+    database_override: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Registry {
+    active_profile_id: String,
+    profiles: Vec<ProfileEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ProfileEntry {
+    id: String,
+    name: String,
+    #[serde(with = "crate::dates::datetime_format")]
+    created_at: NaiveDateTime,
+    // 場所を指定した場合のデータベース（指定しない場合はアプリのデータディレクトリに作成する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    database_path: Option<PathBuf>,
+}
+
+impl Profiles {
+    /// プロファイルの一覧を読み込む（初回は既定のプロファイルだけの一覧を作成する）
+    ///
+    /// database_overrideは起動時に指定されたデータベース（parse_database_override）。
+    pub fn load(app_dir: PathBuf, database_override: Option<PathBuf>) -> Result<Self, AppError> {
+        let path = app_dir.join(REGISTRY_FILE);
+        let registry = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            let registry = Registry::initial();
+            save(&path, &registry)?;
+            registry
+        };
+        Ok(Profiles {
+            app_dir,
+            registry: Arc::new(Mutex::new(registry)),
+            database_override,
+        })
+    }
+
+    pub fn list(&self) -> Vec<Profile> {
+        let registry = self.registry();
+        registry
+            .profiles
+            .iter()
+            .map(|entry| self.to_profile(entry, &registry.active_profile_id))
+            .collect()
+    }
+
+    /// 起動時に指定されたデータベース
+    pub fn database_override(&self) -> Option<&Path> {
+        self.database_override.as_deref()
+    }
+
+    /// 起動時に開くプロファイルのデータベースの場所
+    pub fn active_database_path(&self) -> PathBuf {
+        let registry = self.registry();
+        match registry.find(&registry.active_profile_id) {
+            Some(entry) => self.database_path(entry),
+            None => self.app_dir.join(DATABASE_FILE),
+        }
+    }
+
+    /// プロファイルを作成する（database_pathを指定した場合は、そのデータベースを使う。ディレクトリの場合はその中に作成する）
+    pub fn create(&self, name: &str, database_path: Option<PathBuf>) -> Result<Profile, AppError> {
+        let name = name.trim();
+        let mut registry = self.registry();
+        registry.ensure_unique_name(name, None)?;
+
+        let mut id = Local::now().naive_local().format(ID_FORMAT).to_string();
+        while registry.find(&id).is_some() {
+            id.push('0');
+        }
+        let database_path = database_path.map(|path| resolve_database_path(&path));
+        match &database_path {
+            Some(path) => {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+            }
+            None => fs::create_dir_all(self.profile_dir(&id))?,
+        }
+
+        let entry = ProfileEntry {
+            id,
+            name: name.to_string(),
+            created_at: Local::now().naive_local(),
+            database_path,
+        };
+        registry.profiles.push(entry.clone());
+        self.save(&registry)?;
+        log::info!("プロファイル「{}」を作成しました", entry.name);
+        Ok(self.to_profile(&entry, &registry.active_profile_id))
+    }
+
+    pub fn rename(&self, id: &str, name: &str) -> Result<Profile, AppError> {
+        let name = name.trim();
+        let mut registry = self.registry();
+        registry.find(id).ok_or_else(not_found)?;
+        registry.ensure_unique_name(name, Some(id))?;
+
+        let active_profile_id = registry.active_profile_id.clone();
+        let entry = registry
+            .profiles
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(not_found)?;
+        entry.name = name.to_string();
+        let profile = self.to_profile(entry, &active_profile_id);
+        self.save(&registry)?;
+        Ok(profile)
+    }
+
+    /// プロファイルとそのデータベース・バックアップを削除する（使用中のプロファイルと既定のプロファイルは削除できない）
+    ///
+    /// 場所を指定したデータベースは、他のコンピューターで使っている場合もあるため一覧から削除するだけにする。
+    pub fn delete(&self, id: &str) -> Result<(), AppError> {
+        let mut registry = self.registry();
+        let entry = registry.find(id).ok_or_else(not_found)?.clone();
+        if id == registry.active_profile_id || id == DEFAULT_PROFILE_ID {
+            return Err(ErrorDetail::new(ErrorCode::ProfileNotDeletable)
+                .param("name", entry.name)
+                .into());
+        }
+
+        // 一覧から先に削除し、ファイルを削除できなかった場合も一覧には残さない
+        registry.profiles.retain(|entry| entry.id != id);
+        self.save(&registry)?;
+        let dir = self.profile_dir(id);
+        if entry.database_path.is_none() && dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        log::info!("プロファイル「{}」を削除しました", entry.name);
+        Ok(())
+    }
+
+    /// プロファイルを切り替える（アプリを再起動せずに、切り替え先のデータベースを開き直す）
+    ///
+    /// 切り替え先のデータベースが暗号化されている場合は、パスフレーズの入力待ちになる（unlock_database）。
+    /// PINが設定されている場合は、起動時と同じくロックする。
+    pub async fn switch(
+        &self,
+        pool: &DbPool,
+        backups: &BackupStore,
+        app_lock: &AppLock,
+        id: &str,
+    ) -> Result<(), AppError> {
+        if let Some(path) = &self.database_override {
+            return Err(ErrorDetail::new(ErrorCode::ProfileSwitchOverridden)
+                .param("path", path.to_string_lossy().into_owned())
+                .into());
+        }
+        let entry = self.registry().find(id).ok_or_else(not_found)?.clone();
+        let path = self.database_path(&entry);
+        if pool.path().as_ref() == Some(&path) {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let config = DbConfig { path: path.clone(), passphrase: None };
+        match pool.reopen(move |_| Ok(config)).await {
+            Ok(()) | Err(AppError::PassphraseRequired) => {}
+            Err(error) => return Err(error),
+        }
+        backups.set_dir(backup::backup_dir(&self.app_dir, &path));
+        // 開けていない場合は、パスフレーズを入力して開いた際にロックする
+        app_lock.reset();
+        if pool.is_open() {
+            let app_lock = app_lock.clone();
+            pool.read(move |db| app_lock.load(db.conn(), true)).await?;
+        }
+
+        let mut registry = self.registry();
+        registry.active_profile_id = entry.id;
+        self.save(&registry)?;
+        log::info!("プロファイル「{}」に切り替えました", entry.name);
+        Ok(())
+    }
+
+    fn database_path(&self, entry: &ProfileEntry) -> PathBuf {
+        if let Some(path) = &entry.database_path {
+            path.clone()
+        } else if entry.id == DEFAULT_PROFILE_ID {
+            self.app_dir.join(DATABASE_FILE)
+        } else {
+            self.profile_dir(&entry.id).join(DATABASE_FILE)
+        }
+    }
+
+    fn to_profile(&self, entry: &ProfileEntry, active_profile_id: &str) -> Profile {
+        Profile {
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            created_at: entry.created_at,
+            database_path: self.database_path(entry).to_string_lossy().into_owned(),
+            active: self.database_override.is_none() && entry.id == active_profile_id,
+        }
+    }
+
+    fn profile_dir(&self, id: &str) -> PathBuf {
+        self.app_dir.join(PROFILES_DIR).join(id)
+    }
+
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn save(&self, registry: &Registry) -> Result<(), AppError> {
+        save(&self.app_dir.join(REGISTRY_FILE), registry)
+    }
+}
+
+impl Registry {
+    fn initial() -> Self {
+        let name = match Locale::from_system().unwrap_or_default() {
+            Locale::Ja => "メイン",
+            Locale::En => "Main",
+        };
+        Registry {
+            active_profile_id: DEFAULT_PROFILE_ID.to_string(),
+            profiles: vec![ProfileEntry {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: name.to_string(),
+                created_at: Local::now().naive_local(),
+                database_path: None,
+            }],
+        }
+    }
+
+    fn find(&self, id: &str) -> Option<&ProfileEntry> {
+        self.profiles.iter().find(|entry| entry.id == id)
+    }
+
+    fn ensure_unique_name(&self, name: &str, exclude_id: Option<&str>) -> Result<(), AppError> {
+        let exists = self
+            .profiles
+            .iter()
+            .any(|entry| entry.name == name && Some(entry.id.as_str()) != exclude_id);
+        if exists {
+            return Err(ErrorDetail::new(ErrorCode::ProfileNameExists)
+                .param("name", name)
+                .into());
+        }
+        Ok(())
+    }
+}
+
+/// コマンドライン引数（--database <パス>）・環境変数（NOMI_LOG_DATABASE）で指定された、起動時に開くデータベース
+///
+/// 指定された場合はプロファイルの一覧に関係なくそのデータベースを開く（一覧や前回使っていたプロファイルは変更しない）。
+pub fn parse_database_override() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        if arg == DATABASE_ARG {
+            path = args.next();
+        } else if let Some(value) = arg.strip_prefix(DATABASE_ARG).and_then(|rest| rest.strip_prefix('=')) {
+            path = Some(value.to_string());
+        }
+    }
+    let path = path
+        .or_else(|| std::env::var(DATABASE_ENV).ok())
+        .filter(|path| !path.trim().is_empty())?;
+
+    let path = PathBuf::from(path);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir().map(|dir| dir.join(&path)).unwrap_or(path)
+    };
+    Some(resolve_database_path(&path))
+}
+
+/// ディレクトリが指定された場合は、その中のデータベースファイルにする
+fn resolve_database_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join(DATABASE_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn not_found() -> AppError {
+    ErrorDetail::new(ErrorCode::ProfileNotFound).into()
+}
+
+/// 一時ファイルに書き込んでから名前を変更する（途中で終了しても一覧が壊れない）
+fn save(path: &Path, registry: &Registry) -> Result<(), AppError> {
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(registry)?)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::i18n::{self, NameTable};
+use crate::messages::ErrorCode;
+use crate::models::*;
+use crate::validation;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+// レシピの入れ子の最大の深さ（循環参照の防止を兼ねる）
+const MAX_RECIPE_DEPTH: usize = 8;
+
+fn load_ingredients(conn: &Connection, beverage_id: i64) -> Result<Vec<RecipeIngredient>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT
+            bi.ingredient_id,
+            {},
+            bi.amount,
+            b.alcohol_content
+        FROM beverage_ingredients bi
+        INNER JOIN beverages b ON bi.ingredient_id = b.id
+        WHERE bi.beverage_id = ?1
+        ORDER BY bi.display_order, bi.id",
+        i18n::localized_name_sql(NameTable::Beverages, "b")
+    ))?;
+
+    let ingredients = stmt.query_map(params![beverage_id], |row| {
+        Ok(RecipeIngredient {
+            ingredient_id: row.get(0)?,
+            ingredient_name: row.get(1)?,
+            amount: row.get(2)?,
+            alcohol_content: row.get(3)?,
+        })
+    })?;
+
+    Ok(ingredients.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// 材料の量と度数から総量と実効アルコール度数を算出する（度数未設定の材料は0%として扱う）
+fn build_recipe(beverage_id: i64, ingredients: Vec<RecipeIngredient>) -> BeverageRecipe {
+    let total_volume: f64 = ingredients.iter().map(|i| i.amount).sum();
+    let alcohol_volume: f64 = ingredients
+        .iter()
+        .map(|i| i.amount * i.alcohol_content.unwrap_or(0.0))
+        .sum();
+
+    BeverageRecipe {
+        beverage_id,
+        ingredients,
+        total_volume,
+        alcohol_content: if total_volume > 0.0 { alcohol_volume / total_volume } else { 0.0 },
+    }
+}
+
+pub fn get_recipe(conn: &Connection, beverage_id: i64) -> Result<Option<BeverageRecipe>, AppError> {
+    let ingredients = load_ingredients(conn, beverage_id)?;
+    if ingredients.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(build_recipe(beverage_id, ingredients)))
+}
+
+/// ingredient_idのレシピを（入れ子も含めて）たどった先にbeverage_idが含まれるか
+pub fn contains_beverage(
+    conn: &Connection,
+    ingredient_id: i64,
+    beverage_id: i64,
+    depth: usize,
+) -> Result<bool, AppError> {
+    if ingredient_id == beverage_id {
+        return Ok(true);
+    }
+    if depth >= MAX_RECIPE_DEPTH {
+        return Err(ErrorDetail::new(ErrorCode::RecipeTooDeep).into());
+    }
+
+    for ingredient in load_ingredients(conn, ingredient_id)? {
+        if contains_beverage(conn, ingredient.ingredient_id, beverage_id, depth + 1)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// お酒のレシピを置き換える（空の場合はレシピを解除する。材料の量・重複・存在はvalidationで検証済みとする）
+pub fn set_recipe(
+    conn: &Connection,
+    beverage_id: i64,
+    inputs: &[RecipeIngredientInput],
+) -> Result<Option<BeverageRecipe>, AppError> {
+    for (index, input) in inputs.iter().enumerate() {
+        if contains_beverage(conn, input.ingredient_id, beverage_id, 0)? {
+            return Err(validation::field_error(
+                &format!("ingredients[{}].ingredient_id", index),
+                ErrorDetail::new(ErrorCode::RecipeSelfReference),
+            ));
+        }
+    }
+
+    conn.execute(
+        "DELETE FROM beverage_ingredients WHERE beverage_id = ?1",
+        params![beverage_id],
+    )?;
+
+    for (index, input) in inputs.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO beverage_ingredients (beverage_id, ingredient_id, amount, display_order)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![beverage_id, input.ingredient_id, input.amount, index as i64 + 1],
+        )?;
+    }
+
+    refresh(conn, beverage_id)?;
+    get_recipe(conn, beverage_id)
+}
+
+/// レシピから算出した度数・総量をお酒に反映し、このお酒を材料に使うレシピも再計算する
+pub fn refresh(conn: &Connection, beverage_id: i64) -> Result<(), AppError> {
+    refresh_recursive(conn, beverage_id, 0)
+}
+
+fn refresh_recursive(conn: &Connection, beverage_id: i64, depth: usize) -> Result<(), AppError> {
+    if depth > MAX_RECIPE_DEPTH {
+        return Ok(());
+    }
+
+    if let Some(recipe) = get_recipe(conn, beverage_id)? {
+        conn.execute(
+            "UPDATE beverages SET alcohol_content = ?1, serving_ml = ?2 WHERE id = ?3",
+            params![recipe.alcohol_content, recipe.total_volume, beverage_id],
+        )?;
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT beverage_id FROM beverage_ingredients WHERE ingredient_id = ?1",
+    )?;
+    let dependents = stmt
+        .query_map(params![beverage_id], |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for dependent in dependents {
+        refresh_recursive(conn, dependent, depth + 1)?;
+    }
+
+    Ok(())
+}
+
+/// 飲んだ量をレシピの材料（ベースのお酒）ごとの量に分解する
+pub struct RecipeExpander {
+    ingredients: HashMap<i64, Vec<(i64, f64)>>,
+}
+
+impl RecipeExpander {
+    pub fn load(conn: &Connection) -> Result<Self, AppError> {
+        let mut stmt = conn.prepare(
+            "SELECT beverage_id, ingredient_id, amount FROM beverage_ingredients ORDER BY display_order, id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?))
+        })?;
+
+        let mut ingredients: HashMap<i64, Vec<(i64, f64)>> = HashMap::new();
+        for row in rows {
+            let (beverage_id, ingredient_id, amount) = row?;
+            ingredients.entry(beverage_id).or_default().push((ingredient_id, amount));
+        }
+
+        Ok(RecipeExpander { ingredients })
+    }
+
+    pub fn expand(&self, beverage_id: i64, amount: f64) -> Vec<(i64, f64)> {
+        let mut result = Vec::new();
+        self.expand_into(beverage_id, amount, 0, &mut result);
+        result
+    }
+
+    fn expand_into(&self, beverage_id: i64, amount: f64, depth: usize, result: &mut Vec<(i64, f64)>) {
+        let ingredients = match self.ingredients.get(&beverage_id) {
+            Some(ingredients) if depth < MAX_RECIPE_DEPTH => ingredients,
+            _ => {
+                result.push((beverage_id, amount));
+                return;
+            }
+        };
+
+        let total_volume: f64 = ingredients.iter().map(|(_, amount)| amount).sum();
+        if total_volume <= 0.0 {
+            result.push((beverage_id, amount));
+            return;
+        }
+
+        for (ingredient_id, ingredient_amount) in ingredients {
+            self.expand_into(*ingredient_id, amount * ingredient_amount / total_volume, depth + 1, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::stats::pure_alcohol;
+
+    fn beverage(conn: &Connection, name: &str, alcohol_content: f64) -> i64 {
+        conn.execute(
+            "INSERT INTO beverages (name, alcohol_content, category_id)
+            VALUES (?1, ?2, (SELECT MIN(id) FROM categories))",
+            params![name, alcohol_content],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn recipe(conn: &Connection, beverage_id: i64, ingredients: &[(i64, f64)]) -> Result<Option<BeverageRecipe>, AppError> {
+        let inputs: Vec<RecipeIngredientInput> = ingredients
+            .iter()
+            .map(|&(ingredient_id, amount)| RecipeIngredientInput { ingredient_id, amount })
+            .collect();
+        set_recipe(conn, beverage_id, &inputs)
+    }
+
+    fn validation_error(result: Result<Option<BeverageRecipe>, AppError>) -> ErrorDetail {
+        match result {
+            Err(AppError::Validation(mut errors)) => errors.remove(0),
+            other => panic!("検証エラーになりませんでした: {:?}", other.ok()),
+        }
+    }
+
+    #[test]
+    fn self_reference_is_rejected() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let whisky = beverage(conn, "ウイスキー", 40.0);
+        let highball = beverage(conn, "ハイボール", 0.0);
+        let soda = beverage(conn, "ソーダ", 0.0);
+
+        let error = validation_error(recipe(conn, highball, &[(soda, 90.0), (highball, 30.0)]));
+        assert_eq!(error.code, ErrorCode::RecipeSelfReference);
+        assert_eq!(error.field.as_deref(), Some("ingredients[1].ingredient_id"));
+
+        // ハイボールの材料のウイスキーに、ハイボールを含めることはできない
+        recipe(conn, highball, &[(whisky, 30.0), (soda, 90.0)]).unwrap();
+        let error = validation_error(recipe(conn, whisky, &[(highball, 120.0)]));
+        assert_eq!(error.code, ErrorCode::RecipeSelfReference);
+        assert!(get_recipe(conn, whisky).unwrap().is_none());
+    }
+
+    #[test]
+    fn nesting_deeper_than_the_limit_is_rejected() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        // chain[i]はchain[i - 1]だけを材料にする（chain[0]は材料なし）
+        let mut chain = vec![beverage(conn, "ベース", 40.0)];
+        for depth in 1..=MAX_RECIPE_DEPTH {
+            let id = beverage(conn, &format!("入れ子{}", depth), 0.0);
+            recipe(conn, id, &[(chain[depth - 1], 100.0)]).unwrap();
+            chain.push(id);
+        }
+
+        let top = beverage(conn, "最上位", 0.0);
+        match recipe(conn, top, &[(chain[MAX_RECIPE_DEPTH], 100.0)]) {
+            Err(error) => assert_eq!(error.detail().code, ErrorCode::RecipeTooDeep),
+            Ok(_) => panic!("入れ子の深さの上限を超えたレシピを登録できました"),
+        }
+        recipe(conn, top, &[(chain[MAX_RECIPE_DEPTH - 1], 100.0)]).unwrap();
+    }
+
+    #[test]
+    fn nested_recipes_attribute_pure_alcohol_to_base_beverages() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let whisky = beverage(conn, "ウイスキー", 40.0);
+        let soda = beverage(conn, "ソーダ", 0.0);
+        let highball = beverage(conn, "ハイボール", 0.0);
+        let pitcher = beverage(conn, "濃いめハイボールのピッチャー", 0.0);
+        recipe(conn, highball, &[(whisky, 30.0), (soda, 90.0)]).unwrap();
+        let pitcher_recipe = recipe(conn, pitcher, &[(highball, 240.0), (whisky, 60.0)]).unwrap().unwrap();
+        // ハイボールは10%、ピッチャーは(240 * 10 + 60 * 40) / 300 = 16%
+        assert!((pitcher_recipe.alcohol_content - 16.0).abs() < 1e-9);
+
+        let expander = RecipeExpander::load(conn).unwrap();
+        let parts = expander.expand(pitcher, 300.0);
+        assert_eq!(parts, vec![(whisky, 60.0), (soda, 180.0), (whisky, 60.0)]);
+
+        // 材料ごとの純アルコール量の合計は、算出した度数で計算した量と一致する
+        let alcohol_contents = HashMap::from([(whisky, 40.0), (soda, 0.0)]);
+        let total: f64 = parts
+            .iter()
+            .map(|(id, amount)| pure_alcohol(*amount, alcohol_contents.get(id).copied()))
+            .sum();
+        assert!((total - 38.4).abs() < 1e-9);
+        assert!((total - pure_alcohol(300.0, Some(pitcher_recipe.alcohol_content))).abs() < 1e-9);
+
+        // 材料のないお酒はそのまま
+        assert_eq!(expander.expand(whisky, 30.0), vec![(whisky, 30.0)]);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::dates;
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::{ErrorCode, Label};
+use crate::models::*;
+use crate::settings;
+use crate::stats;
+use crate::validation;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Timelike};
+use rusqlite::Connection;
+
+/// 投稿の飲酒時間（分）を表すSQL式（postsの別名はp）
+///
+/// 開始・終了時刻が未設定の場合は、最初と最後にお酒を飲んだ時刻で補う。
+pub const DURATION_MINUTES_SQL: &str = "ROUND((
+    julianday(COALESCE(p.ended_at, (SELECT MAX(consumed_at) FROM post_beverages WHERE post_id = p.id)))
+    - julianday(COALESCE(p.started_at, (SELECT MIN(consumed_at) FROM post_beverages WHERE post_id = p.id)))
+) * 86400) / 60.0";
+
+/// 検証・正規化済みの投稿内容
+pub struct NormalizedPost {
+    pub date: NaiveDate,
+    pub comment: Option<String>,
+    pub started_at: Option<NaiveDateTime>,
+    pub ended_at: Option<NaiveDateTime>,
+    pub beverages: Vec<NormalizedBeverageAmount>,
+}
+
+pub struct NormalizedBeverageAmount {
+    pub beverage_id: i64,
+    pub amount: f64,
+    pub consumed_at: Option<NaiveDateTime>,
+}
+
+/// 日時の入力を解釈する
+///
+/// 時刻のみの場合は投稿の日付（飲酒日）の時刻として扱い、日付の切り替え時刻より前の場合や
+/// baseより前になる場合は日付をまたいだものとして翌日にする。
+fn normalize_datetime(
+    date: NaiveDate,
+    value: Option<String>,
+    base: Option<NaiveDateTime>,
+    rollover_hour: i64,
+    label: Label,
+) -> Result<Option<NaiveDateTime>, AppError> {
+    let Some(value) = value.filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    let Some(time) = dates::parse_time(&value) else {
+        return dates::parse_datetime(&value, label).map(Some);
+    };
+
+    let mut datetime = date.and_time(time);
+    if i64::from(datetime.hour()) < rollover_hour || base.is_some_and(|base| datetime < base) {
+        datetime += Duration::days(1);
+    }
+    Ok(Some(datetime))
+}
+
+/// 投稿の日付が開始時刻の飲酒日（切り替え時刻より前に始めた飲酒は前日）と一致するか検証する
+///
+/// 時刻のみの開始時刻は投稿の日付から解釈するため常に一致し、日時で指定した場合だけ食い違いうる。
+pub fn check_drinking_day(date: NaiveDate, started_at: NaiveDateTime, rollover_hour: i64) -> Result<(), AppError> {
+    let drinking_day = settings::drinking_day(started_at, rollover_hour);
+    if date != drinking_day {
+        return Err(validation::field_error(
+            "date",
+            ErrorDetail::new(ErrorCode::DateNotDrinkingDay)
+                .param("started_at", started_at.format(dates::DATETIME_FORMAT).to_string())
+                .param("drinking_day", drinking_day.format(dates::DATE_FORMAT).to_string())
+                .param("rollover_hour", rollover_hour),
+        ));
+    }
+    Ok(())
+}
+
+/// 投稿の日付・開始・終了時刻とお酒ごとの飲んだ時刻を検証し、正規化する
+pub fn normalize_post_request(
+    conn: &Connection,
+    request: CreatePostRequest,
+) -> Result<NormalizedPost, AppError> {
+    let rollover_hour = settings::day_rollover_hour(conn)?;
+    let started_at =
+        normalize_datetime(request.date, request.started_at, None, rollover_hour, Label::StartedAt)?;
+    let ended_at =
+        normalize_datetime(request.date, request.ended_at, started_at, rollover_hour, Label::EndedAt)?;
+
+    if let (Some(started_at), Some(ended_at)) = (started_at, ended_at) {
+        if ended_at < started_at {
+            return Err(validation::field_error("ended_at", ErrorDetail::new(ErrorCode::EndBeforeStart)));
+        }
+    }
+
+    let mut beverages = Vec::with_capacity(request.beverages.len());
+    for beverage in request.beverages {
+        beverages.push(NormalizedBeverageAmount {
+            beverage_id: beverage.beverage_id,
+            amount: beverage.amount,
+            consumed_at: normalize_datetime(
+                request.date,
+                beverage.consumed_at,
+                started_at,
+                rollover_hour,
+                Label::ConsumedAt,
+            )?,
+        });
+    }
+
+    if let Some(started_at) = started_at {
+        check_drinking_day(request.date, started_at, rollover_hour)?;
+    }
+
+    Ok(NormalizedPost {
+        date: request.date,
+        comment: request.comment,
+        started_at,
+        ended_at,
+        beverages,
+    })
+}
+
+/// 飲酒時間と純アルコール量から1時間あたりのペースを算出する
+pub fn pace(duration_minutes: Option<f64>, beverages: &[BeverageAmount]) -> SessionPace {
+    let total_pure_alcohol: f64 = beverages
+        .iter()
+        .map(|beverage| stats::pure_alcohol(beverage.amount, beverage.alcohol_content))
+        .sum();
+    let duration_minutes = duration_minutes.filter(|minutes| *minutes >= 0.0);
+
+    SessionPace {
+        duration_minutes,
+        total_pure_alcohol,
+        grams_per_hour: duration_minutes
+            .filter(|minutes| *minutes > 0.0)
+            .map(|minutes| total_pure_alcohol / (minutes / 60.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn date_must_be_drinking_day_of_start() {
+        // 切り替え時刻より前に始めた飲酒は前日
+        check_drinking_day(date("2024-05-31"), at("2024-06-01 03:00"), 5).unwrap();
+        check_drinking_day(date("2024-06-01"), at("2024-06-01 05:00"), 5).unwrap();
+        check_drinking_day(date("2024-06-01"), at("2024-06-01 03:00"), 0).unwrap();
+
+        match check_drinking_day(date("2024-06-01"), at("2024-06-01 03:00"), 5) {
+            Err(AppError::Validation(errors)) => {
+                assert_eq!(errors[0].code, ErrorCode::DateNotDrinkingDay);
+                assert_eq!(errors[0].field.as_deref(), Some("date"));
+                assert_eq!(errors[0].params["drinking_day"], "2024-05-31");
+            }
+            other => panic!("検証エラーになりませんでした: {:?}", other.ok()),
+        }
+    }
+}
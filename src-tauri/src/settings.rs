@@ -0,0 +1,145 @@
+use crate::error::AppError;
+use crate::messages::Locale;
+use crate::models::*;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension};
+
+// 日付の切り替え時刻（この時刻より前の飲酒は前日の飲酒として扱う）
+const DAY_ROLLOVER_HOUR: &str = "day_rollover_hour";
+pub const MAX_DAY_ROLLOVER_HOUR: i64 = 12;
+// 血中アルコール濃度の推定に使う体重（kg）と性別
+const BODY_WEIGHT_KG: &str = "body_weight_kg";
+const BODY_SEX: &str = "body_sex";
+// 表示する言語（初回起動時にOSの言語で設定する。未設定の場合は日本語）
+pub const LANGUAGE: &str = "language";
+// 残すバックアップの数（直近の日ごと・月ごとに最新の1つずつ）
+const BACKUP_KEEP_DAILY: &str = "backup_keep_daily";
+const BACKUP_KEEP_MONTHLY: &str = "backup_keep_monthly";
+pub const DEFAULT_BACKUP_KEEP_DAILY: i64 = 7;
+pub const DEFAULT_BACKUP_KEEP_MONTHLY: i64 = 12;
+pub const MAX_BACKUP_KEEP_DAILY: i64 = 365;
+pub const MAX_BACKUP_KEEP_MONTHLY: i64 = 120;
+
+pub fn get_value(conn: &Connection, key: &str) -> Result<Option<String>, AppError> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+pub fn set_value(conn: &Connection, key: &str, value: &str) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = datetime('now', 'localtime')",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// 日付の切り替え時刻（未設定の場合は0時）
+pub fn day_rollover_hour(conn: &Connection) -> Result<i64, AppError> {
+    Ok(get_value(conn, DAY_ROLLOVER_HOUR)?
+        .and_then(|value| value.parse().ok())
+        .filter(|hour| (0..=MAX_DAY_ROLLOVER_HOUR).contains(hour))
+        .unwrap_or(0))
+}
+
+/// 日ごとに残すバックアップの数（未設定の場合は7日分）
+pub fn backup_keep_daily(conn: &Connection) -> Result<i64, AppError> {
+    Ok(get_value(conn, BACKUP_KEEP_DAILY)?
+        .and_then(|value| value.parse().ok())
+        .filter(|count| (1..=MAX_BACKUP_KEEP_DAILY).contains(count))
+        .unwrap_or(DEFAULT_BACKUP_KEEP_DAILY))
+}
+
+/// 月ごとに残すバックアップの数（未設定の場合は12か月分）
+pub fn backup_keep_monthly(conn: &Connection) -> Result<i64, AppError> {
+    Ok(get_value(conn, BACKUP_KEEP_MONTHLY)?
+        .and_then(|value| value.parse().ok())
+        .filter(|count| (1..=MAX_BACKUP_KEEP_MONTHLY).contains(count))
+        .unwrap_or(DEFAULT_BACKUP_KEEP_MONTHLY))
+}
+
+/// 表示する言語
+pub fn language(conn: &Connection) -> Result<Locale, AppError> {
+    Ok(get_value(conn, LANGUAGE)?
+        .and_then(|value| Locale::from_tag(&value))
+        .unwrap_or_default())
+}
+
+pub fn delete_value(conn: &Connection, key: &str) -> Result<(), AppError> {
+    conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+    Ok(())
+}
+
+pub fn get_settings(conn: &Connection) -> Result<AppSettings, AppError> {
+    Ok(AppSettings {
+        day_rollover_hour: day_rollover_hour(conn)?,
+        body_weight_kg: get_value(conn, BODY_WEIGHT_KG)?
+            .and_then(|value| value.parse().ok())
+            .filter(|weight: &f64| *weight > 0.0),
+        body_sex: match get_value(conn, BODY_SEX)?.as_deref() {
+            Some("male") => Some(BodySex::Male),
+            Some("female") => Some(BodySex::Female),
+            _ => None,
+        },
+        language: language(conn)?,
+        backup_keep_daily: backup_keep_daily(conn)?,
+        backup_keep_monthly: backup_keep_monthly(conn)?,
+    })
+}
+
+/// 設定を更新する（値の範囲はvalidationで検証済みとする）
+///
+/// 表示する言語の切り替え（Locale::set_current）は、コミットした後に呼び出し側で行う。
+pub fn update_settings(conn: &Connection, request: UpdateSettingsRequest) -> Result<AppSettings, AppError> {
+    if let Some(hour) = request.day_rollover_hour {
+        set_value(conn, DAY_ROLLOVER_HOUR, &hour.to_string())?;
+    }
+
+    if request.clear_body_profile == Some(true) {
+        delete_value(conn, BODY_WEIGHT_KG)?;
+        delete_value(conn, BODY_SEX)?;
+    }
+    if let Some(weight) = request.body_weight_kg {
+        set_value(conn, BODY_WEIGHT_KG, &weight.to_string())?;
+    }
+    if let Some(sex) = request.body_sex {
+        let value = match sex {
+            BodySex::Male => "male",
+            BodySex::Female => "female",
+        };
+        set_value(conn, BODY_SEX, value)?;
+    }
+    if let Some(count) = request.backup_keep_daily {
+        set_value(conn, BACKUP_KEEP_DAILY, &count.to_string())?;
+    }
+    if let Some(count) = request.backup_keep_monthly {
+        set_value(conn, BACKUP_KEEP_MONTHLY, &count.to_string())?;
+    }
+    if let Some(locale) = request.language {
+        set_value(conn, LANGUAGE, locale.as_str())?;
+    }
+
+    get_settings(conn)
+}
+
+/// 開始時刻の飲酒日（切り替え時刻より前に始めた飲酒は前日）
+///
+/// 開始時刻がある投稿は、保存する際に投稿日がこの日付と一致するか検証する（集計・一覧は投稿日を飲酒日として扱う）。
+pub fn drinking_day(started_at: NaiveDateTime, rollover_hour: i64) -> NaiveDate {
+    (started_at - Duration::hours(rollover_hour)).date()
+}
+
+/// 切り替え時刻を考慮した今日の飲酒日
+pub fn drinking_today(conn: &Connection) -> Result<NaiveDate, AppError> {
+    let rollover_hour = day_rollover_hour(conn)?;
+    Ok(conn.query_row(
+        "SELECT date('now', 'localtime', ?1)",
+        params![format!("-{} hours", rollover_hour)],
+        |row| row.get(0),
+    )?)
+}
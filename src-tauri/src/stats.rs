@@ -0,0 +1,400 @@
+use crate::error::AppError;
+use crate::i18n::{self, NameTable};
+use crate::models::*;
+use crate::recipes::RecipeExpander;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+/// 純アルコール量を計算する: 飲んだ容量(ml) * アルコール度数(%) / 100 * 0.8
+pub fn pure_alcohol(amount: f64, alcohol_content: Option<f64>) -> f64 {
+    match alcohol_content {
+        Some(alc_content) if alc_content > 0.0 => amount * (alc_content / 100.0) * 0.8,
+        _ => 0.0,
+    }
+}
+
+struct CategoryNode {
+    name: String,
+    parent_id: Option<i64>,
+    display_order: i64,
+}
+
+/// 親をたどって最上位のカテゴリーIDを返す（循環していても停止する）
+fn root_category(nodes: &HashMap<i64, CategoryNode>, category_id: i64) -> i64 {
+    let mut current = category_id;
+    for _ in 0..nodes.len() {
+        match nodes.get(&current).and_then(|node| node.parent_id) {
+            Some(parent_id) if nodes.contains_key(&parent_id) => current = parent_id,
+            _ => break,
+        }
+    }
+    current
+}
+
+/// カテゴリーIDから最上位のカテゴリーのIDと名称を引く対応表を返す
+pub fn root_categories(conn: &Connection) -> Result<HashMap<i64, (i64, String)>, AppError> {
+    let nodes = load_category_nodes(conn)?;
+    Ok(nodes
+        .keys()
+        .map(|&category_id| {
+            let root_id = root_category(&nodes, category_id);
+            let name = nodes.get(&root_id).map(|node| node.name.clone()).unwrap_or_default();
+            (category_id, (root_id, name))
+        })
+        .collect())
+}
+
+struct BeverageNode {
+    name: String,
+    category_id: i64,
+    alcohol_content: Option<f64>,
+}
+
+fn load_category_nodes(conn: &Connection) -> Result<HashMap<i64, CategoryNode>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, {}, parent_id, display_order FROM categories c",
+        i18n::localized_name_sql(NameTable::Categories, "c")
+    ))?;
+    let nodes = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                CategoryNode {
+                    name: row.get(1)?,
+                    parent_id: row.get(2)?,
+                    display_order: row.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(nodes)
+}
+
+fn load_beverage_nodes(conn: &Connection) -> Result<HashMap<i64, BeverageNode>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, {}, category_id, alcohol_content FROM beverages b",
+        i18n::localized_name_sql(NameTable::Beverages, "b")
+    ))?;
+    let nodes = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                BeverageNode {
+                    name: row.get(1)?,
+                    category_id: row.get(2)?,
+                    alcohol_content: row.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(nodes)
+}
+
+/// 期間内（飲酒日で判定）に飲んだお酒と量の一覧を返す
+///
+/// attribute_recipesがtrueの場合、レシピで定義されたお酒は材料（ベースのお酒）ごとの量に分解する。
+fn consumed_amounts(
+    conn: &Connection,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    attribute_recipes: bool,
+) -> Result<Vec<(i64, f64)>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT
+            pb.beverage_id,
+            pb.amount
+        FROM post_beverages pb
+        INNER JOIN posts p ON pb.post_id = p.id
+        WHERE p.active = 0 AND p.date >= ?1 AND p.date < ?2",
+    )?;
+
+    let rows = stmt
+        .query_map(params![start_date, end_date], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !attribute_recipes {
+        return Ok(rows);
+    }
+
+    let expander = RecipeExpander::load(conn)?;
+    Ok(rows
+        .into_iter()
+        .flat_map(|(beverage_id, amount)| expander.expand(beverage_id, amount))
+        .collect())
+}
+
+/// 期間内のカテゴリー別の飲酒量と純アルコール摂取量を集計する
+///
+/// rollupがtrueの場合、子カテゴリーの集計を最上位の親カテゴリーにまとめる。
+/// attribute_recipesがtrueの場合、カクテル等はベースのお酒のカテゴリーに振り分ける。
+pub fn category_intake(
+    conn: &Connection,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    rollup: bool,
+    attribute_recipes: bool,
+) -> Result<Vec<CategoryAlcoholIntake>, AppError> {
+    let categories = load_category_nodes(conn)?;
+    let beverages = load_beverage_nodes(conn)?;
+
+    let mut totals: HashMap<i64, (f64, f64)> = HashMap::new();
+    for (beverage_id, amount) in consumed_amounts(conn, start_date, end_date, attribute_recipes)? {
+        let Some(beverage) = beverages.get(&beverage_id) else {
+            continue;
+        };
+        let category_id = if rollup {
+            root_category(&categories, beverage.category_id)
+        } else {
+            beverage.category_id
+        };
+        let total = totals.entry(category_id).or_insert((0.0, 0.0));
+        total.0 += amount;
+        total.1 += pure_alcohol(amount, beverage.alcohol_content);
+    }
+
+    let mut result: Vec<CategoryAlcoholIntake> = totals
+        .into_iter()
+        .filter_map(|(category_id, (total_amount, total_intake))| {
+            categories.get(&category_id).map(|node| CategoryAlcoholIntake {
+                category_id,
+                category_name: node.name.clone(),
+                parent_id: node.parent_id,
+                total_amount,
+                total_intake,
+            })
+        })
+        .collect();
+
+    result.sort_by_key(|intake| {
+        (
+            categories.get(&intake.category_id).map(|node| node.display_order),
+            intake.category_id,
+        )
+    });
+
+    Ok(result)
+}
+
+/// 期間内のお酒別の飲酒量と純アルコール摂取量を摂取量の多い順に集計する
+pub fn beverage_intake(
+    conn: &Connection,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    attribute_recipes: bool,
+) -> Result<Vec<BeverageAlcoholIntake>, AppError> {
+    let categories = load_category_nodes(conn)?;
+    let beverages = load_beverage_nodes(conn)?;
+
+    let mut totals: HashMap<i64, (f64, f64)> = HashMap::new();
+    for (beverage_id, amount) in consumed_amounts(conn, start_date, end_date, attribute_recipes)? {
+        let Some(beverage) = beverages.get(&beverage_id) else {
+            continue;
+        };
+        let total = totals.entry(beverage_id).or_insert((0.0, 0.0));
+        total.0 += amount;
+        total.1 += pure_alcohol(amount, beverage.alcohol_content);
+    }
+
+    let mut result: Vec<BeverageAlcoholIntake> = totals
+        .into_iter()
+        .filter_map(|(beverage_id, (total_amount, total_intake))| {
+            let beverage = beverages.get(&beverage_id)?;
+            Some(BeverageAlcoholIntake {
+                beverage_id,
+                beverage_name: beverage.name.clone(),
+                category_id: beverage.category_id,
+                category_name: categories
+                    .get(&beverage.category_id)
+                    .map(|node| node.name.clone())
+                    .unwrap_or_default(),
+                total_amount,
+                total_intake,
+            })
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        b.total_intake
+            .total_cmp(&a.total_intake)
+            .then(a.beverage_id.cmp(&b.beverage_id))
+    });
+
+    Ok(result)
+}
+
+/// 飲酒日ごとの飲酒量と純アルコール摂取量を日付順に集計する（期間を省略した場合は全期間）
+pub fn daily_intake(
+    conn: &Connection,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Vec<DailyAlcoholIntake>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT
+            p.date,
+            p.id,
+            pb.amount,
+            b.alcohol_content
+        FROM posts p
+        LEFT JOIN post_beverages pb ON pb.post_id = p.id
+        LEFT JOIN beverages b ON pb.beverage_id = b.id
+        WHERE p.active = 0 AND (?1 IS NULL OR p.date >= ?1) AND (?2 IS NULL OR p.date < ?2)
+        ORDER BY p.date, p.id",
+    )?;
+
+    let rows = stmt.query_map(params![start_date, end_date], |row| {
+        Ok((
+            row.get::<_, NaiveDate>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, Option<f64>>(2)?,
+            row.get::<_, Option<f64>>(3)?,
+        ))
+    })?;
+
+    let mut result: Vec<DailyAlcoholIntake> = Vec::new();
+    let mut last_post_id = None;
+    for row in rows {
+        let (date, post_id, amount, alcohol_content) = row?;
+        if result.last().map_or(true, |day| day.date != date) {
+            result.push(DailyAlcoholIntake {
+                date,
+                post_count: 0,
+                total_amount: 0.0,
+                total_intake: 0.0,
+            });
+            last_post_id = None;
+        }
+
+        let Some(day) = result.last_mut() else {
+            continue;
+        };
+        if last_post_id != Some(post_id) {
+            day.post_count += 1;
+            last_post_id = Some(post_id);
+        }
+        if let Some(amount) = amount {
+            day.total_amount += amount;
+            day.total_intake += pure_alcohol(amount, alcohol_content);
+        }
+    }
+
+    Ok(result)
+}
+
+/// 今日（飲酒日）までの連続飲酒日数と連続休肝日数を算出する
+///
+/// 今日が飲酒日の場合は連続飲酒日数、そうでない場合は最後に飲んだ日の翌日から今日までの連続休肝日数だけを現在の日数とする
+/// （どちらか一方は必ず0になる）。
+pub fn drinking_streaks(conn: &Connection, today: NaiveDate) -> Result<DrinkingStreaks, AppError> {
+    let today_number = i64::from(today.num_days_from_ce());
+
+    let drinking_days: Vec<i64> = daily_intake(conn, None, None)?
+        .into_iter()
+        .filter(|day| day.total_intake > 0.0)
+        .map(|day| i64::from(day.date.num_days_from_ce()))
+        .filter(|number| *number <= today_number)
+        .collect();
+
+    let mut streaks = DrinkingStreaks {
+        today,
+        current_drinking_days: 0,
+        current_sober_days: 0,
+        longest_drinking_days: 0,
+        longest_sober_days: 0,
+    };
+
+    let mut run = 0;
+    let mut previous: Option<i64> = None;
+    for &number in &drinking_days {
+        match previous {
+            Some(previous) if number == previous + 1 => run += 1,
+            Some(previous) => {
+                streaks.longest_sober_days = streaks.longest_sober_days.max(number - previous - 1);
+                run = 1;
+            }
+            None => run = 1,
+        }
+        streaks.longest_drinking_days = streaks.longest_drinking_days.max(run);
+        previous = Some(number);
+    }
+
+    if let Some(last) = previous {
+        if last == today_number {
+            streaks.current_drinking_days = run;
+        } else {
+            streaks.current_sober_days = today_number - last;
+            streaks.longest_sober_days = streaks.longest_sober_days.max(streaks.current_sober_days);
+        }
+    }
+
+    Ok(streaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::settings;
+    use chrono::NaiveDateTime;
+
+    const ROLLOVER_HOUR: i64 = 4;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    /// 開始時刻から投稿日（飲酒日）を求めて、1杯飲んだ投稿を作成する
+    fn drink(conn: &Connection, started_at: &str) {
+        let started_at = at(started_at);
+        conn.execute(
+            "INSERT INTO posts (date, started_at) VALUES (?1, ?2)",
+            params![settings::drinking_day(started_at, ROLLOVER_HOUR), started_at],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO post_beverages (post_id, beverage_id, amount) VALUES (last_insert_rowid(), 1, 350)",
+            [],
+        )
+        .unwrap();
+    }
+
+    fn streaks_at(conn: &Connection, now: &str) -> DrinkingStreaks {
+        drinking_streaks(conn, settings::drinking_day(at(now), ROLLOVER_HOUR)).unwrap()
+    }
+
+    #[test]
+    fn streaks_follow_rollover_hour() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO beverages (id, name, alcohol_content, category_id)
+            VALUES (1, 'ビール', 5.0, (SELECT MIN(id) FROM categories))",
+            [],
+        )
+        .unwrap();
+        drink(conn, "2024-05-01 20:00");
+        // 切り替え時刻より前の深夜は前日（5月1日）の飲酒
+        drink(conn, "2024-05-02 01:00");
+        drink(conn, "2024-05-02 21:00");
+
+        // 5月3日の深夜はまだ5月2日（飲酒日）
+        let streaks = streaks_at(conn, "2024-05-03 03:00");
+        assert_eq!(streaks.today, NaiveDate::from_ymd_opt(2024, 5, 2).unwrap());
+        assert_eq!(streaks.current_drinking_days, 2);
+        assert_eq!(streaks.current_sober_days, 0);
+
+        // 切り替え時刻を過ぎると5月3日（まだ飲んでいない）
+        let streaks = streaks_at(conn, "2024-05-03 05:00");
+        assert_eq!(streaks.current_drinking_days, 0);
+        assert_eq!(streaks.current_sober_days, 1);
+        assert_eq!(streaks.longest_drinking_days, 2);
+        assert_eq!(streaks.longest_sober_days, 1);
+
+        let streaks = streaks_at(conn, "2024-05-06 12:00");
+        assert_eq!(streaks.current_drinking_days, 0);
+        assert_eq!(streaks.current_sober_days, 4);
+        assert_eq!(streaks.longest_sober_days, 4);
+    }
+}
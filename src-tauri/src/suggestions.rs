@@ -0,0 +1,281 @@
+use crate::error::AppError;
+use crate::models::*;
+use crate::settings;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+// 履歴として参照する期間（日数）
+const HISTORY_DAYS: i64 = 365;
+// 最終利用日からの経過日数に対する減衰の時定数（日）
+const RECENCY_DECAY_DAYS: f64 = 14.0;
+// 利用回数を数える際の減衰の時定数（日）。古い記録ほど回数への寄与を小さくする
+const FREQUENCY_DECAY_DAYS: f64 = 60.0;
+// 「同じ時間帯」とみなす時刻の差（時間）
+const HOUR_WINDOW: i64 = 2;
+
+const WEIGHT_RECENCY: f64 = 1.0;
+const WEIGHT_FREQUENCY: f64 = 1.0;
+const WEIGHT_WEEKDAY: f64 = 0.5;
+const WEIGHT_TIME_OF_DAY: f64 = 0.5;
+
+struct UsageRow {
+    beverage_id: i64,
+    amount: f64,
+    date: NaiveDate,
+    days_ago: f64,
+    same_weekday: bool,
+    hour: Option<i64>,
+}
+
+#[derive(Default)]
+struct Usage {
+    count: i64,
+    last_used_date: Option<NaiveDate>,
+    last_days_ago: f64,
+    weighted_count: f64,
+    same_weekday_count: i64,
+    same_hour_count: i64,
+    // 量ごとの（回数, 最初に現れた順位, 量）。順位は新しい記録ほど小さい
+    amounts: HashMap<i64, (i64, usize, f64)>,
+}
+
+impl Usage {
+    fn score(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let count = self.count as f64;
+        WEIGHT_RECENCY * (-self.last_days_ago / RECENCY_DECAY_DAYS).exp()
+            + WEIGHT_FREQUENCY * self.weighted_count.ln_1p()
+            + WEIGHT_WEEKDAY * self.same_weekday_count as f64 / count
+            + WEIGHT_TIME_OF_DAY * self.same_hour_count as f64 / count
+    }
+
+    /// 最もよく飲む量（同数の場合は最近飲んだ量を優先する）
+    fn typical_amount(&self) -> Option<f64> {
+        self.amounts
+            .values()
+            .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))
+            .map(|(_, _, amount)| *amount)
+    }
+}
+
+/// 時刻の差を日をまたいで計算する（23時と1時の差は2時間）
+fn hour_distance(a: i64, b: i64) -> i64 {
+    let diff = (a - b).rem_euclid(24);
+    diff.min(24 - diff)
+}
+
+/// 期間内の飲酒履歴を新しい順に取得する（経過日数・曜日は日付の切り替え時刻を考慮した飲酒日で判定）
+fn load_usage_rows(conn: &Connection) -> Result<Vec<UsageRow>, AppError> {
+    let today = settings::drinking_today(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT
+            pb.beverage_id,
+            pb.amount,
+            p.date,
+            julianday(?1) - julianday(p.date) AS days_ago,
+            strftime('%w', p.date) = strftime('%w', ?1) AS same_weekday,
+            CAST(strftime('%H', COALESCE(pb.consumed_at, p.started_at, pb.created_at)) AS INTEGER) AS hour
+        FROM post_beverages pb
+        INNER JOIN posts p ON pb.post_id = p.id
+        WHERE p.active = 0 AND p.date >= date(?1, ?2)
+        ORDER BY p.date DESC, pb.id DESC",
+    )?;
+
+    let rows = stmt.query_map(params![today, format!("-{} days", HISTORY_DAYS)], |row| {
+        Ok(UsageRow {
+            beverage_id: row.get(0)?,
+            amount: row.get(1)?,
+            date: row.get(2)?,
+            days_ago: row.get::<_, Option<f64>>(3)?.unwrap_or(0.0).max(0.0),
+            same_weekday: row.get::<_, Option<bool>>(4)?.unwrap_or(false),
+            hour: row.get(5)?,
+        })
+    })?;
+
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// 最近・よく・同じ曜日や時間帯に飲んだお酒ほど上位になるよう並べたおすすめを返す
+///
+/// お気に入りのお酒は履歴がなくても常に先頭に含める。履歴のないお気に入りの量はserving_mlを使う。
+pub fn suggest(
+    conn: &Connection,
+    beverages: Vec<Beverage>,
+    limit: usize,
+) -> Result<Vec<SuggestedBeverage>, AppError> {
+    let current_hour: i64 =
+        conn.query_row("SELECT CAST(strftime('%H', 'now', 'localtime') AS INTEGER)", [], |row| {
+            row.get(0)
+        })?;
+
+    let mut usages: HashMap<i64, Usage> = HashMap::new();
+    for (index, row) in load_usage_rows(conn)?.into_iter().enumerate() {
+        let usage = usages.entry(row.beverage_id).or_default();
+        if usage.count == 0 {
+            usage.last_used_date = Some(row.date);
+            usage.last_days_ago = row.days_ago;
+        }
+        usage.count += 1;
+        usage.weighted_count += (-row.days_ago / FREQUENCY_DECAY_DAYS).exp();
+        if row.same_weekday {
+            usage.same_weekday_count += 1;
+        }
+        if row.hour.is_some_and(|hour| hour_distance(hour, current_hour) <= HOUR_WINDOW) {
+            usage.same_hour_count += 1;
+        }
+
+        // 浮動小数点の誤差を避けるため0.1ml単位で同じ量とみなす
+        let key = (row.amount * 10.0).round() as i64;
+        usage.amounts.entry(key).or_insert((0, index, row.amount)).0 += 1;
+    }
+
+    let mut suggestions: Vec<SuggestedBeverage> = beverages
+        .into_iter()
+        .filter_map(|beverage| {
+            let usage = usages.remove(&beverage.id);
+            if usage.is_none() && !beverage.favorite {
+                return None;
+            }
+            let usage = usage.unwrap_or_default();
+            Some(SuggestedBeverage {
+                score: usage.score(),
+                use_count: usage.count,
+                typical_amount: usage.typical_amount().or(beverage.serving_ml),
+                last_used_date: usage.last_used_date,
+                beverage,
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| {
+        b.beverage
+            .favorite
+            .cmp(&a.beverage.favorite)
+            .then(b.score.total_cmp(&a.score))
+            .then(a.beverage.name.cmp(&b.beverage.name))
+    });
+    suggestions.truncate(limit);
+
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    /// 一覧用のお酒（おすすめの対象。DBにも同じidで登録する）
+    fn beverage(conn: &Connection, id: i64, name: &str, favorite: bool, serving_ml: Option<f64>) -> Beverage {
+        conn.execute(
+            "INSERT INTO beverages (id, name, category_id, favorite, serving_ml)
+            VALUES (?1, ?2, (SELECT MIN(id) FROM categories), ?3, ?4)",
+            params![id, name, favorite, serving_ml],
+        )
+        .unwrap();
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "category_id": 1,
+            "serving_ml": serving_ml,
+            "archived": false,
+            "favorite": favorite,
+            "is_recipe": false,
+            "attributes": [],
+        }))
+        .unwrap()
+    }
+
+    /// 今日（飲酒日）のdays_ago日前に飲んだ記録を追加する
+    fn drink(conn: &Connection, beverage_id: i64, days_ago: i64, amount: f64) {
+        let today = settings::drinking_today(conn).unwrap();
+        conn.execute(
+            "INSERT INTO posts (date) VALUES (date(?1, ?2))",
+            params![today, format!("-{} days", days_ago)],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO post_beverages (post_id, beverage_id, amount) VALUES (last_insert_rowid(), ?1, ?2)",
+            params![beverage_id, amount],
+        )
+        .unwrap();
+    }
+
+    fn ranking(suggestions: &[SuggestedBeverage]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.beverage.name.as_str()).collect()
+    }
+
+    #[test]
+    fn hour_distance_wraps_around_midnight() {
+        assert_eq!(hour_distance(23, 1), 2);
+        assert_eq!(hour_distance(1, 23), 2);
+        assert_eq!(hour_distance(20, 20), 0);
+        assert_eq!(hour_distance(0, 12), 12);
+    }
+
+    #[test]
+    fn recent_and_frequent_beverages_rank_higher() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let beverages = vec![
+            beverage(conn, 1, "ビール", false, None),
+            beverage(conn, 2, "日本酒", false, None),
+            beverage(conn, 3, "ワイン", false, None),
+            beverage(conn, 4, "ハイボール", true, Some(350.0)),
+            beverage(conn, 5, "梅酒", false, None),
+        ];
+        for days_ago in [1, 3, 5, 8, 10] {
+            drink(conn, 1, days_ago, 350.0);
+        }
+        drink(conn, 2, 2, 180.0);
+        drink(conn, 3, 200, 120.0);
+        // 履歴の期間より前の記録は数えない
+        drink(conn, 5, HISTORY_DAYS + 10, 90.0);
+
+        let suggestions = suggest(conn, beverages.clone(), 10).unwrap();
+        // お気に入りは履歴がなくても先頭（量はserving_ml）、履歴のないお酒は含めない
+        assert_eq!(ranking(&suggestions), vec!["ハイボール", "ビール", "日本酒", "ワイン"]);
+        assert_eq!(suggestions[0].use_count, 0);
+        assert_eq!(suggestions[0].typical_amount, Some(350.0));
+        assert_eq!(suggestions[1].use_count, 5);
+        let today = settings::drinking_today(conn).unwrap();
+        assert_eq!(suggestions[2].last_used_date, Some(today - chrono::Duration::days(2)));
+
+        assert_eq!(ranking(&suggest(conn, beverages, 2).unwrap()), vec!["ハイボール", "ビール"]);
+    }
+
+    #[test]
+    fn same_weekday_outranks_slightly_more_recent() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let beverages = vec![beverage(conn, 1, "ビール", false, None), beverage(conn, 2, "日本酒", false, None)];
+        drink(conn, 1, 6, 350.0);
+        drink(conn, 2, 7, 180.0);
+
+        assert_eq!(ranking(&suggest(conn, beverages, 10).unwrap()), vec!["日本酒", "ビール"]);
+    }
+
+    #[test]
+    fn typical_amount_is_most_common_then_most_recent() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        let beverages = vec![beverage(conn, 1, "ビール", false, None), beverage(conn, 2, "日本酒", false, Some(180.0))];
+        // 同数の場合は最近飲んだ量
+        for (days_ago, amount) in [(10, 350.0), (9, 350.0), (2, 500.0), (1, 500.0)] {
+            drink(conn, 1, days_ago, amount);
+        }
+        // 最も多い量（serving_mlより履歴を優先する）
+        for (days_ago, amount) in [(10, 90.0), (9, 90.0), (8, 90.0), (1, 300.0)] {
+            drink(conn, 2, days_ago, amount);
+        }
+
+        let suggestions = suggest(conn, beverages, 10).unwrap();
+        let typical_amount = |name: &str| {
+            suggestions.iter().find(|s| s.beverage.name == name).and_then(|s| s.typical_amount)
+        };
+        assert_eq!(typical_amount("ビール"), Some(500.0));
+        assert_eq!(typical_amount("日本酒"), Some(90.0));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::i18n::{self, NameTable};
+use crate::messages::ErrorCode;
+use crate::models::*;
+use crate::validation;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+fn load_template_beverages(conn: &Connection) -> Result<HashMap<i64, Vec<BeverageAmount>>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT
+            tb.template_id,
+            tb.beverage_id,
+            {},
+            tb.amount,
+            b.alcohol_content
+        FROM post_template_beverages tb
+        INNER JOIN beverages b ON tb.beverage_id = b.id
+        ORDER BY tb.display_order, tb.id",
+        i18n::localized_name_sql(NameTable::Beverages, "b")
+    ))?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            BeverageAmount {
+                beverage_id: row.get(1)?,
+                beverage_name: row.get(2)?,
+                amount: row.get(3)?,
+                alcohol_content: row.get(4)?,
+                consumed_at: None,
+            },
+        ))
+    })?;
+
+    let mut beverages: HashMap<i64, Vec<BeverageAmount>> = HashMap::new();
+    for row in rows {
+        let (template_id, beverage) = row?;
+        beverages.entry(template_id).or_default().push(beverage);
+    }
+    Ok(beverages)
+}
+
+/// テンプレート一覧を名前順で取得する
+pub fn get_templates(conn: &Connection) -> Result<Vec<PostTemplate>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, comment, tags, created_at, updated_at
+        FROM post_templates
+        ORDER BY name",
+    )?;
+
+    let templates = stmt.query_map([], |row| {
+        let tags: Option<String> = row.get(3)?;
+        Ok(PostTemplate {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            comment: row.get(2)?,
+            tags: tags
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            beverages: vec![],
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+        })
+    })?;
+    let mut templates = templates.collect::<Result<Vec<_>, _>>()?;
+
+    let mut beverages = load_template_beverages(conn)?;
+    for template in &mut templates {
+        template.beverages = beverages.remove(&template.id).unwrap_or_default();
+    }
+
+    Ok(templates)
+}
+
+pub fn get_template(conn: &Connection, id: i64) -> Result<PostTemplate, AppError> {
+    get_templates(conn)?
+        .into_iter()
+        .find(|template| template.id == id)
+        .ok_or_else(|| ErrorDetail::new(ErrorCode::TemplateNotFound).into())
+}
+
+/// テンプレートのリクエストの名前の重複を確認し、名前・コメント・タグを正規化する（入力値の検証はvalidationで行う）
+pub fn normalize_template_request(
+    conn: &Connection,
+    id: Option<i64>,
+    request: CreatePostTemplateRequest,
+) -> Result<CreatePostTemplateRequest, AppError> {
+    let name = request.name.trim().to_string();
+    let duplicate: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM post_templates WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if duplicate.is_some_and(|duplicate_id| Some(duplicate_id) != id) {
+        return Err(validation::field_error(
+            "name",
+            ErrorDetail::new(ErrorCode::TemplateNameExists).param("name", name),
+        ));
+    }
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in request.tags.unwrap_or_default() {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    Ok(CreatePostTemplateRequest {
+        name,
+        comment: request
+            .comment
+            .map(|comment| comment.trim().to_string())
+            .filter(|comment| !comment.is_empty()),
+        tags: Some(tags),
+        beverages: request.beverages,
+    })
+}
+
+/// テンプレートのお酒をまとめて置き換える
+pub fn replace_template_beverages(
+    conn: &Connection,
+    template_id: i64,
+    beverages: &[BeverageAmountInput],
+) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM post_template_beverages WHERE template_id = ?1",
+        params![template_id],
+    )?;
+
+    for (index, beverage) in beverages.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO post_template_beverages (template_id, beverage_id, amount, display_order)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![template_id, beverage.beverage_id, beverage.amount, index as i64 + 1],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// テンプレートに上書き内容を適用して投稿リクエストを組み立てる（お酒の検証は投稿時に行う）
+pub fn build_post_request(
+    template: PostTemplate,
+    date: NaiveDate,
+    overrides: PostTemplateOverrides,
+) -> Result<CreatePostRequest, AppError> {
+    let mut beverages: Vec<BeverageAmountInput> = template
+        .beverages
+        .into_iter()
+        .map(|beverage| BeverageAmountInput {
+            beverage_id: beverage.beverage_id,
+            amount: beverage.amount,
+            consumed_at: None,
+        })
+        .collect();
+
+    for input in overrides.beverages.unwrap_or_default() {
+        match beverages.iter().position(|beverage| beverage.beverage_id == input.beverage_id) {
+            Some(index) if input.amount <= 0.0 => {
+                beverages.remove(index);
+            }
+            Some(index) => {
+                beverages[index].amount = input.amount;
+                beverages[index].consumed_at = input.consumed_at;
+            }
+            None => beverages.push(input),
+        }
+    }
+
+    if beverages.is_empty() {
+        return Err(validation::field_error("beverages", ErrorDetail::new(ErrorCode::PostBeveragesRequired)));
+    }
+
+    Ok(CreatePostRequest {
+        date,
+        comment: overrides.comment.or(template.comment),
+        started_at: overrides.started_at,
+        ended_at: overrides.ended_at,
+        beverages,
+    })
+}
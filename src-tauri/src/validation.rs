@@ -0,0 +1,658 @@
+use crate::app_lock;
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::{ErrorCode, Label};
+use crate::models::*;
+use crate::settings;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+
+// 名称（お酒・カテゴリー・属性・テンプレート）の最大文字数
+const MAX_NAME_LENGTH: usize = 100;
+// コメント・メモの最大文字数
+const MAX_TEXT_LENGTH: usize = 2000;
+// 1杯（1品目）あたりの量の上限（ml）
+const MAX_AMOUNT_ML: f64 = 10_000.0;
+// 表示順の上限（並べ替えで振り直す値は件数以下になる）
+const MAX_DISPLAY_ORDER: i64 = 10_000;
+const MAX_HANGOVER_SEVERITY: i64 = 5;
+const MIN_SCORE: i64 = 1;
+const MAX_SCORE: i64 = 5;
+const MAX_BODY_WEIGHT_KG: f64 = 500.0;
+
+/// 検証エラーを項目ごとに収集する
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<ErrorDetail>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, error: ErrorDetail) {
+        self.errors.push(error.field(field));
+    }
+
+    fn has_error(&self, field: &str) -> bool {
+        self.errors
+            .iter()
+            .any(|error| error.field.as_deref() == Some(field))
+    }
+
+    /// 必須の名称（前後の空白を除いて1文字以上、最大文字数以内）
+    pub fn name(&mut self, field: &str, value: &str, label: Label) {
+        let value = value.trim();
+        if value.is_empty() {
+            self.add(field, ErrorDetail::new(ErrorCode::Required).label(label));
+        } else if value.chars().count() > MAX_NAME_LENGTH {
+            self.add(
+                field,
+                ErrorDetail::new(ErrorCode::TooLong).label(label).param("max", MAX_NAME_LENGTH),
+            );
+        }
+    }
+
+    /// 任意入力のテキスト（コメント・メモ）
+    pub fn text(&mut self, field: &str, value: Option<&str>, label: Label) {
+        if value.is_some_and(|value| value.trim().chars().count() > MAX_TEXT_LENGTH) {
+            self.add(
+                field,
+                ErrorDetail::new(ErrorCode::TooLong).label(label).param("max", MAX_TEXT_LENGTH),
+            );
+        }
+    }
+
+    /// 飲んだ量・材料の量（0より大きく上限以下のml）
+    pub fn amount(&mut self, field: &str, value: f64, label: Label) {
+        if !(value.is_finite() && value > 0.0) {
+            self.add(field, ErrorDetail::new(ErrorCode::MustBePositive).label(label));
+        } else if value > MAX_AMOUNT_ML {
+            self.add(
+                field,
+                ErrorDetail::new(ErrorCode::TooLarge).label(label).param("max", MAX_AMOUNT_ML),
+            );
+        }
+    }
+
+    /// 任意入力の量（容量・1杯の量）
+    pub fn optional_amount(&mut self, field: &str, value: Option<f64>, label: Label) {
+        if let Some(value) = value {
+            self.amount(field, value, label);
+        }
+    }
+
+    /// アルコール度数（0〜100%）
+    pub fn alcohol_content(&mut self, field: &str, value: Option<f64>) {
+        if value.is_some_and(|value| !(value.is_finite() && (0.0..=100.0).contains(&value))) {
+            self.add(field, ErrorDetail::new(ErrorCode::AlcoholContentRange));
+        }
+    }
+
+    /// 表示順（0〜上限）
+    pub fn display_order(&mut self, field: &str, value: Option<i64>) {
+        if value.is_some_and(|value| !(0..=MAX_DISPLAY_ORDER).contains(&value)) {
+            self.add(
+                field,
+                ErrorDetail::new(ErrorCode::OutOfRange)
+                    .label(Label::DisplayOrder)
+                    .param("min", 0)
+                    .param("max", MAX_DISPLAY_ORDER),
+            );
+        }
+    }
+
+    /// 整数のスコアが範囲内か
+    pub fn score(&mut self, field: &str, value: Option<i64>, min: i64, max: i64, label: Label) {
+        if value.is_some_and(|value| !(min..=max).contains(&value)) {
+            self.add(
+                field,
+                ErrorDetail::new(ErrorCode::OutOfRange)
+                    .label(label)
+                    .param("min", min)
+                    .param("max", max),
+            );
+        }
+    }
+
+    /// IDの一覧に重複がないか（2件目以降の重複した項目にエラーを付ける）
+    pub fn unique_ids(
+        &mut self,
+        ids: impl IntoIterator<Item = i64>,
+        field: impl Fn(usize) -> String,
+        code: ErrorCode,
+    ) {
+        let mut seen = HashSet::new();
+        for (index, id) in ids.into_iter().enumerate() {
+            if !seen.insert(id) {
+                self.add(field(index), ErrorDetail::new(code));
+            }
+        }
+    }
+
+    /// 指定したIDの行がテーブルに存在するか
+    pub fn exists(
+        &mut self,
+        conn: &Connection,
+        table: &str,
+        id: i64,
+        field: &str,
+        code: ErrorCode,
+    ) -> Result<(), AppError> {
+        if self.has_error(field) {
+            return Ok(());
+        }
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE id = ?1", table),
+            params![id],
+            |row| row.get(0),
+        )?;
+        if count == 0 {
+            self.add(field, ErrorDetail::new(code));
+        }
+        Ok(())
+    }
+
+    /// 収集したエラーがあればまとめて返す
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+}
+
+/// 1項目のみの検証エラー
+pub fn field_error(field: &str, error: ErrorDetail) -> AppError {
+    AppError::Validation(vec![error.field(field)])
+}
+
+/// コマンドの入力として受け取るリクエストの検証
+pub trait Validate {
+    fn validate(&self, conn: &Connection, v: &mut Validator) -> Result<(), AppError>;
+}
+
+/// リクエストを検証し、エラーがあれば項目ごとのエラーとして返す
+pub fn validate<T: Validate + ?Sized>(conn: &Connection, request: &T) -> Result<(), AppError> {
+    let mut v = Validator::new();
+    request.validate(conn, &mut v)?;
+    v.finish()
+}
+
+/// 飲んだお酒の一覧（量は正の数、同じお酒の重複なし、お酒が存在すること）
+fn validate_beverage_amounts(
+    conn: &Connection,
+    v: &mut Validator,
+    beverages: &[BeverageAmountInput],
+) -> Result<(), AppError> {
+    for (index, beverage) in beverages.iter().enumerate() {
+        v.amount(&format!("beverages[{}].amount", index), beverage.amount, Label::Amount);
+    }
+    v.unique_ids(
+        beverages.iter().map(|beverage| beverage.beverage_id),
+        |index| format!("beverages[{}].beverage_id", index),
+        ErrorCode::DuplicateBeverage,
+    );
+    for (index, beverage) in beverages.iter().enumerate() {
+        v.exists(
+            conn,
+            "beverages",
+            beverage.beverage_id,
+            &format!("beverages[{}].beverage_id", index),
+            ErrorCode::BeverageNotFound,
+        )?;
+    }
+    Ok(())
+}
+
+impl Validate for CreatePostRequest {
+    fn validate(&self, conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.text("comment", self.comment.as_deref(), Label::Comment);
+        validate_beverage_amounts(conn, v, &self.beverages)
+    }
+}
+
+impl Validate for CreatePostTemplateRequest {
+    fn validate(&self, conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.name("name", &self.name, Label::TemplateName);
+        v.text("comment", self.comment.as_deref(), Label::Comment);
+        if self.beverages.is_empty() {
+            v.add("beverages", ErrorDetail::new(ErrorCode::TemplateBeveragesRequired));
+        }
+        validate_beverage_amounts(conn, v, &self.beverages)
+    }
+}
+
+impl Validate for PostTemplateOverrides {
+    fn validate(&self, _conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.text("comment", self.comment.as_deref(), Label::Comment);
+        // 0以下の量はテンプレートからの除外を表すため、数値であることのみ確認する
+        for (index, beverage) in self.beverages.iter().flatten().enumerate() {
+            if !beverage.amount.is_finite() {
+                v.add(
+                    format!("beverages[{}].amount", index),
+                    ErrorDetail::new(ErrorCode::NotANumber).label(Label::Amount),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for CreateBeverageRequest {
+    fn validate(&self, conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.name("name", &self.name, Label::BeverageName);
+        v.alcohol_content("alcohol_content", self.alcohol_content);
+        v.optional_amount("volume_ml", self.volume_ml, Label::Volume);
+        v.optional_amount("serving_ml", self.serving_ml, Label::Serving);
+        v.text("notes", self.notes.as_deref(), Label::Notes);
+        if let Some(attributes) = &self.attributes {
+            v.unique_ids(
+                attributes.iter().map(|attribute| attribute.attribute_id),
+                |index| format!("attributes[{}].attribute_id", index),
+                ErrorCode::DuplicateAttribute,
+            );
+        }
+        v.exists(
+            conn,
+            "categories",
+            self.category_id,
+            "category_id",
+            ErrorCode::CategoryNotFound,
+        )
+    }
+}
+
+impl Validate for CreateCategoryRequest {
+    fn validate(&self, conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.name("name", &self.name, Label::CategoryName);
+        v.display_order("display_order", self.display_order);
+        if let Some(parent_id) = self.parent_id {
+            v.exists(
+                conn,
+                "categories",
+                parent_id,
+                "parent_id",
+                ErrorCode::ParentCategoryNotFound,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Validate for CreateCategoryAttributeRequest {
+    fn validate(&self, _conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.name("name", &self.name, Label::AttributeName);
+        v.display_order("display_order", self.display_order);
+        for (index, value) in self.allowed_values.iter().flatten().enumerate() {
+            if value.trim().chars().count() > MAX_NAME_LENGTH {
+                v.add(
+                    format!("allowed_values[{}]", index),
+                    ErrorDetail::new(ErrorCode::TooLong)
+                        .label(Label::AllowedValue)
+                        .param("max", MAX_NAME_LENGTH),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for [RecipeIngredientInput] {
+    fn validate(&self, conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        for (index, ingredient) in self.iter().enumerate() {
+            v.amount(&format!("ingredients[{}].amount", index), ingredient.amount, Label::IngredientAmount);
+        }
+        v.unique_ids(
+            self.iter().map(|ingredient| ingredient.ingredient_id),
+            |index| format!("ingredients[{}].ingredient_id", index),
+            ErrorCode::DuplicateIngredient,
+        );
+        for (index, ingredient) in self.iter().enumerate() {
+            v.exists(
+                conn,
+                "beverages",
+                ingredient.ingredient_id,
+                &format!("ingredients[{}].ingredient_id", index),
+                ErrorCode::IngredientNotFound,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Validate for SaveWellbeingLogRequest {
+    fn validate(&self, _conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.score(
+            "hangover_severity",
+            self.hangover_severity,
+            0,
+            MAX_HANGOVER_SEVERITY,
+            Label::HangoverSeverity,
+        );
+        v.score("sleep_quality", self.sleep_quality, MIN_SCORE, MAX_SCORE, Label::SleepQuality);
+        v.score("mood", self.mood, MIN_SCORE, MAX_SCORE, Label::Mood);
+        v.text("notes", self.notes.as_deref(), Label::Notes);
+        Ok(())
+    }
+}
+
+impl Validate for SetAppLockPinRequest {
+    fn validate(&self, _conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        if (self.pin.chars().count() as i64) < app_lock::MIN_PIN_LENGTH {
+            v.add(
+                "pin",
+                ErrorDetail::new(ErrorCode::TooShort)
+                    .label(Label::Pin)
+                    .param("min", app_lock::MIN_PIN_LENGTH),
+            );
+        }
+        v.score(
+            "idle_timeout_minutes",
+            self.idle_timeout_minutes,
+            1,
+            app_lock::MAX_IDLE_TIMEOUT_MINUTES,
+            Label::IdleTimeout,
+        );
+        Ok(())
+    }
+}
+
+impl Validate for UpdateSettingsRequest {
+    fn validate(&self, _conn: &Connection, v: &mut Validator) -> Result<(), AppError> {
+        v.score(
+            "day_rollover_hour",
+            self.day_rollover_hour,
+            0,
+            settings::MAX_DAY_ROLLOVER_HOUR,
+            Label::DayRolloverHour,
+        );
+        v.score(
+            "backup_keep_daily",
+            self.backup_keep_daily,
+            1,
+            settings::MAX_BACKUP_KEEP_DAILY,
+            Label::BackupKeepDaily,
+        );
+        v.score(
+            "backup_keep_monthly",
+            self.backup_keep_monthly,
+            1,
+            settings::MAX_BACKUP_KEEP_MONTHLY,
+            Label::BackupKeepMonthly,
+        );
+        if self
+            .body_weight_kg
+            .is_some_and(|weight| !(weight.is_finite() && weight > 0.0 && weight < MAX_BODY_WEIGHT_KG))
+        {
+            v.add("body_weight_kg", ErrorDetail::new(ErrorCode::BodyWeightRange));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates;
+    use crate::db::Database;
+
+    /// 検証エラーの項目とコード（項目の順）
+    fn errors<T: Validate + ?Sized>(conn: &Connection, request: &T) -> Vec<(String, ErrorCode)> {
+        match validate(conn, request) {
+            Ok(()) => vec![],
+            Err(AppError::Validation(errors)) => errors
+                .into_iter()
+                .map(|error| (error.field.unwrap_or_default(), error.code))
+                .collect(),
+            Err(error) => panic!("検証エラー以外のエラーになりました: {:?}", error),
+        }
+    }
+
+    fn field(name: &str, code: ErrorCode) -> (String, ErrorCode) {
+        (name.to_string(), code)
+    }
+
+    fn database_with_beverage() -> Database {
+        let db = Database::open_in_memory();
+        db.conn()
+            .execute(
+                "INSERT INTO beverages (id, name, category_id)
+                VALUES (1, 'ビール', (SELECT MIN(id) FROM categories))",
+                [],
+            )
+            .unwrap();
+        db
+    }
+
+    fn beverage_request(value: serde_json::Value) -> CreateBeverageRequest {
+        let mut request = serde_json::json!({"name": "ビール", "category_id": 1});
+        request
+            .as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn post_beverages_report_indexed_fields() {
+        let db = database_with_beverage();
+        let amount = |beverage_id, amount| BeverageAmountInput {
+            beverage_id,
+            amount,
+            consumed_at: None,
+        };
+        let request = CreatePostRequest {
+            date: "2024-05-01".parse().unwrap(),
+            comment: Some("あ".repeat(MAX_TEXT_LENGTH + 1)),
+            started_at: None,
+            ended_at: None,
+            beverages: vec![
+                amount(1, 350.0),
+                amount(1, -5.0),
+                amount(999, 0.0),
+                amount(1, MAX_AMOUNT_ML + 1.0),
+            ],
+        };
+
+        // 量・重複の誤りがある項目は、お酒の存在を確認しない
+        assert_eq!(
+            errors(db.conn(), &request),
+            vec![
+                field("comment", ErrorCode::TooLong),
+                field("beverages[1].amount", ErrorCode::MustBePositive),
+                field("beverages[2].amount", ErrorCode::MustBePositive),
+                field("beverages[3].amount", ErrorCode::TooLarge),
+                field("beverages[1].beverage_id", ErrorCode::DuplicateBeverage),
+                field("beverages[3].beverage_id", ErrorCode::DuplicateBeverage),
+                field("beverages[2].beverage_id", ErrorCode::BeverageNotFound),
+            ]
+        );
+
+        let request = CreatePostRequest {
+            comment: None,
+            beverages: vec![amount(1, MAX_AMOUNT_ML)],
+            ..request
+        };
+        assert_eq!(errors(db.conn(), &request), vec![]);
+    }
+
+    #[test]
+    fn recipe_ingredients_report_indexed_fields() {
+        let db = database_with_beverage();
+        let ingredient = |ingredient_id, amount| RecipeIngredientInput {
+            ingredient_id,
+            amount,
+        };
+        let ingredients = [
+            ingredient(1, 30.0),
+            ingredient(1, f64::NAN),
+            ingredient(999, 90.0),
+        ];
+
+        assert_eq!(
+            errors(db.conn(), &ingredients[..]),
+            vec![
+                field("ingredients[1].amount", ErrorCode::MustBePositive),
+                field("ingredients[1].ingredient_id", ErrorCode::DuplicateIngredient),
+                field("ingredients[2].ingredient_id", ErrorCode::IngredientNotFound),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_attributes_are_field_errors() {
+        let db = database_with_beverage();
+        let request = beverage_request(serde_json::json!({
+            "attributes": [
+                {"attribute_id": 1, "value": "山田錦"},
+                {"attribute_id": 2, "value": 60},
+                {"attribute_id": 1, "value": "雄町"},
+            ],
+        }));
+        assert_eq!(
+            errors(db.conn(), &request),
+            vec![field("attributes[2].attribute_id", ErrorCode::DuplicateAttribute)]
+        );
+    }
+
+    #[test]
+    fn names_must_be_present_and_within_length() {
+        let db = database_with_beverage();
+        let category = |name: String| CreateCategoryRequest {
+            name,
+            display_order: None,
+            parent_id: None,
+        };
+
+        assert_eq!(
+            errors(db.conn(), &category("  ".to_string())),
+            vec![field("name", ErrorCode::Required)]
+        );
+        assert_eq!(errors(db.conn(), &category("a".repeat(MAX_NAME_LENGTH))), vec![]);
+        // 文字数はバイト数ではなく文字で数える
+        assert_eq!(errors(db.conn(), &category("酒".repeat(MAX_NAME_LENGTH))), vec![]);
+
+        let Err(AppError::Validation(errors)) =
+            validate(db.conn(), &category("酒".repeat(MAX_NAME_LENGTH + 1)))
+        else {
+            panic!("長すぎる名前が検証エラーになりませんでした");
+        };
+        assert_eq!(errors[0].code, ErrorCode::TooLong);
+        assert_eq!(errors[0].params["max"], MAX_NAME_LENGTH);
+        assert_eq!(
+            errors[0].params["label"],
+            serde_json::to_value(Label::CategoryName).unwrap()
+        );
+    }
+
+    #[test]
+    fn numbers_must_be_within_range() {
+        let db = database_with_beverage();
+        let conn = db.conn();
+
+        for alcohol_content in [-0.1, 100.1, f64::NAN, f64::INFINITY] {
+            let request = beverage_request(serde_json::json!({}));
+            let request = CreateBeverageRequest {
+                alcohol_content: Some(alcohol_content),
+                ..request
+            };
+            assert_eq!(
+                errors(conn, &request),
+                vec![field("alcohol_content", ErrorCode::AlcoholContentRange)]
+            );
+        }
+        let request = beverage_request(
+            serde_json::json!({"alcohol_content": 100.0, "volume_ml": 0.0, "category_id": 999}),
+        );
+        assert_eq!(
+            errors(conn, &request),
+            vec![
+                field("volume_ml", ErrorCode::MustBePositive),
+                field("category_id", ErrorCode::CategoryNotFound)
+            ]
+        );
+
+        let category = |display_order| CreateCategoryRequest {
+            name: "ビール".to_string(),
+            display_order,
+            parent_id: None,
+        };
+        assert_eq!(errors(conn, &category(Some(0))), vec![]);
+        assert_eq!(errors(conn, &category(Some(MAX_DISPLAY_ORDER))), vec![]);
+        for display_order in [-1, MAX_DISPLAY_ORDER + 1] {
+            assert_eq!(
+                errors(conn, &category(Some(display_order))),
+                vec![field("display_order", ErrorCode::OutOfRange)]
+            );
+        }
+        let request = CreateCategoryRequest {
+            parent_id: Some(999),
+            ..category(None)
+        };
+        assert_eq!(
+            errors(conn, &request),
+            vec![field("parent_id", ErrorCode::ParentCategoryNotFound)]
+        );
+
+        let request = SaveWellbeingLogRequest {
+            date: "2024-05-01".parse().unwrap(),
+            hangover_severity: Some(0),
+            sleep_quality: Some(0),
+            mood: Some(MAX_SCORE + 1),
+            notes: None,
+        };
+        assert_eq!(
+            errors(conn, &request),
+            vec![
+                field("sleep_quality", ErrorCode::OutOfRange),
+                field("mood", ErrorCode::OutOfRange)
+            ]
+        );
+
+        let request = UpdateSettingsRequest {
+            day_rollover_hour: Some(settings::MAX_DAY_ROLLOVER_HOUR + 1),
+            body_weight_kg: Some(0.0),
+            backup_keep_daily: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(conn, &request),
+            vec![
+                field("day_rollover_hour", ErrorCode::OutOfRange),
+                field("backup_keep_daily", ErrorCode::OutOfRange),
+                field("body_weight_kg", ErrorCode::BodyWeightRange),
+            ]
+        );
+    }
+
+    #[test]
+    fn dates_must_exist() {
+        for value in ["2024-02-30", "2024/05/01", "yesterday", ""] {
+            let detail = dates::parse_date(value).unwrap_err().detail();
+            assert_eq!(detail.code, ErrorCode::InvalidDate, "{}", value);
+            assert_eq!(detail.params["value"], value);
+        }
+        assert_eq!(
+            dates::parse_date(" 2024-02-29 ").unwrap(),
+            "2024-02-29".parse::<chrono::NaiveDate>().unwrap()
+        );
+
+        let detail = dates::parse_datetime("2024-05-01 25:00", Label::StartedAt)
+            .unwrap_err()
+            .detail();
+        assert_eq!(detail.code, ErrorCode::InvalidDatetime);
+        assert_eq!(
+            detail.params["label"],
+            serde_json::to_value(Label::StartedAt).unwrap()
+        );
+        assert!(dates::parse_datetime("2024-05-01T21:30", Label::StartedAt).is_ok());
+
+        // 日付の型の項目は、コマンドの引数を受け取る時点で検証する
+        let request = serde_json::from_value::<CreatePostRequest>(serde_json::json!({
+            "date": "2024-13-40", "comment": null, "started_at": null, "ended_at": null, "beverages": [],
+        }));
+        assert!(request.is_err());
+    }
+}
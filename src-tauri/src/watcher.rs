@@ -0,0 +1,117 @@
+use crate::error::AppError;
+use crate::pool::DbPool;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+// 外部からの書き込みを検出した際にフロントエンドへ送るイベント
+pub const DATABASE_CHANGED_EVENT: &str = "database_changed";
+
+// 一連の書き込み（データベースファイル・WALファイルの更新）をまとめて1回の通知にするための待ち時間
+const DEBOUNCE: Duration = Duration::from_millis(500);
+// データベースの場所が変わっていないか確認する間隔（ファイルの変更がない間）
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// データベースファイルを監視し、アプリ以外（sqlite3コマンドなど）から書き込まれた場合にon_changeを呼び出す
+///
+/// WALファイル（-wal）・共有メモリファイル（-shm）も更新されるため、ファイルのあるディレクトリを監視する。
+/// アプリ自身の書き込みでもファイルは更新されるため、通知する前にDbPool::has_external_changesで確認する。
+/// プロファイルの切り替えでデータベースの場所が変わった場合は、監視するディレクトリも変更する。
+pub fn watch<F>(pool: DbPool, on_change: F) -> Result<(), AppError>
+where
+    F: Fn() + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(sender)
+        .map_err(|error| AppError::Internal(error.to_string()))?;
+    let mut target = None;
+    follow(&mut watcher, &mut target, pool.path().as_deref())?;
+
+    std::thread::spawn(move || {
+        loop {
+            let event = match receiver.recv_timeout(FOLLOW_INTERVAL) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            if let Err(error) = follow(&mut watcher, &mut target, pool.path().as_deref()) {
+                log::warn!("データベースファイルの監視を変更できませんでした: {}", error);
+            }
+
+            match event {
+                Some(Ok(event)) if target.as_ref().is_some_and(|target| target.matches(&event)) => {}
+                Some(Err(error)) => {
+                    log::warn!("データベースファイルの監視でエラーが発生しました: {}", error);
+                    continue;
+                }
+                _ => continue,
+            }
+
+            // 続けて届いた通知を読み捨て、書き込みが落ち着いてから確認する
+            loop {
+                match receiver.recv_timeout(DEBOUNCE) {
+                    Ok(_) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            match pool.has_external_changes() {
+                Ok(true) => {
+                    log::info!("外部からのデータベースへの書き込みを検出しました");
+                    on_change();
+                }
+                Ok(false) => {}
+                Err(error) => log::warn!("データベースの変更の確認に失敗しました: {}", error),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// 監視しているディレクトリとデータベースのファイル名
+#[derive(PartialEq)]
+struct Target {
+    dir: PathBuf,
+    file_name: String,
+}
+
+impl Target {
+    fn new(db_path: &Path) -> Result<Self, AppError> {
+        let dir = db_path
+            .parent()
+            .ok_or_else(|| AppError::Internal(format!("データベースのパスが不正です: {}", db_path.display())))?
+            .to_path_buf();
+        let file_name = db_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        Ok(Target { dir, file_name })
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        event.paths.iter().any(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().starts_with(self.file_name.as_str()))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// 監視するディレクトリを開いているデータベースの場所に合わせる
+fn follow(watcher: &mut RecommendedWatcher, target: &mut Option<Target>, db_path: Option<&Path>) -> Result<(), AppError> {
+    let next = db_path.map(Target::new).transpose()?;
+    if *target == next {
+        return Ok(());
+    }
+
+    if let Some(previous) = target.take() {
+        let _ = watcher.unwatch(&previous.dir);
+    }
+    if let Some(next) = next {
+        watcher
+            .watch(&next.dir, RecursiveMode::NonRecursive)
+            .map_err(|error| AppError::Internal(error.to_string()))?;
+        *target = Some(next);
+    }
+    Ok(())
+}
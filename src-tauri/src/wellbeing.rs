@@ -0,0 +1,450 @@
+use crate::error::{AppError, ErrorDetail};
+use crate::messages::{ErrorCode, GroupLabel, Locale};
+use crate::models::*;
+use crate::sessions;
+use crate::stats;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use std::collections::{BTreeSet, HashMap};
+
+// 相関係数を算出する最小の記録数
+const MIN_CORRELATION_SAMPLES: usize = 3;
+
+// 前日の純アルコール量の区分（上限g・キー・表示名。下限は1つ前の区分の上限で含まない）
+const INTAKE_BUCKETS: &[(f64, &str, GroupLabel)] = &[
+    (0.0, "intake_none", GroupLabel::NoDrinking),
+    (20.0, "intake_0_20", GroupLabel::IntakeAtMost),
+    (40.0, "intake_20_40", GroupLabel::IntakeBetween),
+    (60.0, "intake_40_60", GroupLabel::IntakeBetween),
+    (f64::INFINITY, "intake_over_60", GroupLabel::IntakeOver),
+];
+// 前日のペースの区分（上限g/時・キー・表示名）
+const PACE_BUCKETS: &[(f64, &str, GroupLabel)] = &[
+    (10.0, "pace_0_10", GroupLabel::PaceAtMost),
+    (20.0, "pace_10_20", GroupLabel::PaceBetween),
+    (f64::INFINITY, "pace_over_20", GroupLabel::PaceOver),
+];
+
+fn wellbeing_log_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<WellbeingLog> {
+    Ok(WellbeingLog {
+        id: row.get(0)?,
+        date: row.get(1)?,
+        hangover_severity: row.get(2)?,
+        sleep_quality: row.get(3)?,
+        mood: row.get(4)?,
+        notes: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+/// 体調の記録を日付の新しい順に取得する（期間を省略した場合は全期間、終了日は含まない）
+pub fn get_logs(
+    conn: &Connection,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> Result<Vec<WellbeingLog>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, date, hangover_severity, sleep_quality, mood, notes, created_at, updated_at
+        FROM wellbeing_logs
+        WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date < ?2)
+        ORDER BY date DESC",
+    )?;
+
+    let logs = stmt.query_map(params![start_date, end_date], wellbeing_log_from_row)?;
+
+    Ok(logs.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// 体調の記録を日付ごとに保存する（同じ日付の記録は上書きする。スコアの範囲はvalidationで検証済みとする）
+pub fn save_log(conn: &Connection, request: SaveWellbeingLogRequest) -> Result<WellbeingLog, AppError> {
+    let date = request.date;
+
+    let notes = request
+        .notes
+        .map(|notes| notes.trim().to_string())
+        .filter(|notes| !notes.is_empty());
+    if request.hangover_severity.is_none()
+        && request.sleep_quality.is_none()
+        && request.mood.is_none()
+        && notes.is_none()
+    {
+        return Err(ErrorDetail::new(ErrorCode::WellbeingEmpty).into());
+    }
+
+    conn.execute(
+        "INSERT INTO wellbeing_logs (date, hangover_severity, sleep_quality, mood, notes)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(date) DO UPDATE SET
+            hangover_severity = excluded.hangover_severity,
+            sleep_quality = excluded.sleep_quality,
+            mood = excluded.mood,
+            notes = excluded.notes,
+            updated_at = datetime('now', 'localtime')",
+        params![date, request.hangover_severity, request.sleep_quality, request.mood, notes],
+    )?;
+
+    Ok(conn.query_row(
+        "SELECT id, date, hangover_severity, sleep_quality, mood, notes, created_at, updated_at
+        FROM wellbeing_logs
+        WHERE date = ?1",
+        params![date],
+        wellbeing_log_from_row,
+    )?)
+}
+
+/// 飲酒日ごとの飲み方（純アルコール量・最も速いペース・飲んだ最上位カテゴリー）
+#[derive(Default)]
+struct DayProfile {
+    total_intake: f64,
+    max_pace: Option<f64>,
+    categories: BTreeSet<i64>,
+}
+
+fn load_day_profiles(conn: &Connection) -> Result<HashMap<NaiveDate, DayProfile>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT
+            p.date,
+            p.id,
+            {} AS duration_minutes,
+            pb.amount,
+            b.alcohol_content,
+            b.category_id
+        FROM posts p
+        INNER JOIN post_beverages pb ON pb.post_id = p.id
+        INNER JOIN beverages b ON pb.beverage_id = b.id
+        WHERE p.active = 0",
+        sessions::DURATION_MINUTES_SQL
+    ))?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, NaiveDate>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, Option<f64>>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, Option<f64>>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+
+    let roots = stats::root_categories(conn)?;
+    let mut profiles: HashMap<NaiveDate, DayProfile> = HashMap::new();
+    let mut posts: HashMap<i64, (NaiveDate, Option<f64>, f64)> = HashMap::new();
+
+    for row in rows {
+        let (day, post_id, duration_minutes, amount, alcohol_content, category_id) = row?;
+        let intake = stats::pure_alcohol(amount, alcohol_content);
+
+        let profile = profiles.entry(day).or_default();
+        profile.total_intake += intake;
+        // ノンアルコールは「ちゃんぽん」の判定に含めない
+        if intake > 0.0 {
+            let root_id = roots.get(&category_id).map_or(category_id, |(root_id, _)| *root_id);
+            profile.categories.insert(root_id);
+        }
+
+        posts.entry(post_id).or_insert((day, duration_minutes, 0.0)).2 += intake;
+    }
+
+    for (day, duration_minutes, intake) in posts.into_values() {
+        let Some(minutes) = duration_minutes.filter(|minutes| *minutes > 0.0) else {
+            continue;
+        };
+        let pace = intake / (minutes / 60.0);
+        if let Some(profile) = profiles.get_mut(&day) {
+            profile.max_pace = Some(profile.max_pace.map_or(pace, |max| max.max(pace)));
+        }
+    }
+
+    Ok(profiles)
+}
+
+/// ピアソンの相関係数（記録が少ない場合や値にばらつきがない場合はNone）
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < MIN_CORRELATION_SAMPLES {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (x, y) in pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+
+    if variance_x <= 0.0 || variance_y <= 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+fn average(values: impl Iterator<Item = Option<i64>>) -> Option<f64> {
+    let values: Vec<f64> = values.flatten().map(|value| value as f64).collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+fn average_scores(logs: &[&WellbeingLog]) -> WellbeingScores {
+    WellbeingScores {
+        hangover_severity: average(logs.iter().map(|log| log.hangover_severity)),
+        sleep_quality: average(logs.iter().map(|log| log.sleep_quality)),
+        mood: average(logs.iter().map(|log| log.mood)),
+    }
+}
+
+fn correlate(samples: &[(f64, &WellbeingLog)]) -> WellbeingScores {
+    let pairs = |score: fn(&WellbeingLog) -> Option<i64>| -> Vec<(f64, f64)> {
+        samples
+            .iter()
+            .filter_map(|(factor, log)| score(log).map(|value| (*factor, value as f64)))
+            .collect()
+    };
+
+    WellbeingScores {
+        hangover_severity: pearson(&pairs(|log| log.hangover_severity)),
+        sleep_quality: pearson(&pairs(|log| log.sleep_quality)),
+        mood: pearson(&pairs(|log| log.mood)),
+    }
+}
+
+fn group(
+    key: String,
+    range: (Option<f64>, Option<f64>),
+    label: String,
+    category_id: Option<i64>,
+    logs: &[&WellbeingLog],
+) -> WellbeingGroup {
+    WellbeingGroup {
+        key,
+        min: range.0,
+        max: range.1,
+        label,
+        category_id,
+        day_count: logs.len() as i64,
+        averages: average_scores(logs),
+    }
+}
+
+/// 範囲の区分ごとに記録をまとめる（記録のない区分は含めない）
+fn bucket_groups(
+    samples: &[(f64, &WellbeingLog)],
+    buckets: &[(f64, &str, GroupLabel)],
+) -> Vec<WellbeingGroup> {
+    let locale = Locale::current();
+    let mut lower = f64::NEG_INFINITY;
+    let mut groups = Vec::new();
+    for &(upper, key, label) in buckets {
+        let logs: Vec<&WellbeingLog> = samples
+            .iter()
+            .filter(|(value, _)| *value > lower && *value <= upper)
+            .map(|(_, log)| *log)
+            .collect();
+        if !logs.is_empty() {
+            let min = Some(lower).filter(|min| min.is_finite());
+            let max = Some(upper).filter(|max| max.is_finite());
+            groups.push(group(key.to_string(), (min, max), label.render(min, max, locale), None, &logs));
+        }
+        lower = upper;
+    }
+    groups
+}
+
+/// 体調の記録と前日（飲酒日）の飲み方との関係を分析する
+pub fn analyze(conn: &Connection) -> Result<WellbeingAnalysis, AppError> {
+    let logs = get_logs(conn, None, None)?;
+    let profiles = load_day_profiles(conn)?;
+    let roots = stats::root_categories(conn)?;
+    let empty = DayProfile::default();
+
+    let samples: Vec<(&WellbeingLog, &DayProfile)> = logs
+        .iter()
+        .filter_map(|log| {
+            let previous_day = log.date.pred_opt()?;
+            Some((log, profiles.get(&previous_day).unwrap_or(&empty)))
+        })
+        .collect();
+
+    let intake_samples: Vec<(f64, &WellbeingLog)> = samples
+        .iter()
+        .map(|(log, profile)| (profile.total_intake, *log))
+        .collect();
+    let pace_samples: Vec<(f64, &WellbeingLog)> = samples
+        .iter()
+        .filter_map(|(log, profile)| profile.max_pace.map(|pace| (pace, *log)))
+        .collect();
+
+    let mut category_ids: Vec<i64> = samples
+        .iter()
+        .flat_map(|(_, profile)| profile.categories.iter().copied())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    category_ids.sort_by_key(|category_id| {
+        std::cmp::Reverse(
+            samples
+                .iter()
+                .filter(|(_, profile)| profile.categories.contains(category_id))
+                .count(),
+        )
+    });
+    let category_groups = category_ids
+        .into_iter()
+        .map(|category_id| {
+            let logs: Vec<&WellbeingLog> = samples
+                .iter()
+                .filter(|(_, profile)| profile.categories.contains(&category_id))
+                .map(|(log, _)| *log)
+                .collect();
+            let name = roots
+                .get(&category_id)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_default();
+            group(format!("category_{}", category_id), (None, None), name, Some(category_id), &logs)
+        })
+        .collect();
+
+    // 飲んだ最上位カテゴリーの数の区分（下限は含まず、上限は含む）
+    let locale = Locale::current();
+    let mixing_groups = [
+        ("single_category", 0, 1, GroupLabel::SingleCategory),
+        ("mixed_categories", 1, usize::MAX, GroupLabel::MixedCategories),
+    ]
+    .into_iter()
+    .filter_map(|(key, lower, upper, label)| {
+        let logs: Vec<&WellbeingLog> = samples
+            .iter()
+            .filter(|(_, profile)| (lower + 1..=upper).contains(&profile.categories.len()))
+            .map(|(log, _)| *log)
+            .collect();
+        let max = Some(upper).filter(|upper| *upper != usize::MAX).map(|upper| upper as f64);
+        (!logs.is_empty()).then(|| {
+            group(key.to_string(), (Some(lower as f64), max), label.render(None, None, locale), None, &logs)
+        })
+    })
+    .collect();
+
+    Ok(WellbeingAnalysis {
+        log_count: samples.len() as i64,
+        intake_correlation: correlate(&intake_samples),
+        pace_correlation: correlate(&pace_samples),
+        intake_groups: bucket_groups(&intake_samples, INTAKE_BUCKETS),
+        pace_groups: bucket_groups(&pace_samples, PACE_BUCKETS),
+        category_groups,
+        mixing_groups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::settings;
+
+    fn log(date: &str, hangover_severity: i64) -> WellbeingLog {
+        let created_at = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        WellbeingLog {
+            id: 0,
+            date: date.parse().unwrap(),
+            hangover_severity: Some(hangover_severity),
+            sleep_quality: None,
+            mood: None,
+            notes: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    #[test]
+    fn pearson_needs_samples_and_variance() {
+        assert_eq!(pearson(&[]), None);
+        assert_eq!(pearson(&[(1.0, 2.0)]), None);
+        assert_eq!(pearson(&[(1.0, 2.0), (2.0, 3.0)]), None);
+        // どちらかの値が一定の場合は相関係数を定義できない
+        assert_eq!(pearson(&[(10.0, 1.0), (20.0, 1.0), (30.0, 1.0)]), None);
+        assert_eq!(pearson(&[(0.0, 1.0), (0.0, 2.0), (0.0, 3.0)]), None);
+
+        let positive = pearson(&[(0.0, 1.0), (20.0, 2.0), (40.0, 3.0)]).unwrap();
+        assert!((positive - 1.0).abs() < 1e-9);
+        let negative = pearson(&[(0.0, 3.0), (20.0, 2.0), (40.0, 1.0)]).unwrap();
+        assert!((negative + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bucket_upper_bounds_are_inclusive() {
+        let logs = [log("2024-05-01", 1), log("2024-05-02", 2), log("2024-05-03", 3), log("2024-05-04", 4)];
+        let samples: Vec<(f64, &WellbeingLog)> = [0.0, 20.0, 40.0, 40.1].into_iter().zip(logs.iter()).collect();
+
+        let groups = bucket_groups(&samples, INTAKE_BUCKETS);
+        let keys: Vec<&str> = groups.iter().map(|group| group.key.as_str()).collect();
+        assert_eq!(keys, ["intake_none", "intake_0_20", "intake_20_40", "intake_40_60"]);
+        assert!(groups.iter().all(|group| group.day_count == 1));
+
+        assert_eq!((groups[0].min, groups[0].max), (None, Some(0.0)));
+        assert_eq!((groups[1].min, groups[1].max), (Some(0.0), Some(20.0)));
+        assert_eq!((groups[2].min, groups[2].max), (Some(20.0), Some(40.0)));
+        assert_eq!(groups[2].averages.hangover_severity, Some(3.0));
+        assert_eq!(groups[3].averages.hangover_severity, Some(4.0));
+
+        let over = bucket_groups(&[(60.5, &logs[0])], INTAKE_BUCKETS);
+        assert_eq!((over[0].key.as_str(), over[0].min, over[0].max), ("intake_over_60", Some(60.0), None));
+    }
+
+    #[test]
+    fn previous_day_follows_rollover_hour() {
+        let db = Database::open_in_memory();
+        let conn = db.conn();
+        settings::set_value(conn, "day_rollover_hour", "5").unwrap();
+        let category_id: i64 = conn.query_row("SELECT MIN(id) FROM categories", [], |row| row.get(0)).unwrap();
+        conn.execute(
+            "INSERT INTO beverages (name, alcohol_content, category_id) VALUES ('ビール', 5.0, ?1)",
+            params![category_id],
+        )
+        .unwrap();
+        let beverage_id = conn.last_insert_rowid();
+        // 5時より前に飲み始めた投稿は前日（5月1日）の飲酒として扱う
+        conn.execute(
+            "INSERT INTO posts (date, started_at, ended_at) VALUES ('2024-05-01', '2024-05-02 01:00:00', '2024-05-02 03:00:00')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO post_beverages (post_id, beverage_id, amount) VALUES (?1, ?2, 400)",
+            params![conn.last_insert_rowid(), beverage_id],
+        )
+        .unwrap();
+
+        for (date, hangover_severity) in [("2024-05-02", 4), ("2024-05-03", 1)] {
+            save_log(
+                conn,
+                SaveWellbeingLogRequest {
+                    date: date.parse().unwrap(),
+                    hangover_severity: Some(hangover_severity),
+                    sleep_quality: None,
+                    mood: None,
+                    notes: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let analysis = analyze(conn).unwrap();
+        assert_eq!(analysis.log_count, 2);
+        // 400ml × 5% × 0.8 = 16g・2時間で8g/時（5月2日の記録の前日）、5月3日の記録の前日は飲酒なし
+        let keys: Vec<(&str, Option<f64>)> = analysis
+            .intake_groups
+            .iter()
+            .map(|group| (group.key.as_str(), group.averages.hangover_severity))
+            .collect();
+        assert_eq!(keys, [("intake_none", Some(1.0)), ("intake_0_20", Some(4.0))]);
+        assert_eq!(analysis.pace_groups[0].key, "pace_0_10");
+    }
+}
@@ -1,11 +1,18 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./assets/styles/app.scss";
 import PostForm from "./components/PostForm";
 import PostList from "./components/PostList";
 import BeverageList from "./components/BeverageList";
 import AlcoholIntakeView from "./components/AlcoholIntakeView";
 import type { PostWithBeverages, Beverage } from "./types";
+import {
+  DATABASE_CHANGED_EVENT,
+  POSTS_CHANGED_EVENT,
+  BEVERAGES_CHANGED_EVENT,
+  CATEGORIES_CHANGED_EVENT,
+} from "./types";
 
 type Tab = "posts" | "beverages" | "alcohol-intake";
 
@@ -22,6 +29,27 @@ function App() {
   const [beveragesLoading, setBeveragesLoading] = useState(true);
   const [beveragesError, setBeveragesError] = useState<string | null>(null);
 
+  // データが変更されるたびに増やし、表示中のデータを再読み込みする
+  // （他のウィンドウでの変更や、外部（sqlite3コマンドなど）からの書き換えを含む）
+  const [dataVersion, setDataVersion] = useState(0);
+
+  useEffect(() => {
+    const events = [
+      DATABASE_CHANGED_EVENT,
+      POSTS_CHANGED_EVENT,
+      BEVERAGES_CHANGED_EVENT,
+      CATEGORIES_CHANGED_EVENT,
+    ];
+    const unlisteners = events.map((event) =>
+      listen(event, () => {
+        setDataVersion((version) => version + 1);
+      })
+    );
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((f) => f()));
+    };
+  }, []);
+
   useEffect(() => {
     if (activeTab === "posts") {
       loadPosts();
@@ -29,7 +57,7 @@ function App() {
       loadBeverages();
     }
     // alcohol-intakeタブの場合はデータロードは不要（コンポーネント内で処理）
-  }, [activeTab]);
+  }, [activeTab, dataVersion]);
 
   const loadPosts = async () => {
     try {
@@ -126,7 +154,7 @@ function App() {
       {/* 飲酒量タブ */}
       {activeTab === "alcohol-intake" && (
         <div className="app--content">
-          <AlcoholIntakeView />
+          <AlcoholIntakeView key={dataVersion} />
         </div>
       )}
     </div>
@@ -72,6 +72,15 @@ export default function BeverageForm({
         name: name.trim(),
         alcohol_content: alcoholContentValue,
         category_id: categoryId,
+        // フォームで編集しない詳細情報は既存の値を引き継ぐ
+        producer: beverage?.producer,
+        country: beverage?.country,
+        region: beverage?.region,
+        style: beverage?.style,
+        volume_ml: beverage?.volume_ml,
+        barcode: beverage?.barcode,
+        notes: beverage?.notes,
+        serving_ml: beverage?.serving_ml,
       };
 
       if (beverage) {
@@ -35,6 +35,16 @@ export default function PostForm({
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
 
+  // 日付の切り替え時刻を考慮した「今日」を取得する（深夜の飲酒は前日として扱われる）
+  const loadDrinkingDay = async () => {
+    try {
+      const result = await invoke<string>("get_drinking_day");
+      setDate(result);
+    } catch (err) {
+      console.error("Error loading drinking day:", err);
+    }
+  };
+
   // 全てのお酒を読み込む（編集時にbeverage_idからBeverageオブジェクトに変換するため）
   useEffect(() => {
     const loadAllBeverages = async () => {
@@ -65,6 +75,9 @@ export default function PostForm({
               name: ba.beverage_name,
               alcohol_content: ba.alcohol_content,
               category_id: 0, // 一時的な値
+              archived: false,
+              favorite: false,
+              is_recipe: false,
             },
             amount: ba.amount,
           };
@@ -77,11 +90,7 @@ export default function PostForm({
       setSelectedBeverages(beverages);
     } else {
       // 新規作成モードの場合、フォームをリセット
-      const today = new Date();
-      const year = today.getFullYear();
-      const month = String(today.getMonth() + 1).padStart(2, "0");
-      const day = String(today.getDate()).padStart(2, "0");
-      setDate(`${year}-${month}-${day}`);
+      loadDrinkingDay();
       setComment("");
       setSelectedBeverages([]);
     }
@@ -119,9 +128,15 @@ export default function PostForm({
       const request: CreatePostRequest = {
         date: date.trim(),
         comment: comment.trim() || undefined,
+        // 編集時はフォームで扱わない開始・終了時刻、飲んだ時刻を引き継ぐ
+        started_at: editingPost?.started_at,
+        ended_at: editingPost?.ended_at,
         beverages: beveragesWithAmount.map((sb) => ({
           beverage_id: sb.beverage.id,
           amount: sb.amount,
+          consumed_at: editingPost?.beverages.find(
+            (b) => b.beverage_id === sb.beverage.id
+          )?.consumed_at,
         })),
       };
 
@@ -134,11 +149,7 @@ export default function PostForm({
         // 新規作成モード
         await invoke("create_post", { request });
         // フォームをリセット
-        const today = new Date();
-        const year = today.getFullYear();
-        const month = String(today.getMonth() + 1).padStart(2, "0");
-        const day = String(today.getDate()).padStart(2, "0");
-        setDate(`${year}-${month}-${day}`);
+        loadDrinkingDay();
         setComment("");
         setSelectedBeverages([]);
         setError(null);
@@ -2,6 +2,8 @@ export interface Category {
   id: number;
   name: string;
   display_order: number;
+  parent_id?: number;
+  archived: boolean;
   created_at?: string;
   updated_at?: string;
 }
@@ -12,15 +14,49 @@ export interface Beverage {
   alcohol_content?: number;
   category_id: number;
   category_name?: string;
+  producer?: string;
+  country?: string;
+  region?: string;
+  style?: string;
+  volume_ml?: number;
+  barcode?: string;
+  notes?: string;
+  serving_ml?: number;
+  catalog_key?: string;
+  archived: boolean;
+  favorite: boolean;
+  is_recipe: boolean;
+  attributes?: BeverageAttributeValue[];
   created_at?: string;
   updated_at?: string;
 }
 
+export type AttributeType = "text" | "number" | "integer" | "boolean" | "select";
+
+export interface CategoryAttribute {
+  id: number;
+  category_id: number;
+  name: string;
+  value_type: AttributeType;
+  unit?: string;
+  allowed_values: string[];
+  display_order: number;
+}
+
+export interface BeverageAttributeValue {
+  attribute_id: number;
+  name: string;
+  value_type: AttributeType;
+  unit?: string;
+  value: string | number | boolean | null;
+}
+
 export interface BeverageAmount {
   beverage_id: number;
   beverage_name: string;
   amount: number;
   alcohol_content?: number;
+  consumed_at?: string;
 }
 
 export interface Post {
@@ -35,20 +71,32 @@ export interface PostWithBeverages {
   id: number;
   date: string;
   comment?: string;
+  started_at?: string;
+  ended_at?: string;
   created_at: string;
   updated_at: string;
   beverages: BeverageAmount[];
+  pace: SessionPace;
+}
+
+export interface SessionPace {
+  duration_minutes?: number;
+  total_pure_alcohol: number;
+  grams_per_hour?: number;
 }
 
 export interface CreatePostRequest {
   date: string;
   comment?: string;
+  started_at?: string;
+  ended_at?: string;
   beverages: BeverageAmountInput[];
 }
 
 export interface BeverageAmountInput {
   beverage_id: number;
   amount: number;
+  consumed_at?: string;
 }
 
 export interface MonthlyAlcoholIntake {
@@ -57,3 +105,235 @@ export interface MonthlyAlcoholIntake {
   drinking_days: number;
 }
 
+
+export interface SuggestedBeverage {
+  beverage: Beverage;
+  score: number;
+  use_count: number;
+  last_used_date?: string;
+  typical_amount?: number;
+}
+
+export interface PostTemplate {
+  id: number;
+  name: string;
+  comment?: string;
+  tags: string[];
+  beverages: BeverageAmount[];
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CreatePostTemplateRequest {
+  name: string;
+  comment?: string;
+  tags?: string[];
+  beverages: BeverageAmountInput[];
+}
+
+export interface PostTemplateOverrides {
+  comment?: string;
+  started_at?: string;
+  ended_at?: string;
+  beverages?: BeverageAmountInput[];
+}
+
+export type BodySex = "male" | "female";
+
+export interface AppSettings {
+  day_rollover_hour: number;
+  body_weight_kg?: number;
+  body_sex?: BodySex;
+  language: Locale;
+  backup_keep_daily: number;
+  backup_keep_monthly: number;
+}
+
+export interface UpdateSettingsRequest {
+  day_rollover_hour?: number;
+  body_weight_kg?: number;
+  body_sex?: BodySex;
+  clear_body_profile?: boolean;
+  language?: Locale;
+  backup_keep_daily?: number;
+  backup_keep_monthly?: number;
+}
+
+export interface DailyAlcoholIntake {
+  date: string;
+  post_count: number;
+  total_amount: number;
+  total_intake: number;
+}
+
+export interface DrinkingStreaks {
+  today: string;
+  current_drinking_days: number;
+  current_sober_days: number;
+  longest_drinking_days: number;
+  longest_sober_days: number;
+}
+
+export interface SessionDrink {
+  id: number;
+  beverage_id: number;
+  beverage_name: string;
+  amount: number;
+  alcohol_content?: number;
+  consumed_at: string;
+}
+
+export interface ActiveSession {
+  post_id: number;
+  date: string;
+  comment?: string;
+  started_at: string;
+  now: string;
+  drinks: SessionDrink[];
+  total_amount: number;
+  pace: SessionPace;
+  projected_bac?: number;
+}
+
+export interface WellbeingLog {
+  id: number;
+  date: string;
+  hangover_severity?: number;
+  sleep_quality?: number;
+  mood?: number;
+  notes?: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface SaveWellbeingLogRequest {
+  date: string;
+  hangover_severity?: number;
+  sleep_quality?: number;
+  mood?: number;
+  notes?: string;
+}
+
+export interface WellbeingScores {
+  hangover_severity?: number;
+  sleep_quality?: number;
+  mood?: number;
+}
+
+// keyは区分を表す変わらない値（例: intake_20_40）。min（含まない）・max（含む）は区分の範囲
+export interface WellbeingGroup {
+  key: string;
+  min?: number;
+  max?: number;
+  label: string;
+  category_id?: number;
+  day_count: number;
+  averages: WellbeingScores;
+}
+
+export interface WellbeingAnalysis {
+  log_count: number;
+  intake_correlation: WellbeingScores;
+  pace_correlation: WellbeingScores;
+  intake_groups: WellbeingGroup[];
+  pace_groups: WellbeingGroup[];
+  category_groups: WellbeingGroup[];
+  mixing_groups: WellbeingGroup[];
+}
+
+export interface FieldError {
+  code: string;
+  params: Record<string, unknown>;
+  field: string;
+  message: string;
+}
+
+// コマンドのエラー（codeで分岐・翻訳する。messageは既定の言語で組み立てたもの）
+export interface AppError {
+  code: string;
+  params: Record<string, unknown>;
+  field: string | null;
+  message: string;
+  // codeが"validation_failed"の場合のみ
+  field_errors?: FieldError[];
+}
+
+export type Locale = "ja" | "en";
+
+export interface MessageCatalog {
+  locale: Locale;
+  errors: Record<string, string>;
+  labels: Record<string, string>;
+  groups: Record<string, string>;
+}
+
+// 外部（sqlite3コマンドなど）からデータベースが書き換えられた際にバックエンドから届くイベント
+export const DATABASE_CHANGED_EVENT = "database_changed";
+
+// 書き込みがコミットされた際にバックエンドから届くイベント（allがtrueの場合はすべてが変更された可能性がある）
+export const POSTS_CHANGED_EVENT = "posts_changed";
+export const BEVERAGES_CHANGED_EVENT = "beverages_changed";
+export const CATEGORIES_CHANGED_EVENT = "categories_changed";
+export const TEMPLATES_CHANGED_EVENT = "templates_changed";
+export const WELLBEING_CHANGED_EVENT = "wellbeing_changed";
+
+export interface DateRange {
+  from: string; // YYYY-MM-DD
+  to: string; // YYYY-MM-DD
+}
+
+export interface PostsChanged {
+  ids: number[];
+  date_range?: DateRange; // 変更前後の投稿日・飲酒日を含む範囲
+  all: boolean;
+}
+
+export interface EntitiesChanged {
+  ids: number[];
+  all: boolean;
+}
+
+export interface WellbeingChanged {
+  date_range?: DateRange;
+  all: boolean;
+}
+
+// バックアップ（起動時と1日ごとに自動作成し、設定した数（既定は直近7日分は日ごと・直近12か月分は月ごと）を残す）
+export interface BackupInfo {
+  id: string; // ファイル名
+  created_at: string;
+  size_bytes: number;
+  schema_version?: number; // 読み取れない場合は省略
+}
+
+// プロファイル（プロファイルごとに別のデータベースを使う。switch_profileで切り替える。起動時にデータベースを指定した場合はactiveがすべてfalseで、切り替えられない）
+export interface Profile {
+  id: string;
+  name: string;
+  created_at: string;
+  database_path: string;
+  active: boolean;
+}
+
+// データベースの状態（lockedは暗号化されたデータベースのパスフレーズの入力待ち。unlock_databaseで開く）
+// in_use_byは別ののみログが使用中の場合のコンピューター名（そのアプリの終了後にreopen_databaseで開き直す）
+export interface DatabaseStatus {
+  locked: boolean;
+  encrypted: boolean;
+  in_use_by?: string;
+}
+
+// 一定時間操作がなくアプリをロックした際にバックエンドから届くイベント（unlock_appでPINを入力して解除する）
+export const APP_LOCKED_EVENT = "app_locked";
+
+// アプリのロックの設定（PINが設定されていない場合はenabledがfalseで、idle_timeout_minutesは省略）
+export interface AppLockSettings {
+  enabled: boolean;
+  idle_timeout_minutes?: number;
+}
+
+export interface SetAppLockPinRequest {
+  current_pin?: string; // 既にPINが設定されている場合は必須
+  pin: string;
+  idle_timeout_minutes?: number; // 省略した場合は現在の設定（未設定の場合は5分）
+}